@@ -0,0 +1,115 @@
+use crate::template::escape_html;
+use std::collections::HashSet;
+
+fn parse_definition(trimmed: &str) -> Option<(String, String)> {
+    let rest = trimmed.strip_prefix("*[")?;
+    let (abbr, expansion) = rest.split_once("]:")?;
+    let (abbr, expansion) = (abbr.trim(), expansion.trim());
+    (!abbr.is_empty() && !expansion.is_empty()).then(|| (abbr.to_string(), expansion.to_string()))
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn boundary_before(line: &str, index: usize) -> bool {
+    line[..index]
+        .chars()
+        .next_back()
+        .map_or(true, |c| !is_word_char(c) && c != '/' && c != '-')
+}
+
+fn boundary_after(line: &str, index: usize) -> bool {
+    line[index..]
+        .chars()
+        .next()
+        .map_or(true, |c| !is_word_char(c))
+}
+
+fn replace_in_line(
+    line: &str,
+    abbreviations: &[(String, String)],
+    seen: &mut HashSet<String>,
+    expand_first_use: bool,
+) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut in_code = false;
+    let mut in_tag = false;
+    let mut index = 0;
+
+    'outer: while let Some(c) = line[index..].chars().next() {
+        match c {
+            '`' => in_code = !in_code,
+            '<' if !in_code => in_tag = true,
+            '>' if !in_code => in_tag = false,
+            _ => {}
+        }
+
+        if !in_code && !in_tag && boundary_before(line, index) {
+            for (abbr, expansion) in abbreviations {
+                let end = index + abbr.len();
+                if line[index..].starts_with(abbr.as_str()) && boundary_after(line, end) {
+                    let title = escape_html(expansion);
+                    result.push_str(&format!("<abbr title=\"{}\">{}</abbr>", title, abbr));
+                    if expand_first_use && seen.insert(abbr.clone()) {
+                        result.push_str(&format!(" ({})", expansion));
+                    }
+                    index = end;
+                    continue 'outer;
+                }
+            }
+        }
+
+        result.push(c);
+        index += c.len_utf8();
+    }
+
+    result
+}
+
+/// Applies PHP-Markdown-style `*[HTML]: HyperText Markup Language`
+/// definitions. Every use becomes an `<abbr>`; the first use in body text
+/// also spells out the expansion, since tooltips don't exist on paper.
+pub fn apply_abbreviations(markdown: &str) -> String {
+    let mut abbreviations: Vec<(String, String)> = markdown
+        .lines()
+        .filter_map(|line| parse_definition(line.trim()))
+        .collect();
+
+    if abbreviations.is_empty() {
+        return markdown.to_string();
+    }
+
+    // Prefer the longest match when abbreviations share a prefix
+    abbreviations.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+
+    let mut result = String::new();
+    let mut seen = HashSet::new();
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+
+        if parse_definition(trimmed).is_some() {
+            continue;
+        }
+
+        if in_code_block {
+            result.push_str(line);
+        } else {
+            let is_heading = trimmed.starts_with('#');
+            result.push_str(&replace_in_line(
+                line,
+                &abbreviations,
+                &mut seen,
+                !is_heading,
+            ));
+        }
+        result.push('\n');
+    }
+
+    result
+}
@@ -0,0 +1,261 @@
+use crate::template::escape_html;
+use pulldown_cmark::{CowStr, Event, Tag};
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+/// Link texts that mark a heading permalink rather than content.
+const PERMALINK_TEXTS: [&str; 7] = ["¶", "#", "§", "🔗", "⚓", "link", "permalink"];
+
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if (c == ' ' || c == '-' || c == '_') && !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+fn heading_text(events: &[Event]) -> String {
+    let mut text = String::new();
+    for event in events {
+        match event {
+            Event::End(Tag::Heading(..)) => break,
+            Event::Text(t) | Event::Code(t) => text.push_str(t),
+            _ => {}
+        }
+    }
+    text
+}
+
+fn is_permalink_text(text: &str) -> bool {
+    let text = text.trim();
+    text.is_empty() || PERMALINK_TEXTS.iter().any(|p| text.eq_ignore_ascii_case(p))
+}
+
+/// Drops self-referencing permalink anchors that sources rendered by other
+/// tools put inside headings, both as markdown (`[¶](#slug)`) and as raw
+/// HTML (`<a class="headerlink" href="#slug">¶</a>`).
+fn strip_permalinks(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut result: Vec<Event> = Vec::with_capacity(events.len());
+    let mut in_heading = false;
+    let mut index = 0;
+
+    while index < events.len() {
+        match &events[index] {
+            Event::Start(Tag::Heading(..)) => in_heading = true,
+            Event::End(Tag::Heading(..)) => in_heading = false,
+            _ => {}
+        }
+
+        if in_heading {
+            // Link start, permalink text, link end
+            let opens = match &events[index] {
+                Event::Start(Tag::Link(_, dest, _)) => dest.starts_with('#'),
+                Event::Html(html) => {
+                    let html = html.trim_start();
+                    html.starts_with("<a ") && html.contains("href=\"#")
+                }
+                _ => false,
+            };
+            if opens {
+                let end = events[index + 1..]
+                    .iter()
+                    .position(|event| match event {
+                        Event::End(Tag::Link(..)) => true,
+                        Event::Html(html) => html.trim() == "</a>",
+                        _ => false,
+                    })
+                    .map(|offset| index + 1 + offset);
+                if let Some(end) = end {
+                    let text: String = events[index + 1..end]
+                        .iter()
+                        .filter_map(|event| match event {
+                            Event::Text(text) => Some(text.as_ref()),
+                            _ => None,
+                        })
+                        .collect();
+                    if is_permalink_text(&text) {
+                        index = end + 1;
+                        continue;
+                    }
+                }
+            }
+        }
+
+        result.push(events[index].clone());
+        index += 1;
+    }
+
+    result
+}
+
+/// The `data-source` of a `<section>` wrapping a combined file.
+pub fn section_source(html: &str) -> Option<String> {
+    if !html.trim_start().starts_with("<section ") {
+        return None;
+    }
+    let start = html.find("data-source=\"")? + "data-source=\"".len();
+    let end = html[start..].find('"')?;
+    Some(html[start..start + end].replace("&amp;", "&"))
+}
+
+/// Resolves `other.md` relative to the file at `source`, without touching the
+/// filesystem.
+pub fn resolve_relative(source: &str, target: &str) -> PathBuf {
+    let mut path = PathBuf::new();
+    let joined = Path::new(source)
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(target);
+    for component in joined.components() {
+        match component {
+            Component::ParentDir => {
+                path.pop();
+            }
+            Component::Normal(part) => path.push(part),
+            _ => {}
+        }
+    }
+    path
+}
+
+/// Gives every heading an `id`. Repeated slugs (several files with an
+/// "Overview") are suffixed with their parent heading's id, then a counter,
+/// and in-document `#slug` links are resolved against the nearest parent
+/// section so they land on the matching heading. Links between combined
+/// files (`other.md`, `other.md#slug`) become jumps to that file's section
+/// or heading, and permalink anchors inside headings are dropped.
+pub fn assign_heading_ids(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let events = strip_permalinks(events);
+
+    // First pass: pick a unique id for every heading in document order
+    let mut used = HashSet::new();
+    let mut ids = Vec::new();
+    let mut scoped: HashMap<(String, String), String> = HashMap::new();
+    let mut stack: Vec<(usize, String)> = Vec::new();
+    // Heading ids by (source file, slug), first occurrence wins
+    let mut by_source: HashMap<(String, String), String> = HashMap::new();
+    let mut sources = HashSet::new();
+    let mut source = String::new();
+
+    for (index, event) in events.iter().enumerate() {
+        if let Event::Html(html) = event {
+            if let Some(found) = section_source(html) {
+                sources.insert(found.clone());
+                source = found;
+            }
+            continue;
+        }
+        let Event::Start(Tag::Heading(level, explicit, _)) = event else {
+            continue;
+        };
+        let level = *level as usize;
+        let slug = match explicit {
+            Some(id) => id.to_string(),
+            None => slugify(&heading_text(&events[index + 1..])),
+        };
+        let slug = if slug.is_empty() {
+            "section".to_string()
+        } else {
+            slug
+        };
+
+        stack.retain(|(l, _)| *l < level);
+        let parent = stack.last().map(|(_, id)| id.clone()).unwrap_or_default();
+
+        let mut id = slug.clone();
+        if used.contains(&id) && !parent.is_empty() {
+            id = format!("{}-{}", parent, slug);
+        }
+        let base = id.clone();
+        let mut counter = 2;
+        while used.contains(&id) {
+            id = format!("{}-{}", base, counter);
+            counter += 1;
+        }
+
+        used.insert(id.clone());
+        by_source
+            .entry((source.clone(), slug.clone()))
+            .or_insert_with(|| id.clone());
+        scoped.insert((parent, slug), id.clone());
+        stack.push((level, id.clone()));
+        ids.push(id);
+    }
+
+    // Second pass: emit headings with ids and rewrite anchor links
+    let mut ids = ids.into_iter();
+    let mut stack: Vec<(usize, String)> = Vec::new();
+    let mut result = Vec::with_capacity(events.len());
+    let mut source = String::new();
+
+    for event in events {
+        match event {
+            Event::Html(html) if html.trim_start().starts_with("<section ") => {
+                if let Some(found) = section_source(&html) {
+                    source = found;
+                }
+                result.push(Event::Html(html));
+            }
+            Event::Start(Tag::Heading(level, _, classes)) => {
+                let id = ids.next().unwrap_or_default();
+                let level = level as usize;
+                stack.retain(|(l, _)| *l < level);
+                stack.push((level, id.clone()));
+
+                let class = if classes.is_empty() {
+                    String::new()
+                } else {
+                    format!(" class=\"{}\"", escape_html(&classes.join(" ")))
+                };
+                result.push(Event::Html(
+                    format!("<h{} id=\"{}\"{}>", level, escape_html(&id), class).into(),
+                ));
+            }
+            Event::End(Tag::Heading(level, ..)) => {
+                result.push(Event::Html(format!("</h{}>\n", level as usize).into()));
+            }
+            Event::Start(Tag::Link(link_type, dest, title)) if dest.starts_with('#') => {
+                let target = dest[1..].to_string();
+                let resolved = stack
+                    .iter()
+                    .rev()
+                    .map(|(_, id)| id.clone())
+                    .chain(std::iter::once(String::new()))
+                    .find_map(|parent| scoped.get(&(parent, target.clone())).cloned());
+                let dest = match resolved {
+                    Some(id) => CowStr::from(format!("#{}", id)),
+                    None => dest,
+                };
+                result.push(Event::Start(Tag::Link(link_type, dest, title)));
+            }
+            Event::Start(Tag::Link(link_type, dest, title))
+                if !dest.contains("://")
+                    && dest.split('#').next().unwrap_or("").ends_with(".md") =>
+            {
+                let (file, fragment) = dest.split_once('#').unwrap_or((&*dest, ""));
+                let target = resolve_relative(&source, file);
+                let target_source = target.to_string_lossy().replace('\\', "/");
+                let resolved = if sources.contains(&target_source) {
+                    by_source
+                        .get(&(target_source, fragment.to_string()))
+                        .cloned()
+                        .or_else(|| Some(crate::section_id(&target)))
+                } else {
+                    None
+                };
+                let dest = match resolved {
+                    Some(id) => CowStr::from(format!("#{}", id)),
+                    None => dest,
+                };
+                result.push(Event::Start(Tag::Link(link_type, dest, title)));
+            }
+            other => result.push(other),
+        }
+    }
+
+    result
+}
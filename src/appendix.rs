@@ -0,0 +1,79 @@
+use crate::directives;
+use crate::frontmatter::Frontmatter;
+use crate::sections;
+use crate::strings;
+
+/// Whether a file is an appendix: `appendix: true` in its frontmatter, or
+/// its path matches one of the `[sections] appendices` patterns.
+pub fn is_appendix(relative_path: &str, frontmatter: &Frontmatter, patterns: &[String]) -> bool {
+    frontmatter
+        .get("appendix")
+        .map_or(false, |value| value.trim() == "true")
+        || patterns
+            .iter()
+            .any(|pattern| sections::glob_match(pattern, relative_path))
+}
+
+/// `A`, `B`, ..., `Z`, `AA`, `AB`, ... for the 1-based appendix `index`.
+pub fn letter(index: usize) -> String {
+    let mut index = index;
+    let mut letters = Vec::new();
+    while index > 0 {
+        index -= 1;
+        letters.push((b'A' + (index % 26) as u8) as char);
+        index /= 26;
+    }
+    letters.iter().rev().collect()
+}
+
+pub fn title(letter: &str, heading: &str) -> String {
+    format!("{} {}: {}", strings::get().appendix, letter, heading)
+}
+
+/// Numbers the headings of an appendix's (already shifted) markdown:
+/// headings at `level` become "Appendix A: ...", deeper ones "A.1", "A.1.1".
+/// Lines inside fenced code blocks are left untouched.
+pub fn number_headings(markdown: &str, letter: &str, level: usize) -> String {
+    let mut result = String::new();
+    let mut counters: Vec<usize> = Vec::new();
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        if directives::is_fence(line) {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block || directives::is_fence(line) {
+            result.push_str(line);
+            result.push('\n');
+            continue;
+        }
+
+        let hashes = line.chars().take_while(|c| *c == '#').count();
+        let text = &line[hashes..];
+        if hashes == 0 || !text.starts_with(' ') || hashes < level {
+            result.push_str(line);
+            result.push('\n');
+            continue;
+        }
+
+        let text = text.trim();
+        if hashes == level {
+            result.push_str(&format!("{} {}\n", "#".repeat(hashes), title(letter, text)));
+            continue;
+        }
+
+        let depth = hashes - level;
+        counters.resize(depth, 0);
+        counters[depth - 1] += 1;
+        let number: Vec<String> = counters.iter().map(|n| n.to_string()).collect();
+        result.push_str(&format!(
+            "{} {}.{} {}\n",
+            "#".repeat(hashes),
+            letter,
+            number.join("."),
+            text
+        ));
+    }
+
+    result
+}
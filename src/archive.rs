@@ -0,0 +1,101 @@
+use crate::tempdir::TempDir;
+use crate::warnings;
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The file type bits of a Unix mode, and their value for a symbolic link.
+const FILE_TYPE_MASK: u32 = 0o170000;
+const SYMLINK_MODE: u32 = 0o120000;
+
+pub fn is_archive(input: &Path) -> bool {
+    let name = input
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    input.is_file()
+        && (name.ends_with(".zip") || name.ends_with(".tar.gz") || name.ends_with(".tgz"))
+}
+
+/// Extracts a `.zip` or `.tar.gz` archive into a temporary directory.
+/// Symbolic and hard links are skipped, so files in the archive can't point
+/// at files elsewhere on the machine.
+pub fn extract(archive: &Path) -> Result<TempDir> {
+    let dir = TempDir::new("archive")?;
+    let file =
+        File::open(archive).with_context(|| format!("Failed to open archive: {:?}", archive))?;
+
+    let is_zip = archive
+        .extension()
+        .map_or(false, |ext| ext.eq_ignore_ascii_case("zip"));
+    let extracted = if is_zip {
+        extract_zip(file, &dir.path)
+    } else {
+        extract_tar(file, &dir.path)
+    };
+    extracted.with_context(|| format!("Failed to extract archive: {:?}", archive))?;
+
+    Ok(dir)
+}
+
+fn extract_zip(file: File, dir: &Path) -> Result<()> {
+    let mut zip = zip::ZipArchive::new(file)?;
+    for index in 0..zip.len() {
+        let mut entry = zip.by_index(index)?;
+        let Some(relative) = entry.enclosed_name().map(Path::to_path_buf) else {
+            warnings::warn(format!(
+                "Skipped archive entry outside the archive: {}",
+                entry.name()
+            ));
+            continue;
+        };
+        if entry
+            .unix_mode()
+            .map_or(false, |mode| mode & FILE_TYPE_MASK == SYMLINK_MODE)
+        {
+            warnings::warn(format!("Skipped link in archive: {}", entry.name()));
+            continue;
+        }
+
+        let path = dir.join(relative);
+        if entry.is_dir() {
+            fs::create_dir_all(&path)?;
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            io::copy(&mut entry, &mut File::create(&path)?)?;
+        }
+    }
+    Ok(())
+}
+
+fn extract_tar(file: File, dir: &Path) -> Result<()> {
+    let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(file));
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let kind = entry.header().entry_type();
+        if kind.is_symlink() || kind.is_hard_link() {
+            warnings::warn(format!("Skipped link in archive: {:?}", entry.path()?));
+            continue;
+        }
+        // unpack_in refuses entries that would land outside `dir`
+        entry.unpack_in(dir)?;
+    }
+    Ok(())
+}
+
+/// Exports usually wrap everything in one top-level folder; descend into it
+/// so that folder doesn't become an extra heading level.
+pub fn content_root(dir: &Path) -> PathBuf {
+    let entries: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default();
+
+    match entries.as_slice() {
+        [single] if single.is_dir() => content_root(single),
+        _ => dir.to_path_buf(),
+    }
+}
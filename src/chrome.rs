@@ -0,0 +1,234 @@
+use crate::exit_codes::Failure;
+use crate::{network, readiness, tempdir};
+use anyhow::{Context, Result};
+use base64::Engine;
+use headless_chrome::protocol::cdp::Emulation::{
+    MediaFeature, SetEmulatedMedia, SetScriptExecutionDisabled,
+};
+use headless_chrome::protocol::cdp::{Page, IO};
+use headless_chrome::types::PrintToPdfOptions;
+use headless_chrome::{Browser, LaunchOptions, Tab};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, Default)]
+pub struct ChromeOptions {
+    /// Profile to reuse; a fresh temporary one is used when unset
+    pub user_data_dir: Option<PathBuf>,
+    pub sandbox: bool,
+    pub window_width: u32,
+    pub javascript: bool,
+    /// Report `prefers-color-scheme: dark` to the page, so scripts that draw
+    /// diagrams or highlight code pick the document's theme
+    pub dark: bool,
+    pub policy: network::RequestPolicy,
+    pub readiness: readiness::Readiness,
+    /// How often to relaunch Chrome after a crash before giving up
+    pub retries: u32,
+}
+
+/// Bytes requested per read when streaming a PDF out of Chrome.
+const STREAM_CHUNK_SIZE: u32 = 4 * 1024 * 1024;
+
+/// A running Chrome with one tab showing the document. Fields drop in
+/// declaration order, so Chrome exits before its temporary profile is removed.
+pub struct Session {
+    _browser: Browser,
+    pub tab: Arc<Tab>,
+    _profile: Option<tempdir::TempDir>,
+}
+
+impl Session {
+    /// Starts Chrome with one blank tab set up for rendering.
+    fn launch(options: &ChromeOptions) -> Result<Self> {
+        // A fresh profile per run keeps parallel conversions from fighting over
+        // the profile lock
+        let (profile, user_data_dir) = match &options.user_data_dir {
+            Some(dir) => (None, dir.clone()),
+            None => {
+                let profile = tempdir::TempDir::new("chrome-profile")?;
+                let path = profile.path.clone();
+                (Some(profile), path)
+            }
+        };
+
+        println!("Starting Chrome for rendering...");
+        let browser = Browser::new(
+            LaunchOptions::default_builder()
+                .headless(true)
+                .sandbox(options.sandbox)
+                .user_data_dir(Some(user_data_dir))
+                .window_size(Some((options.window_width, 1123)))
+                .build()
+                .expect("Could not configure Chrome"),
+        )
+        .context(Failure::ChromeLaunch)?;
+
+        let tab = browser.new_tab().context("Failed to create new tab")?;
+
+        if !options.javascript {
+            tab.call_method(SetScriptExecutionDisabled { value: true })
+                .context("Failed to disable JavaScript")?;
+        }
+        tab.call_method(SetEmulatedMedia {
+            media: None,
+            features: Some(vec![MediaFeature {
+                name: "prefers-color-scheme".to_string(),
+                value: if options.dark { "dark" } else { "light" }.to_string(),
+            }]),
+        })
+        .context("Failed to set the color scheme")?;
+        network::enforce(&tab, options.policy.clone())?;
+
+        Ok(Session {
+            _browser: browser,
+            tab,
+            _profile: profile,
+        })
+    }
+
+    /// Loads the document and waits until it is ready to print.
+    fn load(&self, document_url: &str, options: &ChromeOptions) -> Result<()> {
+        println!("Loading HTML content...");
+        self.tab
+            .navigate_to(document_url)
+            .context("Failed to load HTML content")?;
+        self.tab
+            .wait_until_navigated()
+            .context(Failure::RenderTimeout)?;
+        readiness::wait(&self.tab, &options.readiness)
+    }
+
+    /// Whether Chrome and the tab still answer; after a crash they don't.
+    fn responds(&self) -> bool {
+        self.tab.evaluate("true", false).is_ok()
+    }
+}
+
+/// Runs `render` on the session, opening one if needed. When Chrome fails to
+/// start, the tab crashes, or the browser dies mid-render, Chrome is
+/// relaunched and `render` retried after 1, 2, 4, ... seconds. Errors while
+/// Chrome still responds are about the document and returned right away.
+pub fn with_retries<T>(
+    session: &mut Option<Session>,
+    document_url: &str,
+    options: &ChromeOptions,
+    mut render: impl FnMut(&Session) -> Result<T>,
+) -> Result<T> {
+    let mut attempt = 0;
+    loop {
+        let result = match session {
+            Some(open) => render(open),
+            None => Session::launch(options).and_then(|open| {
+                let open = session.insert(open);
+                open.load(document_url, options)?;
+                render(open)
+            }),
+        };
+
+        let error = match result {
+            Ok(value) => return Ok(value),
+            Err(error) => error,
+        };
+        let crashed = match session {
+            Some(open) => !open.responds(),
+            None => matches!(error.downcast_ref::<Failure>(), Some(Failure::ChromeLaunch)),
+        };
+        if !crashed || attempt >= options.retries {
+            return Err(error);
+        }
+
+        attempt += 1;
+        let delay = Duration::from_secs(1 << (attempt - 1));
+        eprintln!(
+            "Chrome failed: {:#}; relaunching in {}s (retry {}/{})",
+            error,
+            delay.as_secs(),
+            attempt,
+            options.retries
+        );
+        *session = None;
+        thread::sleep(delay);
+    }
+}
+
+/// Number of code blocks, tables, and images wider than the page body.
+pub fn oversized_elements(tab: &Tab) -> u64 {
+    tab.evaluate(
+        "Array.from(document.querySelectorAll('pre, table, img, svg')).filter(e => e.scrollWidth > document.body.clientWidth).length",
+        false,
+    )
+    .ok()
+    .and_then(|result| result.value)
+    .and_then(|value| value.as_u64())
+    .unwrap_or(0)
+}
+
+/// Prints the page to `path`, streaming the PDF out of Chrome in chunks
+/// (`transferMode: ReturnAsStream`) instead of receiving it as one base64
+/// string, so large documents never sit in memory whole.
+pub fn print_to_file(tab: &Tab, options: PrintToPdfOptions, path: &Path) -> Result<()> {
+    let printed = tab
+        .call_method(Page::PrintToPDF {
+            landscape: options.landscape,
+            display_header_footer: options.display_header_footer,
+            print_background: options.print_background,
+            scale: options.scale,
+            paper_width: options.paper_width,
+            paper_height: options.paper_height,
+            margin_top: options.margin_top,
+            margin_bottom: options.margin_bottom,
+            margin_left: options.margin_left,
+            margin_right: options.margin_right,
+            page_ranges: options.page_ranges,
+            header_template: options.header_template,
+            footer_template: options.footer_template,
+            prefer_css_page_size: options.prefer_css_page_size,
+            transfer_mode: Some(Page::PrintToPdfTransfer_modeOption::ReturnAsStream),
+            generate_tagged_pdf: options.generate_tagged_pdf,
+            generate_document_outline: options.generate_document_outline,
+        })
+        .context("Failed to generate PDF")?;
+
+    let file = File::create(path).with_context(|| format!("Failed to save PDF: {:?}", path))?;
+    let mut writer = BufWriter::new(file);
+
+    match printed.stream {
+        Some(handle) => {
+            loop {
+                let chunk = tab
+                    .call_method(IO::Read {
+                        handle: handle.clone(),
+                        offset: None,
+                        size: Some(STREAM_CHUNK_SIZE),
+                    })
+                    .context("Failed to read PDF stream")?;
+                // Binary streams like PDFs always come base64-encoded
+                let bytes = base64::engine::general_purpose::STANDARD
+                    .decode(&chunk.data)
+                    .context("Invalid PDF stream data")?;
+                writer.write_all(&bytes)?;
+                if chunk.eof {
+                    break;
+                }
+            }
+            tab.call_method(IO::Close { handle })
+                .context("Failed to close PDF stream")?;
+        }
+        // Older Chrome versions ignore the transfer mode and return the data inline
+        None => {
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(&printed.data)
+                .context("Invalid PDF data")?;
+            writer.write_all(&bytes)?;
+        }
+    }
+
+    writer
+        .flush()
+        .with_context(|| format!("Failed to save PDF: {:?}", path))
+}
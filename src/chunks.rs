@@ -0,0 +1,30 @@
+/// Splits the combined markdown before every `# ` chapter heading (outside
+/// code blocks) and joins `chapters_per_chunk` chapters into each chunk.
+/// Anything before the first chapter goes with the first chunk.
+pub fn split_chapters(markdown: &str, chapters_per_chunk: usize) -> Vec<String> {
+    let mut chapters: Vec<String> = vec![String::new()];
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+        if !in_code_block
+            && line.starts_with("# ")
+            && !chapters.last().map_or(true, |c| c.trim().is_empty())
+        {
+            chapters.push(String::new());
+        }
+        if let Some(chapter) = chapters.last_mut() {
+            chapter.push_str(line);
+            chapter.push('\n');
+        }
+    }
+
+    chapters
+        .chunks(chapters_per_chunk.max(1))
+        .map(|group| group.concat())
+        .filter(|chunk| !chunk.trim().is_empty())
+        .collect()
+}
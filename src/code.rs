@@ -0,0 +1,108 @@
+use crate::config::Code;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Per-language code block settings, with whether all fenced blocks are kept.
+#[derive(Debug, Default)]
+struct Settings {
+    languages: HashMap<String, Code>,
+    keep_all: bool,
+}
+
+/// Languages drawn as graphics or styled as terminal sessions and patches
+/// rather than printed as plain code, which are always kept.
+const RENDERED_LANGUAGES: &[&str] = &[
+    "abc",
+    "ansi",
+    "console",
+    "diff",
+    "geojson",
+    "patch",
+    "sh-session",
+    "shell-session",
+    "terminal",
+    "topojson",
+];
+
+static SETTINGS: RwLock<Option<Settings>> = RwLock::new(None);
+
+/// Sets the `[code.<language>]` settings for this conversion; `keep_all`
+/// prints every fenced block regardless of language.
+pub fn set(languages: HashMap<String, Code>, keep_all: bool) {
+    *SETTINGS
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(Settings {
+        languages,
+        keep_all,
+    });
+}
+
+/// The language of a code fence line (```` ```json title="x" ```` is `json`).
+pub fn fence_language(line: &str) -> &str {
+    line.trim()
+        .trim_start_matches('`')
+        .split(|c: char| c.is_whitespace() || c == '{' || c == ',')
+        .next()
+        .unwrap_or("")
+}
+
+/// Whether a fenced block in `language` is printed rather than left out.
+pub fn keeps(language: &str) -> bool {
+    if RENDERED_LANGUAGES.contains(&language) {
+        return true;
+    }
+    let settings = SETTINGS
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    settings.as_ref().map_or(false, |settings| {
+        settings.keep_all
+            || settings
+                .languages
+                .get(language)
+                .map_or(false, |code| code.keep)
+    })
+}
+
+/// `language-c++` needs its `+` escaped in a CSS class selector.
+fn css_class(language: &str) -> String {
+    let mut class = String::from("language-");
+    for c in language.chars() {
+        if !(c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            class.push('\\');
+        }
+        class.push(c);
+    }
+    class
+}
+
+/// Font size and wrapping rules for languages that set them.
+pub fn css() -> String {
+    let settings = SETTINGS
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let Some(settings) = settings.as_ref() else {
+        return String::new();
+    };
+
+    let mut languages: Vec<(&String, &Code)> = settings.languages.iter().collect();
+    languages.sort_by_key(|(language, _)| language.as_str());
+
+    let mut css = String::new();
+    for (language, code) in languages {
+        let mut rules = Vec::new();
+        if code.wrap {
+            rules.push("white-space: pre-wrap; overflow-wrap: anywhere;".to_string());
+        }
+        if let Some(size) = &code.font_size {
+            rules.push(format!("font-size: {};", size.replace(['{', '}', ';'], "")));
+        }
+        if !rules.is_empty() {
+            css.push_str(&format!(
+                "\n        pre > code.{} {{ {} }}",
+                css_class(language),
+                rules.join(" ")
+            ));
+        }
+    }
+    css
+}
@@ -0,0 +1,137 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_FILE: &str = "md2pdf.toml";
+
+/// Settings read from `md2pdf.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub hooks: Hooks,
+    pub plugins: Vec<PathBuf>,
+    pub filters: Vec<PathBuf>,
+    pub sections: Sections,
+    pub network: Network,
+    pub lint: Lint,
+    /// Overrides for generated headings and labels, e.g. `list_of_figures = "Figures"`
+    pub strings: HashMap<String, String>,
+    /// Settings per code block language, e.g. `[code.json] wrap = true`
+    pub code: HashMap<String, Code>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hooks {
+    /// Shell commands run before markdown files are collected
+    pub pre: Vec<String>,
+    /// Shell commands run after the PDF has been written
+    pub post: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sections {
+    /// Glob patterns over paths relative to the input directory, in document order
+    pub order: Vec<String>,
+    /// Leave out files that match no pattern instead of appending them
+    pub drop_unlisted: bool,
+    /// Glob patterns for files numbered as appendices (Appendix A, A.1, ...)
+    pub appendices: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Network {
+    /// URL patterns (`*` wildcards) the rendering page may fetch
+    pub allow: Vec<String>,
+    /// URL patterns the rendering page must never fetch
+    pub deny: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Code {
+    /// Print blocks of this language (fenced code is left out by default)
+    pub keep: bool,
+    /// Wrap long lines instead of letting them run off the page
+    pub wrap: bool,
+    /// CSS font size, e.g. "9pt"
+    #[serde(alias = "font-size")]
+    pub font_size: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Lint {
+    /// Longest allowed line inside code blocks, in characters
+    pub max_code_line_length: Option<usize>,
+    pub heading_case: Option<HeadingCase>,
+    /// Words to avoid in prose, matched case-insensitively
+    pub banned_words: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HeadingCase {
+    /// Only the first word capitalized
+    Sentence,
+    /// Every word capitalized except short articles, conjunctions and prepositions
+    Title,
+}
+
+/// Loads the explicit config file, or `md2pdf.toml` next to the input or in
+/// the working directory when present. Relative paths inside the config are
+/// resolved against the config file's directory.
+///
+/// With `untrusted_input` (a cloned repository or extracted archive), a config
+/// found next to the input is still read, but its hooks, plugins, and filters
+/// are dropped so converting someone else's files never runs their code.
+pub fn load(explicit: Option<&Path>, input: &Path, untrusted_input: bool) -> Result<Config> {
+    let input_dir = if input.is_dir() {
+        input
+    } else {
+        input.parent().unwrap_or(Path::new("."))
+    };
+    let path = match explicit {
+        Some(path) => Some(path.to_path_buf()),
+        None => [input_dir.join(CONFIG_FILE), PathBuf::from(CONFIG_FILE)]
+            .into_iter()
+            .find(|candidate| candidate.is_file()),
+    };
+
+    let Some(path) = path else {
+        return Ok(Config::default());
+    };
+
+    let content =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read config: {:?}", path))?;
+    let mut config: Config =
+        toml::from_str(&content).with_context(|| format!("Failed to parse config: {:?}", path))?;
+
+    if untrusted_input && explicit.is_none() && path == input_dir.join(CONFIG_FILE) {
+        let runs_code = !config.hooks.pre.is_empty()
+            || !config.hooks.post.is_empty()
+            || !config.plugins.is_empty()
+            || !config.filters.is_empty();
+        if runs_code {
+            crate::warnings::warn(format!(
+                "Ignoring hooks, plugins, and filters from {:?}; pass --trust-config to run them",
+                path
+            ));
+            config.hooks = Hooks::default();
+            config.plugins.clear();
+            config.filters.clear();
+        }
+    }
+
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    for plugin in config.plugins.iter_mut().chain(config.filters.iter_mut()) {
+        *plugin = base_dir.join(&*plugin);
+    }
+
+    println!("Using config: {:?}", path);
+    Ok(config)
+}
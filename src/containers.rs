@@ -0,0 +1,103 @@
+fn split_quote_prefix(line: &str) -> Option<(&str, &str)> {
+    if !line.trim_start().starts_with('>') {
+        return None;
+    }
+
+    let content = line.trim_start_matches(|c| c == '>' || c == ' ');
+    Some((&line[..line.len() - content.len()], content))
+}
+
+/// The author of a `> — Author` line; only an em dash marks one, so a quoted
+/// `-- option` or `--- rule` stays text.
+fn attribution(content: &str) -> Option<&str> {
+    content
+        .strip_prefix('—')
+        .map(str::trim)
+        .filter(|author| !author.is_empty())
+}
+
+/// Container names become CSS classes, so only `[A-Za-z0-9_-]` is accepted.
+fn is_container_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// `<!-- keep -->` ... `<!-- /keep -->`, the comment form of `::: keep-together`.
+pub const KEEP_START: &str = "<!-- keep -->";
+pub const KEEP_END: &str = "<!-- /keep -->";
+
+/// Expands `::: name` fenced containers into HTML wrappers and turns a
+/// trailing `> — Author` line of a blockquote into a styled attribution.
+pub fn expand_containers(markdown: &str) -> String {
+    let mut result = String::new();
+    let mut open: Vec<&str> = Vec::new();
+    let mut previous_was_quote = false;
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        if line.trim().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block || line.trim().starts_with("```") {
+            result.push_str(line);
+            result.push('\n');
+            previous_was_quote = false;
+            continue;
+        }
+
+        let trimmed = match line.trim() {
+            KEEP_START => "::: keep-together",
+            KEEP_END => ":::",
+            other => other,
+        };
+
+        if let Some(name) = trimmed.strip_prefix(":::") {
+            let name = name.trim();
+            if name.is_empty() {
+                if let Some(tag) = open.pop() {
+                    result.push_str(&format!("\n</{}>\n\n", tag));
+                    previous_was_quote = false;
+                    continue;
+                }
+            } else if is_container_name(name) {
+                let tag = if name == "pullquote" { "aside" } else { "div" };
+                result.push_str(&format!("\n<{} class=\"{}\">\n\n", tag, name));
+                open.push(tag);
+                previous_was_quote = false;
+                continue;
+            }
+        }
+
+        let quote = split_quote_prefix(line);
+        match quote {
+            Some((prefix, content)) if previous_was_quote => match attribution(content) {
+                Some(author) => {
+                    result.push_str(prefix.trim_end());
+                    result.push('\n');
+                    result.push_str(&format!(
+                        "{}<p class=\"attribution\">— {}</p>\n",
+                        prefix, author
+                    ));
+                }
+                None => {
+                    result.push_str(line);
+                    result.push('\n');
+                }
+            },
+            _ => {
+                result.push_str(line);
+                result.push('\n');
+            }
+        }
+        previous_was_quote = quote.is_some();
+    }
+
+    // Close containers left open at the end of the document
+    while let Some(tag) = open.pop() {
+        result.push_str(&format!("\n</{}>\n", tag));
+    }
+
+    result
+}
@@ -0,0 +1,94 @@
+use crate::template::escape_html;
+use clap::ValueEnum;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ChangesMode {
+    /// Render suggestions as tracked changes: insertions, deletions, comments
+    #[default]
+    Show,
+    /// Apply every suggestion and drop comments
+    Accept,
+    /// Discard every suggestion and drop comments
+    Reject,
+}
+
+/// CriticMarkup span delimiters.
+const SPANS: [(&str, &str); 5] = [
+    ("{++", "++}"),
+    ("{--", "--}"),
+    ("{~~", "~~}"),
+    ("{==", "==}"),
+    ("{>>", "<<}"),
+];
+
+fn render(open: &str, inner: &str, mode: ChangesMode) -> String {
+    match (open, mode) {
+        ("{++", ChangesMode::Show) => format!("<ins>{}</ins>", inner),
+        ("{++", ChangesMode::Accept) | ("{--", ChangesMode::Reject) => inner.to_string(),
+        ("{++", ChangesMode::Reject) | ("{--", ChangesMode::Accept) => String::new(),
+        ("{--", ChangesMode::Show) => format!("<del>{}</del>", inner),
+        ("{~~", _) => {
+            let (old, new) = inner.split_once("~>").unwrap_or((inner, ""));
+            match mode {
+                ChangesMode::Show => format!("<del>{}</del><ins>{}</ins>", old, new),
+                ChangesMode::Accept => new.to_string(),
+                ChangesMode::Reject => old.to_string(),
+            }
+        }
+        ("{==", ChangesMode::Show) => format!("<mark>{}</mark>", inner),
+        ("{==", _) => inner.to_string(),
+        ("{>>", ChangesMode::Show) => format!(
+            "<span class=\"critic-comment\">{}</span>",
+            escape_html(inner.trim())
+        ),
+        _ => String::new(),
+    }
+}
+
+fn apply_line(line: &str, mode: ChangesMode) -> String {
+    let mut result = String::new();
+    let mut rest = line;
+
+    loop {
+        // The earliest span opening on the rest of the line
+        let Some((start, open, close)) = SPANS
+            .iter()
+            .filter_map(|(open, close)| rest.find(open).map(|start| (start, *open, *close)))
+            .min_by_key(|(start, ..)| *start)
+        else {
+            break;
+        };
+        let inner_start = start + open.len();
+        let Some(end) = rest[inner_start..].find(close) else {
+            break;
+        };
+        result.push_str(&rest[..start]);
+        result.push_str(&render(open, &rest[inner_start..inner_start + end], mode));
+        rest = &rest[inner_start + end + close.len()..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Renders CriticMarkup (`{++added++}`, `{--deleted--}`, `{~~old~>new~~}`,
+/// `{==highlight==}`, `{>>comment<<}`) as tracked changes, or applies or
+/// discards the suggestions for a clean copy.
+pub fn apply_changes(markdown: &str, mode: ChangesMode) -> String {
+    let mut result = String::new();
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        if line.trim().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block || !SPANS.iter().any(|(open, _)| line.contains(open)) {
+            result.push_str(line);
+        } else {
+            result.push_str(&apply_line(line, mode));
+        }
+        result.push('\n');
+    }
+
+    result
+}
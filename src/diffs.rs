@@ -0,0 +1,109 @@
+use crate::template::escape_html;
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, Tag};
+
+/// Fence languages of unified diffs.
+const DIFF_LANGUAGES: [&str; 2] = ["diff", "patch"];
+
+/// Line backgrounds and gutters, for the light theme and then the dark one.
+pub fn css() -> String {
+    let mut css = String::from(
+        r#"
+        pre code .diff-line { display: block; margin: 0 -15px; padding: 0 15px; }
+        .diff-gutter { display: inline-block; width: 1.5em; user-select: none; opacity: 0.6; }
+        .diff-hunk { font-style: italic; }
+        .diff-header { font-weight: bold; }"#,
+    );
+    for (scope, add, remove, hunk) in [
+        ("", "#e6ffec", "#ffebe9", "#ddf4ff"),
+        (
+            "html[data-theme=\"dark\"] ",
+            "rgba(46, 160, 67, 0.2)",
+            "rgba(248, 81, 73, 0.2)",
+            "rgba(56, 139, 253, 0.15)",
+        ),
+        (
+            "section.theme-dark ",
+            "rgba(46, 160, 67, 0.2)",
+            "rgba(248, 81, 73, 0.2)",
+            "rgba(56, 139, 253, 0.15)",
+        ),
+        ("section.theme-light ", "#e6ffec", "#ffebe9", "#ddf4ff"),
+    ] {
+        css.push_str(&format!(
+            "\n        {s}.diff-add {{ background-color: {a}; }} {s}.diff-remove {{ background-color: {r}; }} {s}.diff-hunk {{ background-color: {h}; }}",
+            s = scope,
+            a = add,
+            r = remove,
+            h = hunk
+        ));
+    }
+    css
+}
+
+/// The class of a unified diff line.
+fn line_class(line: &str) -> &'static str {
+    if line.starts_with("+++ ")
+        || line.starts_with("--- ")
+        || line.starts_with("diff ")
+        || line.starts_with("index ")
+    {
+        "diff-header"
+    } else if line.starts_with("@@") {
+        "diff-hunk"
+    } else if line.starts_with('+') {
+        "diff-add"
+    } else if line.starts_with('-') {
+        "diff-remove"
+    } else {
+        "diff-context"
+    }
+}
+
+/// Puts every line of ```` ```diff ```` blocks on a green (added) or red
+/// (removed) background, with its `+`/`-` marker in a gutter of its own.
+pub fn style_diffs(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut result = Vec::with_capacity(events.len());
+    let mut in_diff = false;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(ref info))) => {
+                let language = info.split_whitespace().next().unwrap_or("");
+                in_diff = DIFF_LANGUAGES.contains(&language);
+                result.push(event);
+            }
+            Event::End(Tag::CodeBlock(_)) => {
+                in_diff = false;
+                result.push(event);
+            }
+            Event::Text(text) if in_diff => {
+                for line in text.split_inclusive('\n') {
+                    let class = line_class(line);
+                    result.push(Event::Html(
+                        format!("<span class=\"diff-line {}\">", class).into(),
+                    ));
+                    let content = match class {
+                        "diff-add" | "diff-remove" | "diff-context" if !line.trim().is_empty() => {
+                            let marker_length = line.chars().next().map_or(0, char::len_utf8);
+                            let (marker, content) = line.split_at(marker_length);
+                            result.push(Event::Html(
+                                format!(
+                                    "<span class=\"diff-gutter\">{}</span>",
+                                    escape_html(marker)
+                                )
+                                .into(),
+                            ));
+                            content
+                        }
+                        _ => line,
+                    };
+                    result.push(Event::Text(CowStr::from(content.to_string())));
+                    result.push(Event::Html("</span>".into()));
+                }
+            }
+            other => result.push(other),
+        }
+    }
+
+    result
+}
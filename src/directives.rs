@@ -0,0 +1,279 @@
+use crate::{containers, tables};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const ONLY: &str = "<!-- md2pdf:only";
+const SKIP_START: &str = "<!-- md2pdf:skip start -->";
+const SKIP_END: &str = "<!-- md2pdf:skip end -->";
+const PAGE_BREAK: &str = "<!-- md2pdf:pagebreak -->";
+const INCLUDE: &str = "<!-- md2pdf:include ";
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Comments that are md2pdf markup read by later passes, so stripping
+/// comments leaves them in place.
+const MARKERS: [&str; 4] = [
+    containers::KEEP_START,
+    containers::KEEP_END,
+    tables::WIDTHS,
+    tables::STYLE,
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum CommentMode {
+    /// Remove HTML comments from the output
+    #[default]
+    Strip,
+    /// Pass HTML comments through to the page
+    Keep,
+    /// Interpret md2pdf page break and include comments, strip the rest
+    Directives,
+}
+
+/// Applies print/web conditional markers. Content between
+/// `<!-- md2pdf:skip start -->` and `<!-- md2pdf:skip end -->` is dropped,
+/// while the body of a `<!-- md2pdf:only ... -->` comment (hidden on the web)
+/// is rendered in the PDF.
+pub fn apply_conditionals(markdown: &str) -> String {
+    let mut result = String::new();
+    let mut skipping = false;
+    let mut in_only = false;
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+
+        if skipping {
+            skipping = trimmed != SKIP_END;
+            continue;
+        }
+
+        if in_only {
+            match line.find("-->") {
+                Some(end) => {
+                    result.push_str(&line[..end]);
+                    in_only = false;
+                }
+                None => result.push_str(line),
+            }
+            result.push('\n');
+            continue;
+        }
+
+        if is_fence(line) {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block || is_fence(line) {
+            result.push_str(line);
+            result.push('\n');
+            continue;
+        }
+
+        if trimmed == SKIP_START {
+            skipping = true;
+            continue;
+        }
+
+        if let Some(body) = trimmed.strip_prefix(ONLY) {
+            match body.find("-->") {
+                Some(end) => result.push_str(body[..end].trim()),
+                None => {
+                    result.push_str(body.trim());
+                    in_only = true;
+                }
+            }
+            result.push('\n');
+            continue;
+        }
+
+        result.push_str(line);
+        result.push('\n');
+    }
+
+    result
+}
+
+/// Whether a line opens or closes a fenced code block.
+pub fn is_fence(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed.starts_with("```") || trimmed.starts_with("~~~")
+}
+
+/// The end of the first run of exactly `count` backticks in `text`, which
+/// closes a code span opened by as many.
+pub fn closing_ticks(text: &str, count: usize) -> Option<usize> {
+    let mut index = 0;
+    while let Some(start) = text[index..].find('`') {
+        let start = index + start;
+        let run = text[start..].len() - text[start..].trim_start_matches('`').len();
+        if run == count {
+            return Some(start + run);
+        }
+        index = start + run;
+    }
+    None
+}
+
+/// Removes `<!-- ... -->` comments, which may span lines, except md2pdf's own
+/// [`MARKERS`]. Fenced code blocks and code spans are left alone, and a
+/// `<!--` that is never closed is kept as text rather than swallowing the
+/// rest of the document.
+fn strip_comments(markdown: &str) -> String {
+    let mut result = String::with_capacity(markdown.len());
+    let mut in_fence = false;
+    let mut line_start = true;
+    let mut index = 0;
+
+    while index < markdown.len() {
+        let rest = &markdown[index..];
+
+        if line_start {
+            let line_end = rest.find('\n').map_or(rest.len(), |end| end + 1);
+            let fence = is_fence(&rest[..line_end]);
+            if fence {
+                in_fence = !in_fence;
+            }
+            if fence || in_fence {
+                result.push_str(&rest[..line_end]);
+                index += line_end;
+                continue;
+            }
+            line_start = false;
+        }
+
+        if rest.starts_with('`') {
+            // Code spans end on their line; unmatched backticks are plain text
+            let ticks = rest.len() - rest.trim_start_matches('`').len();
+            let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+            let end = closing_ticks(&line[ticks..], ticks).map_or(ticks, |end| ticks + end);
+            result.push_str(&rest[..end]);
+            index += end;
+            continue;
+        }
+
+        if rest.starts_with("<!--") {
+            if let Some(end) = rest[4..].find("-->") {
+                let end = 4 + end + 3;
+                if MARKERS.iter().any(|marker| rest.starts_with(marker)) {
+                    result.push_str(&rest[..end]);
+                }
+                index += end;
+                continue;
+            }
+        }
+
+        let c = rest.chars().next().unwrap_or_default();
+        result.push(c);
+        index += c.len_utf8();
+        line_start = c == '\n';
+    }
+
+    result
+}
+
+/// Applies the HTML comment policy after conditional markers were resolved.
+pub fn apply_comments(markdown: &str, mode: CommentMode) -> String {
+    match mode {
+        CommentMode::Keep => markdown.to_string(),
+        CommentMode::Strip => strip_comments(markdown),
+        CommentMode::Directives => {
+            let mut in_code_block = false;
+            let with_breaks = markdown
+                .lines()
+                .map(|line| {
+                    if is_fence(line) {
+                        in_code_block = !in_code_block;
+                    }
+                    if !in_code_block && line.trim() == PAGE_BREAK {
+                        "\n<div class=\"page-break\"></div>\n"
+                    } else {
+                        line
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            strip_comments(&with_breaks)
+        }
+    }
+}
+
+/// `path` with symlinks and `..` resolved, or an error when it lands outside
+/// `root`, so a document can only pull in files from the tree it belongs to.
+pub fn confine(root: &Path, path: &Path) -> Result<PathBuf> {
+    // The parent of a bare `doc.md` is the empty path
+    let root = if root.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        root
+    };
+    let root = root
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve: {:?}", root))?;
+    let resolved = path
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve: {:?}", path))?;
+    if !resolved.starts_with(&root) {
+        anyhow::bail!("{:?} is outside the input {:?}", path, root);
+    }
+    Ok(resolved)
+}
+
+/// Replaces `<!-- md2pdf:include path -->` lines with the referenced file,
+/// resolved relative to `base_dir`. Included files may include others, but
+/// only from within `root`.
+pub fn resolve_includes(markdown: &str, base_dir: &Path, root: &Path) -> Result<String> {
+    resolve_includes_at_depth(markdown, base_dir, root, 0)
+}
+
+fn resolve_includes_at_depth(
+    markdown: &str,
+    base_dir: &Path,
+    root: &Path,
+    depth: usize,
+) -> Result<String> {
+    if depth > MAX_INCLUDE_DEPTH {
+        anyhow::bail!("Includes nested deeper than {} levels", MAX_INCLUDE_DEPTH);
+    }
+
+    let mut result = String::new();
+
+    for line in markdown.lines() {
+        let target = line
+            .trim()
+            .strip_prefix(INCLUDE)
+            .and_then(|rest| rest.strip_suffix("-->"))
+            .map(str::trim);
+
+        match target {
+            Some(target) => {
+                let path = match confine(root, &base_dir.join(target)) {
+                    Ok(path) => path,
+                    Err(error) => {
+                        crate::warnings::warn(format!("Unresolved include: {:#}", error));
+                        result.push_str(line);
+                        result.push('\n');
+                        continue;
+                    }
+                };
+                let Ok(content) = fs::read_to_string(&path) else {
+                    crate::warnings::warn(format!("Unresolved include: {:?}", path));
+                    result.push_str(line);
+                    result.push('\n');
+                    continue;
+                };
+                let nested_base = path.parent().unwrap_or(base_dir);
+                result.push_str(&resolve_includes_at_depth(
+                    &content,
+                    nested_base,
+                    root,
+                    depth + 1,
+                )?);
+            }
+            None => result.push_str(line),
+        }
+        result.push('\n');
+    }
+
+    Ok(result)
+}
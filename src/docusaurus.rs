@@ -0,0 +1,102 @@
+use crate::FileGroups;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Whether the input looks like a Docusaurus `docs/` directory.
+pub fn detect(root: &Path) -> bool {
+    let site = root.parent().unwrap_or(Path::new("."));
+    ["docusaurus.config.js", "docusaurus.config.ts"]
+        .iter()
+        .any(|config| site.join(config).is_file())
+}
+
+#[derive(Debug, Default)]
+struct Category {
+    position: Option<f64>,
+    label: Option<String>,
+}
+
+fn category(dir: &Path) -> Category {
+    let Ok(content) = fs::read_to_string(dir.join("_category_.json")) else {
+        return Category::default();
+    };
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) else {
+        return Category::default();
+    };
+
+    Category {
+        position: json.get("position").and_then(|p| p.as_f64()),
+        label: json
+            .get("label")
+            .and_then(|l| l.as_str())
+            .map(str::to_string),
+    }
+}
+
+fn position(value: Option<f64>) -> f64 {
+    value.unwrap_or(f64::MAX)
+}
+
+/// Orders sections and files like the Docusaurus sidebar: categories by
+/// `_category_.json` position, docs by `sidebar_position` frontmatter, with
+/// category and `sidebar_label` names replacing directory and file names.
+pub fn apply_sidebar_order(root: &Path, groups: &mut FileGroups) {
+    let mut keyed: Vec<(Vec<(f64, String)>, (String, Vec<crate::MarkdownFile>))> = Vec::new();
+
+    for (dir_key, mut files) in std::mem::take(groups) {
+        files.sort_by(|a, b| {
+            let pa = position(
+                a.frontmatter
+                    .get("sidebar_position")
+                    .and_then(|p| p.parse().ok()),
+            );
+            let pb = position(
+                b.frontmatter
+                    .get("sidebar_position")
+                    .and_then(|p| p.parse().ok()),
+            );
+            pa.total_cmp(&pb).then_with(|| a.name.cmp(&b.name))
+        });
+        for file in &mut files {
+            if let Some(label) = file.frontmatter.get("sidebar_label") {
+                file.name = label.to_string();
+            }
+        }
+
+        let relative_dir = files
+            .first()
+            .and_then(|f| f.relative_path.parent())
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        let mut sort_key = Vec::new();
+        let mut labels = Vec::new();
+        let mut dir = PathBuf::from(root);
+        for component in relative_dir.iter() {
+            dir.push(component);
+            let name = component.to_string_lossy().into_owned();
+            let category = category(&dir);
+            labels.push(category.label.clone().unwrap_or_else(|| name.clone()));
+            sort_key.push((position(category.position), name));
+        }
+
+        let dir_key = if labels.is_empty() {
+            dir_key
+        } else {
+            labels.join(" > ")
+        };
+        keyed.push((sort_key, (dir_key, files)));
+    }
+
+    keyed.sort_by(|(a, _), (b, _)| {
+        for ((pa, na), (pb, nb)) in a.iter().zip(b) {
+            let order = pa.total_cmp(pb).then_with(|| na.cmp(nb));
+            if order.is_ne() {
+                return order;
+            }
+        }
+        a.len().cmp(&b.len())
+    });
+
+    *groups = keyed.into_iter().map(|(_, group)| group).collect();
+}
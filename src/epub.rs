@@ -0,0 +1,105 @@
+use crate::template::escape_html;
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+/// The text between `open` and `close` in the rendered HTML document.
+fn between<'a>(html: &'a str, open: &str, close: &str) -> &'a str {
+    html.split_once(open)
+        .and_then(|(_, rest)| rest.split_once(close))
+        .map_or("", |(inner, _)| inner)
+}
+
+/// Packages the rendered HTML document as a single-chapter EPUB 3 book,
+/// reusing its stylesheet.
+pub fn write(path: &Path, html: &str, title: &str, authors: &str) -> Result<()> {
+    let body = between(html, "<body>", "</body>");
+    let style = between(html, "<style>", "</style>");
+    let title = escape_html(title);
+
+    let chapter = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head>
+  <meta charset="utf-8"/>
+  <title>{title}</title>
+  <link rel="stylesheet" type="text/css" href="style.css"/>
+</head>
+<body>
+{body}
+</body>
+</html>
+"#
+    );
+    let nav = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head><title>{title}</title></head>
+<body>
+  <nav epub:type="toc"><ol><li><a href="index.xhtml">{title}</a></li></ol></nav>
+</body>
+</html>
+"#
+    );
+    let opf = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="book-id">urn:md2pdf:{id}</dc:identifier>
+    <dc:title>{title}</dc:title>
+    <dc:creator>{authors}</dc:creator>
+    <dc:language>en</dc:language>
+    <meta property="dcterms:modified">{modified}</meta>
+  </metadata>
+  <manifest>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="index" href="index.xhtml" media-type="application/xhtml+xml"/>
+    <item id="style" href="style.css" media-type="text/css"/>
+  </manifest>
+  <spine>
+    <itemref idref="index"/>
+  </spine>
+</package>
+"#,
+        id = crate::anchors::slugify(&title),
+        authors = escape_html(authors),
+        modified = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
+    );
+
+    let file = File::create(path).with_context(|| format!("Failed to create EPUB: {:?}", path))?;
+    let mut zip = ZipWriter::new(file);
+    // The mimetype entry must come first and be stored uncompressed
+    zip.start_file(
+        "mimetype",
+        FileOptions::default().compression_method(CompressionMethod::Stored),
+    )?;
+    zip.write_all(b"application/epub+zip")?;
+
+    for (name, content) in [
+        ("META-INF/container.xml", CONTAINER_XML),
+        ("OEBPS/content.opf", opf.as_str()),
+        ("OEBPS/nav.xhtml", nav.as_str()),
+        ("OEBPS/index.xhtml", chapter.as_str()),
+        ("OEBPS/style.css", style),
+    ] {
+        zip.start_file(name, FileOptions::default())?;
+        zip.write_all(content.as_bytes())?;
+    }
+
+    zip.finish()
+        .with_context(|| format!("Failed to write EPUB: {:?}", path))?;
+    Ok(())
+}
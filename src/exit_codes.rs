@@ -0,0 +1,51 @@
+use std::fmt;
+use std::path::PathBuf;
+
+/// Failure classes with their own process exit code, so automation can tell
+/// them apart. Anything else exits with 1; clap uses 2 for usage errors.
+#[derive(Debug)]
+pub enum Failure {
+    InputNotFound(PathBuf),
+    NoMarkdownFiles,
+    ChromeLaunch,
+    RenderTimeout,
+    Strict(usize),
+    /// Links to anchors that don't exist, with `--strict`
+    BrokenLinks(usize),
+}
+
+impl Failure {
+    pub fn code(&self) -> i32 {
+        match self {
+            Failure::InputNotFound(_) => 3,
+            Failure::NoMarkdownFiles => 4,
+            Failure::ChromeLaunch => 5,
+            Failure::RenderTimeout => 6,
+            Failure::Strict(_) => 7,
+            Failure::BrokenLinks(_) => 8,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::InputNotFound(path) => write!(f, "Input path does not exist: {:?}", path),
+            Failure::NoMarkdownFiles => write!(f, "No .md files found in directory"),
+            Failure::ChromeLaunch => write!(
+                f,
+                "Failed to start Chrome. Make sure Chrome or Chromium is installed."
+            ),
+            Failure::RenderTimeout => write!(f, "Page navigation timeout"),
+            Failure::Strict(count) => write!(f, "{} warning(s) with --strict", count),
+            Failure::BrokenLinks(count) => write!(f, "{} broken link(s) with --strict", count),
+        }
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// The exit code for an error: the code of the `Failure` it carries, or 1.
+pub fn for_error(error: &anyhow::Error) -> i32 {
+    error.downcast_ref::<Failure>().map_or(1, Failure::code)
+}
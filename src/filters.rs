@@ -0,0 +1,191 @@
+//! Rhai scripts that rewrite the markdown event stream.
+//!
+//! A filter script defines `fn filter(event)`, called once per event with a
+//! map such as `#{ kind: "text", text: "..." }` or
+//! `#{ kind: "start", tag: "link", url: "...", title: "..." }`. Returning
+//! the (possibly modified) map keeps the event, `()` drops it, and an array
+//! of maps replaces it with several events. New events may be of kind
+//! `text`, `html` or `code`.
+
+use anyhow::Result;
+use pulldown_cmark::Event;
+use std::path::PathBuf;
+
+#[cfg(feature = "filters")]
+mod runtime {
+    use anyhow::{Context, Result};
+    use pulldown_cmark::{CowStr, Event, Tag};
+    use rhai::{Array, Dynamic, Engine, Map, Scope, AST};
+    use std::path::PathBuf;
+
+    fn tag_name(tag: &Tag) -> &'static str {
+        match tag {
+            Tag::Paragraph => "paragraph",
+            Tag::Heading(..) => "heading",
+            Tag::BlockQuote => "blockquote",
+            Tag::CodeBlock(_) => "codeblock",
+            Tag::List(_) => "list",
+            Tag::Item => "item",
+            Tag::FootnoteDefinition(_) => "footnote",
+            Tag::Table(_) => "table",
+            Tag::TableHead => "tablehead",
+            Tag::TableRow => "tablerow",
+            Tag::TableCell => "tablecell",
+            Tag::Emphasis => "emphasis",
+            Tag::Strong => "strong",
+            Tag::Strikethrough => "strikethrough",
+            Tag::Link(..) => "link",
+            Tag::Image(..) => "image",
+        }
+    }
+
+    fn text_map(kind: &str, text: &str) -> Map {
+        let mut map = Map::new();
+        map.insert("kind".into(), kind.into());
+        map.insert("text".into(), text.into());
+        map
+    }
+
+    fn tag_map(kind: &str, tag: &Tag) -> Map {
+        let mut map = Map::new();
+        map.insert("kind".into(), kind.into());
+        map.insert("tag".into(), tag_name(tag).into());
+        match tag {
+            Tag::Link(_, url, title) | Tag::Image(_, url, title) => {
+                map.insert("url".into(), url.to_string().into());
+                map.insert("title".into(), title.to_string().into());
+            }
+            Tag::Heading(level, ..) => {
+                map.insert("level".into(), (*level as i64).into());
+            }
+            _ => {}
+        }
+        map
+    }
+
+    fn to_map(event: &Event) -> Map {
+        match event {
+            Event::Text(text) => text_map("text", text),
+            Event::Code(text) => text_map("code", text),
+            Event::Html(text) => text_map("html", text),
+            Event::FootnoteReference(label) => text_map("footnote_reference", label),
+            Event::SoftBreak => text_map("softbreak", ""),
+            Event::HardBreak => text_map("hardbreak", ""),
+            Event::Rule => text_map("rule", ""),
+            Event::TaskListMarker(checked) => {
+                let mut map = text_map("task", "");
+                map.insert("checked".into(), (*checked).into());
+                map
+            }
+            Event::Start(tag) => tag_map("start", tag),
+            Event::End(tag) => tag_map("end", tag),
+        }
+    }
+
+    fn field(map: &Map, key: &str) -> Option<String> {
+        map.get(key).and_then(|v| v.clone().into_string().ok())
+    }
+
+    fn patch_tag<'a>(tag: Tag<'a>, map: &Map) -> Tag<'a> {
+        let url = field(map, "url").map(CowStr::from);
+        let title = field(map, "title").map(CowStr::from);
+        match tag {
+            Tag::Link(kind, old_url, old_title) => {
+                Tag::Link(kind, url.unwrap_or(old_url), title.unwrap_or(old_title))
+            }
+            Tag::Image(kind, old_url, old_title) => {
+                Tag::Image(kind, url.unwrap_or(old_url), title.unwrap_or(old_title))
+            }
+            other => other,
+        }
+    }
+
+    /// Converts a returned map back into an event. Maps of the original kind
+    /// patch the original event; anything else must be a text-like event.
+    fn from_map<'a>(original: &Event<'a>, map: &Map) -> Option<Event<'a>> {
+        let kind = field(map, "kind")?;
+        let text = field(map, "text").unwrap_or_default();
+        match (kind.as_str(), original) {
+            ("start", Event::Start(tag)) => Some(Event::Start(patch_tag(tag.clone(), map))),
+            ("end", Event::End(tag)) => Some(Event::End(patch_tag(tag.clone(), map))),
+            ("text", _) => Some(Event::Text(text.into())),
+            ("code", _) => Some(Event::Code(text.into())),
+            ("html", _) => Some(Event::Html(text.into())),
+            ("softbreak", _) => Some(Event::SoftBreak),
+            ("hardbreak", _) => Some(Event::HardBreak),
+            ("rule", _) => Some(Event::Rule),
+            _ => Some(original.clone()),
+        }
+    }
+
+    pub struct Filter {
+        path: PathBuf,
+        ast: AST,
+    }
+
+    pub fn compile(engine: &Engine, path: &PathBuf) -> Result<Filter> {
+        let ast = engine
+            .compile_file(path.clone())
+            .map_err(|e| anyhow::anyhow!("Failed to compile filter {:?}: {}", path, e))?;
+        Ok(Filter {
+            path: path.clone(),
+            ast,
+        })
+    }
+
+    pub fn run<'a>(
+        engine: &Engine,
+        filter: &Filter,
+        events: Vec<Event<'a>>,
+    ) -> Result<Vec<Event<'a>>> {
+        let mut scope = Scope::new();
+        let mut result = Vec::with_capacity(events.len());
+
+        for event in events {
+            let output: Dynamic = engine
+                .call_fn(&mut scope, &filter.ast, "filter", (to_map(&event),))
+                .map_err(|e| anyhow::anyhow!("Filter {:?} failed: {}", filter.path, e))?;
+
+            if output.is_unit() {
+                continue;
+            }
+            if let Some(map) = output.clone().try_cast::<Map>() {
+                result.extend(from_map(&event, &map));
+                continue;
+            }
+            let items = output.try_cast::<Array>().with_context(|| {
+                format!("Filter {:?} returned an unsupported value", filter.path)
+            })?;
+            for item in items {
+                if let Some(map) = item.try_cast::<Map>() {
+                    result.extend(from_map(&event, &map));
+                }
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// Applies each filter script in turn to the parsed markdown events.
+pub fn apply<'a>(paths: &[PathBuf], events: Vec<Event<'a>>) -> Result<Vec<Event<'a>>> {
+    if paths.is_empty() {
+        return Ok(events);
+    }
+
+    #[cfg(feature = "filters")]
+    {
+        let engine = rhai::Engine::new();
+        let mut events = events;
+        for path in paths {
+            let filter = runtime::compile(&engine, path)?;
+            events = runtime::run(&engine, &filter, events)?;
+        }
+        Ok(events)
+    }
+
+    #[cfg(not(feature = "filters"))]
+    {
+        anyhow::bail!("This build has no filter support. Rebuild with --features filters")
+    }
+}
@@ -0,0 +1,162 @@
+use crate::strings;
+use clap::ValueEnum;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum DetailsMode {
+    /// Show the content with the summary as a sub-heading
+    #[default]
+    Expand,
+    /// Remove collapsible blocks entirely
+    Drop,
+}
+
+fn heading_level(trimmed: &str) -> Option<usize> {
+    let hashes = trimmed.chars().take_while(|&c| c == '#').count();
+    let is_heading = (1..=6).contains(&hashes)
+        && trimmed[hashes..]
+            .chars()
+            .next()
+            .map_or(true, char::is_whitespace);
+    is_heading.then_some(hashes)
+}
+
+/// Flattens `<details>` blocks, which Chrome prints collapsed, so their
+/// content is never silently hidden in the PDF.
+pub fn flatten_details(markdown: &str, mode: DetailsMode) -> String {
+    let mut result = String::new();
+    let mut depth = 0usize;
+    let mut last_heading = 1;
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        let fence = trimmed.starts_with("```");
+        if fence {
+            in_code_block = !in_code_block;
+        }
+        let in_code = in_code_block || fence;
+
+        if in_code || (!trimmed.starts_with("<details") && !trimmed.starts_with("</details>")) {
+            if depth > 0 && mode == DetailsMode::Drop {
+                continue;
+            }
+            // A `# comment` in a shell block is no heading
+            if let Some(level) = heading_level(trimmed).filter(|_| !in_code) {
+                last_heading = level;
+            }
+            result.push_str(line);
+            result.push('\n');
+            continue;
+        }
+
+        let mut rest = trimmed;
+        loop {
+            if let Some(after) = rest.strip_prefix("<details") {
+                depth += 1;
+                rest = after
+                    .find('>')
+                    .map_or("", |end| &after[end + 1..])
+                    .trim_start();
+            } else if let Some(after) = rest.strip_prefix("</details>") {
+                depth = depth.saturating_sub(1);
+                rest = after.trim_start();
+            } else if let Some(after) = rest.strip_prefix("<summary>") {
+                let (summary, after) = match after.find("</summary>") {
+                    Some(end) => (&after[..end], &after[end + "</summary>".len()..]),
+                    None => (after, ""),
+                };
+                if mode == DetailsMode::Expand {
+                    let level = (last_heading + 1).min(6);
+                    result.push_str(&format!("\n{} {}\n\n", "#".repeat(level), summary.trim()));
+                }
+                rest = after.trim_start();
+            } else {
+                break;
+            }
+        }
+
+        if !rest.is_empty() && (depth == 0 || mode == DetailsMode::Expand) {
+            result.push_str(rest);
+            result.push('\n');
+        }
+    }
+
+    result
+}
+
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!(" {}=\"", name))? + name.len() + 3;
+    let end = tag[start..].find('"')?;
+    Some(&tag[start..start + end])
+}
+
+fn tab_label(label: &str) -> String {
+    format!("\n<p class=\"tab-label\">{}</p>\n\n", label)
+}
+
+/// Flattens MkDocs (`=== "Linux"`) and Docusaurus (`<Tabs>`/`<TabItem>`)
+/// tabs into labelled sequential sections so every variant is printed.
+pub fn flatten_tabs(markdown: &str) -> String {
+    let mut result = String::new();
+    let mut in_mkdocs_tab = false;
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        let fence = trimmed.starts_with("```");
+        if fence {
+            in_code_block = !in_code_block;
+        }
+        let in_code = in_code_block || fence;
+
+        if let Some(label) = trimmed.strip_prefix("=== ").filter(|_| !in_code) {
+            result.push_str(&tab_label(label.trim().trim_matches('"')));
+            in_mkdocs_tab = true;
+            continue;
+        }
+
+        if in_mkdocs_tab {
+            if trimmed.is_empty() {
+                result.push('\n');
+                continue;
+            }
+            match line
+                .strip_prefix("    ")
+                .or_else(|| line.strip_prefix('\t'))
+            {
+                Some(dedented) => {
+                    result.push_str(dedented);
+                    result.push('\n');
+                    continue;
+                }
+                None => in_mkdocs_tab = false,
+            }
+        }
+
+        // Code inside a tab is dedented above; elsewhere it stays as written
+        if in_code {
+            result.push_str(line);
+            result.push('\n');
+            continue;
+        }
+
+        if trimmed.starts_with("<Tabs") || trimmed == "</Tabs>" || trimmed == "</TabItem>" {
+            result.push('\n');
+            continue;
+        }
+
+        if trimmed.starts_with("<TabItem") {
+            let label = attribute(trimmed, "label")
+                .or_else(|| attribute(trimmed, "value"))
+                .map(str::to_string)
+                .unwrap_or_else(|| strings::get().tab);
+            result.push_str(&tab_label(&label));
+            continue;
+        }
+
+        result.push_str(line);
+        result.push('\n');
+    }
+
+    result
+}
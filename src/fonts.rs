@@ -0,0 +1,149 @@
+use crate::tempdir::TempDir;
+use anyhow::{Context, Result};
+use base64::Engine;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Font family appended to every font stack in the template.
+pub const FAMILY: &str = "md2pdf-symbols";
+
+/// Monospace stack used when no `--code-font` is given.
+pub const DEFAULT_CODE_FONTS: &str =
+    "ui-monospace, 'DejaVu Sans Mono', Menlo, Consolas, 'Liberation Mono', monospace";
+
+/// Where distributions install the Noto emoji/symbol fonts and DejaVu
+/// (which covers box drawing and most arrows).
+const CANDIDATES: [&str; 8] = [
+    "/usr/share/fonts/truetype/noto/NotoEmoji-Regular.ttf",
+    "/usr/share/fonts/noto/NotoEmoji-Regular.ttf",
+    "/usr/share/fonts/truetype/noto/NotoSansSymbols2-Regular.ttf",
+    "/usr/share/fonts/noto/NotoSansSymbols2-Regular.ttf",
+    "/usr/share/fonts/truetype/noto/NotoSansSymbols-Regular.ttf",
+    "/usr/share/fonts/noto/NotoSansSymbols-Regular.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf",
+    "/usr/share/fonts/TTF/DejaVuSansMono.ttf",
+];
+
+/// Unicode blocks that minimal images usually have no glyphs for: arrows,
+/// technical symbols, box drawing, geometric shapes, dingbats and emoji.
+const SYMBOL_RANGES: [(u32, u32); 6] = [
+    (0x2190, 0x21FF),
+    (0x2300, 0x23FF),
+    (0x2500, 0x27BF),
+    (0x2900, 0x2BFF),
+    (0x1F000, 0x1FAFF),
+    (0xFE0F, 0xFE0F),
+];
+
+fn is_symbol(c: char) -> bool {
+    let code = u32::from(c);
+    SYMBOL_RANGES
+        .iter()
+        .any(|(start, end)| (*start..=*end).contains(&code))
+}
+
+fn data_url(font: &Path, data: &[u8]) -> String {
+    let mime = match font.extension().and_then(|ext| ext.to_str()) {
+        Some("otf") => "font/otf",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        _ => "font/ttf",
+    };
+    format!(
+        "data:{};base64,{}",
+        mime,
+        base64::engine::general_purpose::STANDARD.encode(data)
+    )
+}
+
+/// Whether a `--code-font` value names a font file rather than a family.
+fn is_font_file(value: &str) -> bool {
+    let path = Path::new(value);
+    path.is_file()
+        && path.extension().map_or(false, |ext| {
+            ["ttf", "otf", "woff", "woff2"]
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        })
+}
+
+/// The CSS font family for the code font, plus an `@font-face` rule
+/// embedding it when `value` is a font file.
+pub fn code_font(value: &str) -> Result<(String, String)> {
+    if !is_font_file(value) {
+        return Ok((format!("'{}'", value.replace('\'', "")), String::new()));
+    }
+    let path = Path::new(value);
+    let data = fs::read(path).with_context(|| format!("Failed to read font: {:?}", path))?;
+    let face = format!(
+        "\n        @font-face {{ font-family: 'md2pdf-code'; src: url({}); }}",
+        data_url(path, &data)
+    );
+    Ok(("'md2pdf-code'".to_string(), face))
+}
+
+/// Installed fallback fonts, found in the usual system locations.
+pub fn find_installed() -> Vec<PathBuf> {
+    CANDIDATES
+        .iter()
+        .map(PathBuf::from)
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Cuts the font down to the characters the document uses with fontTools'
+/// `pyftsubset`. Returns `None` when it isn't installed, in which case the
+/// whole font is embedded.
+fn subset(font: &Path, chars: &[char], workspace: &TempDir, index: usize) -> Option<Vec<u8>> {
+    let output = workspace.path.join(format!("subset-{}.ttf", index));
+    let unicodes: Vec<String> = chars
+        .iter()
+        .map(|c| format!("U+{:04X}", u32::from(*c)))
+        .collect();
+    let status = Command::new("pyftsubset")
+        .arg(font)
+        .arg(format!("--unicodes={}", unicodes.join(",")))
+        .arg(format!("--output-file={}", output.display()))
+        .status()
+        .ok()?;
+    if !status.success() {
+        return None;
+    }
+    fs::read(&output).ok()
+}
+
+/// `@font-face` rules embedding the fallback fonts for the emoji and symbols
+/// used in `html`, limited by `unicode-range` so regular text never picks
+/// them up. Empty when the document uses none.
+pub fn fallback_css(html: &str, fonts: &[PathBuf]) -> Result<String> {
+    let mut chars: Vec<char> = html.chars().filter(|c| is_symbol(*c)).collect();
+    if chars.is_empty() || fonts.is_empty() {
+        return Ok(String::new());
+    }
+    chars.sort_unstable();
+    chars.dedup();
+
+    let unicode_range = SYMBOL_RANGES
+        .iter()
+        .map(|(start, end)| format!("U+{:X}-{:X}", start, end))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let workspace = TempDir::new("fonts")?;
+    let mut css = String::new();
+    for (index, font) in fonts.iter().enumerate() {
+        let data = match subset(font, &chars, &workspace, index) {
+            Some(data) => data,
+            None => fs::read(font).with_context(|| format!("Failed to read font: {:?}", font))?,
+        };
+        css.push_str(&format!(
+            "\n        @font-face {{ font-family: '{}'; src: url({}); unicode-range: {}; }}",
+            FAMILY,
+            data_url(font, &data),
+            unicode_range
+        ));
+    }
+
+    Ok(css)
+}
@@ -0,0 +1,146 @@
+use crate::template::escape_html;
+use clap::ValueEnum;
+use pulldown_cmark::{Event, Tag};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum FootnotePlacement {
+    /// At the end of each source file
+    #[default]
+    PerFile,
+    /// At the end of each directory section
+    PerChapter,
+    /// Collected as endnotes at the end of the document
+    EndOfDocument,
+}
+
+fn prefix_labels(line: &str, prefix: &str) -> String {
+    line.replace("[^", &format!("[^{}-", prefix))
+}
+
+fn is_definition(trimmed: &str) -> bool {
+    trimmed.starts_with("[^") && trimmed.contains("]:")
+}
+
+/// Splits footnote definitions out of a file and prefixes every footnote
+/// label with `prefix`, so identical labels from different files don't
+/// collide once combined. Returns the body and the definitions.
+pub fn extract_definitions(markdown: &str, prefix: &str) -> (String, String) {
+    let mut body = String::new();
+    let mut definitions = String::new();
+    let mut in_definition = false;
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+
+        if in_code_block {
+            in_definition = false;
+            body.push_str(line);
+            body.push('\n');
+            continue;
+        }
+
+        // Continuation lines of a definition are indented
+        let continues = in_definition
+            && (trimmed.is_empty() || line.starts_with("    ") || line.starts_with('\t'));
+        in_definition = is_definition(trimmed) || continues;
+
+        let target = if in_definition {
+            &mut definitions
+        } else {
+            &mut body
+        };
+        target.push_str(&prefix_labels(line, prefix));
+        target.push('\n');
+    }
+
+    (body, definitions)
+}
+
+fn reference_id(label: &str, occurrence: usize) -> String {
+    if occurrence == 1 {
+        format!("fnref-{}", label)
+    } else {
+        format!("fnref-{}-{}", label, occurrence)
+    }
+}
+
+/// Renders footnote references and definitions as HTML, numbered in order of
+/// first appearance like pulldown-cmark does. Every reference gets an id, and
+/// with `back_links` each definition ends in a `↩` link per reference.
+pub fn render_footnotes(events: Vec<Event<'_>>, back_links: bool) -> Vec<Event<'_>> {
+    let mut numbers: HashMap<String, usize> = HashMap::new();
+    let mut reference_counts: HashMap<String, usize> = HashMap::new();
+    for event in &events {
+        let label = match event {
+            Event::FootnoteReference(label) => {
+                *reference_counts.entry(label.to_string()).or_default() += 1;
+                label
+            }
+            Event::Start(Tag::FootnoteDefinition(label)) => label,
+            _ => continue,
+        };
+        let next = numbers.len() + 1;
+        numbers.entry(label.to_string()).or_insert(next);
+    }
+
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut open_definitions: Vec<String> = Vec::new();
+
+    events
+        .into_iter()
+        .map(|event| match event {
+            Event::FootnoteReference(label) => {
+                let occurrence = seen.entry(label.to_string()).or_default();
+                *occurrence += 1;
+                let id = escape_html(&label);
+                Event::Html(
+                    format!(
+                        "<sup class=\"footnote-reference\" id=\"{}\"><a href=\"#{}\">{}</a></sup>",
+                        reference_id(&id, *occurrence),
+                        id,
+                        numbers[&*label]
+                    )
+                    .into(),
+                )
+            }
+            Event::Start(Tag::FootnoteDefinition(label)) => {
+                open_definitions.push(label.to_string());
+                Event::Html(
+                    format!(
+                        "<div class=\"footnote-definition\" id=\"{}\"><sup class=\"footnote-definition-label\">{}</sup>",
+                        escape_html(&label),
+                        numbers[&*label]
+                    )
+                    .into(),
+                )
+            }
+            Event::End(Tag::FootnoteDefinition(_)) => {
+                let label = open_definitions.pop().unwrap_or_default();
+                let mut html = String::new();
+                if back_links {
+                    let references = reference_counts.get(&label).copied().unwrap_or(0);
+                    for occurrence in 1..=references {
+                        let arrow = if occurrence == 1 {
+                            "↩".to_string()
+                        } else {
+                            format!("↩<sup>{}</sup>", occurrence)
+                        };
+                        html.push_str(&format!(
+                            " <a href=\"#{}\" class=\"footnote-backref\">{}</a>",
+                            reference_id(&escape_html(&label), occurrence),
+                            arrow
+                        ));
+                    }
+                }
+                html.push_str("</div>\n");
+                Event::Html(html.into())
+            }
+            other => other,
+        })
+        .collect()
+}
@@ -0,0 +1,379 @@
+use crate::pdf_tools;
+use crate::template::escape_html;
+use crate::warnings;
+use anyhow::Result;
+use base64::Engine;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+const MARKER: &str = "[field:";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldKind {
+    Text,
+    /// Multi-line text
+    Textarea,
+    Checkbox,
+}
+
+impl FieldKind {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "text" => Some(FieldKind::Text),
+            "textarea" => Some(FieldKind::Textarea),
+            "checkbox" => Some(FieldKind::Checkbox),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            FieldKind::Text => "text",
+            FieldKind::Textarea => "textarea",
+            FieldKind::Checkbox => "checkbox",
+        }
+    }
+
+    /// Default width and height in points.
+    fn default_size(self) -> (f64, f64) {
+        match self {
+            FieldKind::Text => (142.0, 16.0),
+            FieldKind::Textarea => (425.0, 72.0),
+            FieldKind::Checkbox => (11.0, 11.0),
+        }
+    }
+}
+
+/// A fillable field placed in the document, sized in points.
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub name: String,
+    pub kind: FieldKind,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// A CSS length (`5cm`, `40mm`, `2in`, `120pt`, `160px`) in points.
+fn points(length: &str) -> Option<f64> {
+    let split = length.find(|c: char| c.is_ascii_alphabetic())?;
+    let value: f64 = length[..split].parse().ok()?;
+    let factor = match &length[split..] {
+        "cm" => 72.0 / 2.54,
+        "mm" => 72.0 / 25.4,
+        "in" => 72.0,
+        "pt" => 1.0,
+        "px" => 0.75,
+        _ => return None,
+    };
+    Some(value * factor)
+}
+
+/// The value of `name="..."` in an HTML tag.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!(" {}=\"", name))? + name.len() + 3;
+    let end = tag[start..].find('"')? + start;
+    Some(&tag[start..end])
+}
+
+/// An empty box of the field's size that the PDF pass puts the form field on.
+fn field_html(field: &Field) -> String {
+    format!(
+        "<span class=\"form-field form-{kind}\" id=\"field-{name}\" data-field=\"{name}\" data-type=\"{kind}\" data-size=\"{width}x{height}\" style=\"width: {width}pt; height: {height}pt\"></span>",
+        kind = field.kind.name(),
+        name = escape_html(&field.name),
+        width = field.width,
+        height = field.height,
+    )
+}
+
+/// Parses `name]{type=text width=5cm}` following the `[field:` marker and
+/// returns the field and the length of the text it spans.
+fn parse_field(text: &str) -> Option<(Field, usize)> {
+    let close = text.find(']')?;
+    let name = text[..close].trim();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return None;
+    }
+
+    let mut kind = FieldKind::Text;
+    let mut width = None;
+    let mut height = None;
+    let mut length = close + 1;
+    if text[length..].starts_with('{') {
+        let end = text[length..].find('}')? + length;
+        for setting in text[length + 1..end].split_whitespace() {
+            match setting.split_once('=') {
+                Some(("type", value)) => kind = FieldKind::parse(value)?,
+                Some(("width", value)) => width = Some(points(value)?),
+                Some(("height", value)) => height = Some(points(value)?),
+                _ => return None,
+            }
+        }
+        length = end + 1;
+    }
+
+    let (default_width, default_height) = kind.default_size();
+    let field = Field {
+        name: name.to_string(),
+        kind,
+        width: width.unwrap_or(default_width),
+        height: height.unwrap_or(default_height),
+    };
+    Some((field, length))
+}
+
+/// A field for an `<input type="text|checkbox" name="...">` tag.
+fn input_field(tag: &str) -> Option<Field> {
+    let name = attribute(tag, "name")?;
+    let kind = FieldKind::parse(attribute(tag, "type").unwrap_or("text"))?;
+    let (width, height) = kind.default_size();
+    Some(Field {
+        name: name.to_string(),
+        kind,
+        width,
+        height,
+    })
+}
+
+fn expand_line(line: &str) -> String {
+    let mut result = String::new();
+    let mut rest = line;
+
+    loop {
+        let marker = rest.find(MARKER);
+        let input = rest.find("<input ");
+        match (marker, input) {
+            (Some(start), _) if input.map_or(true, |input| start < input) => {
+                result.push_str(&rest[..start]);
+                let after = &rest[start + MARKER.len()..];
+                match parse_field(after) {
+                    Some((field, length)) => {
+                        result.push_str(&field_html(&field));
+                        rest = &after[length..];
+                    }
+                    None => {
+                        result.push_str(MARKER);
+                        rest = after;
+                    }
+                }
+            }
+            (_, Some(start)) => {
+                result.push_str(&rest[..start]);
+                let Some(end) = rest[start..].find('>').map(|end| start + end + 1) else {
+                    rest = &rest[start..];
+                    break;
+                };
+                match input_field(&rest[start..end]) {
+                    Some(field) => result.push_str(&field_html(&field)),
+                    None => result.push_str(&rest[start..end]),
+                }
+                rest = &rest[end..];
+            }
+            _ => break,
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Replaces `[field:name]{type=text width=5cm}` (type `text`, `textarea`
+/// or `checkbox`, with optional `width` and `height`) and named
+/// `<input type="text|checkbox">` tags with boxes that [`add_fields`] turns
+/// into fillable PDF form fields.
+pub fn expand_fields(markdown: &str) -> String {
+    let mut result = String::new();
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        if line.trim().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block || !(line.contains(MARKER) || line.contains("<input ")) {
+            result.push_str(line);
+        } else {
+            result.push_str(&expand_line(line));
+        }
+        result.push('\n');
+    }
+
+    result
+}
+
+/// The form fields in rendered HTML.
+pub fn fields(html: &str) -> Vec<Field> {
+    html.match_indices("<span class=\"form-field ")
+        .filter_map(|(start, _)| {
+            let tag = &html[start..start + html[start..].find('>')?];
+            let (width, height) = attribute(tag, "data-size")?.split_once('x')?;
+            Some(Field {
+                name: attribute(tag, "data-field")?.to_string(),
+                kind: FieldKind::parse(attribute(tag, "data-type")?)?,
+                width: width.parse().ok()?,
+                height: height.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Hidden links to every field, so Chrome writes a named destination (and
+/// with it the position) for each one.
+pub fn field_links(fields: &[Field]) -> String {
+    let mut html = String::from(
+        "<nav class=\"form-fields\" aria-hidden=\"true\" style=\"position: absolute; width: 0; height: 0; overflow: hidden\">",
+    );
+    for field in fields {
+        html.push_str(&format!(
+            "<a href=\"#field-{}\"></a>",
+            escape_html(&field.name)
+        ));
+    }
+    html.push_str("</nav>\n");
+    html
+}
+
+/// A form XObject appearance stream drawing `content` in a `width`×`height` box.
+fn appearance(content: &str, width: f64, height: f64) -> Value {
+    json!({
+        "stream": {
+            "dict": {
+                "/Type": "/XObject",
+                "/Subtype": "/Form",
+                "/BBox": [0, 0, width, height],
+                "/Resources": { "/Font": { "/ZaDb": zapf_dingbats() } },
+            },
+            "data": base64::engine::general_purpose::STANDARD.encode(content),
+        }
+    })
+}
+
+fn zapf_dingbats() -> Value {
+    json!({ "/Type": "/Font", "/Subtype": "/Type1", "/BaseFont": "/ZapfDingbats" })
+}
+
+/// Adds a fillable AcroForm field over every field box in the PDF, at the
+/// position Chrome recorded for it.
+pub fn add_fields(pdf: &Path, fields: &[Field]) -> Result<()> {
+    let json = pdf_tools::qpdf_json(pdf)?;
+    let destinations = pdf_tools::destinations(&json);
+    let objects = &json["qpdf"][1];
+    let mut next_id = json["qpdf"][0]["maxobjectid"].as_u64().unwrap_or(0);
+    let mut update = serde_json::Map::new();
+    let mut pages: HashMap<String, Value> = HashMap::new();
+    let mut field_refs = Vec::new();
+
+    let mut new_object = |update: &mut serde_json::Map<String, Value>, value: Value| {
+        next_id += 1;
+        let reference = format!("{} 0 R", next_id);
+        update.insert(format!("obj:{}", reference), value);
+        reference
+    };
+
+    for field in fields {
+        let Some(destination) = destinations.get(&format!("field-{}", field.name)) else {
+            warnings::warn(format!(
+                "Form field {} was not found in the PDF",
+                field.name
+            ));
+            continue;
+        };
+        let rect = [
+            destination.left,
+            destination.top - field.height,
+            destination.left + field.width,
+            destination.top,
+        ];
+        let mut widget = json!({
+            "/Type": "/Annot",
+            "/Subtype": "/Widget",
+            "/T": format!("u:{}", field.name),
+            "/Rect": rect,
+            "/F": 4,
+            "/P": destination.page_object,
+            "/MK": { "/BC": [0.6] },
+        });
+        match field.kind {
+            FieldKind::Text | FieldKind::Textarea => {
+                widget["/FT"] = json!("/Tx");
+                widget["/DA"] = json!("u:/Helv 10 Tf 0 g");
+                if field.kind == FieldKind::Textarea {
+                    // Multiline flag
+                    widget["/Ff"] = json!(4096);
+                }
+            }
+            FieldKind::Checkbox => {
+                let size = field.height * 0.8;
+                let check = format!(
+                    "q 0 g BT /ZaDb {:.2} Tf {:.2} {:.2} Td (4) Tj ET Q",
+                    size,
+                    (field.width - size * 0.85) / 2.0,
+                    (field.height - size * 0.7) / 2.0
+                );
+                let on = new_object(&mut update, appearance(&check, field.width, field.height));
+                let off = new_object(&mut update, appearance("", field.width, field.height));
+                widget["/FT"] = json!("/Btn");
+                widget["/V"] = json!("/Off");
+                widget["/AS"] = json!("/Off");
+                widget["/DA"] = json!("u:/ZaDb 0 Tf 0 g");
+                widget["/MK"]["/CA"] = json!("u:4");
+                widget["/AP"] = json!({ "/N": { "/Yes": on, "/Off": off } });
+            }
+        }
+        let reference = new_object(&mut update, json!({ "value": widget }));
+
+        // Chrome's own link annotations stay; /Annots may be inline or a reference
+        let page = pages
+            .entry(destination.page_object.clone())
+            .or_insert_with(|| {
+                objects[format!("obj:{}", destination.page_object)]["value"].clone()
+            });
+        let mut annotations = pdf_tools::resolve(objects, &page["/Annots"])
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        annotations.push(json!(reference));
+        page["/Annots"] = Value::Array(annotations);
+        field_refs.push(json!(reference));
+    }
+
+    if field_refs.is_empty() {
+        return Ok(());
+    }
+    for (page_object, page) in pages {
+        update.insert(format!("obj:{}", page_object), json!({ "value": page }));
+    }
+
+    let Some(root_object) = objects["trailer"]["value"]["/Root"].as_str() else {
+        anyhow::bail!("PDF has no document catalog");
+    };
+    let mut root = objects[format!("obj:{}", root_object)]["value"].clone();
+    root["/AcroForm"] = json!({
+        "/Fields": field_refs,
+        // Viewers draw text field contents themselves
+        "/NeedAppearances": true,
+        "/DA": "u:/Helv 0 Tf 0 g",
+        "/DR": {
+            "/Font": {
+                "/Helv": { "/Type": "/Font", "/Subtype": "/Type1", "/BaseFont": "/Helvetica" },
+                "/ZaDb": zapf_dingbats(),
+            }
+        },
+    });
+    update.insert(format!("obj:{}", root_object), json!({ "value": root }));
+
+    pdf_tools::update_from_json(
+        pdf,
+        &json!({
+            "qpdf": [
+                {
+                    "jsonversion": 2,
+                    "pushedinheritedpageresources": false,
+                    "calledgetallpages": false,
+                    "maxobjectid": next_id,
+                },
+                update,
+            ]
+        }),
+    )
+}
@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+
+/// A minimal YAML frontmatter block: flat `key: value` pairs plus lists
+/// written either inline (`[a, b]`) or as `- item` lines.
+#[derive(Debug, Clone, Default)]
+pub struct Frontmatter {
+    values: BTreeMap<String, Vec<String>>,
+}
+
+fn unquote(value: &str) -> String {
+    value
+        .trim()
+        .trim_matches('"')
+        .trim_matches('\'')
+        .to_string()
+}
+
+impl Frontmatter {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values
+            .get(key)
+            .and_then(|v| v.first())
+            .map(String::as_str)
+    }
+
+    pub fn list(&self, key: &str) -> &[String] {
+        self.values.get(key).map_or(&[], Vec::as_slice)
+    }
+
+    fn parse(block: &str) -> Self {
+        let mut values: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut current_key: Option<String> = None;
+
+        for line in block.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            if let (Some(item), Some(key)) = (trimmed.strip_prefix("- "), &current_key) {
+                values.entry(key.clone()).or_default().push(unquote(item));
+                continue;
+            }
+
+            let Some((key, value)) = trimmed.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_string();
+            let value = value.trim();
+
+            let items = match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+                Some(inline) => inline
+                    .split(',')
+                    .map(unquote)
+                    .filter(|item| !item.is_empty())
+                    .collect(),
+                None if value.is_empty() => Vec::new(),
+                None => vec![unquote(value)],
+            };
+            values.insert(key.clone(), items);
+            current_key = Some(key);
+        }
+
+        Frontmatter { values }
+    }
+}
+
+/// Splits a leading `---` delimited frontmatter block off the markdown.
+pub fn split(markdown: &str) -> (Frontmatter, &str) {
+    let Some(rest) = markdown
+        .strip_prefix("---\n")
+        .or_else(|| markdown.strip_prefix("---\r\n"))
+    else {
+        return (Frontmatter::default(), markdown);
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" || line.trim_end() == "..." {
+            let block = &rest[..offset];
+            return (Frontmatter::parse(block), &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+
+    (Frontmatter::default(), markdown)
+}
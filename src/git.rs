@@ -0,0 +1,137 @@
+use crate::strings;
+use crate::tempdir::TempDir;
+use crate::template::Variables;
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+fn git_log(input: &Path, format: &str, extra: &[&str]) -> Option<String> {
+    let (dir, target) = if input.is_dir() {
+        (input, Path::new("."))
+    } else {
+        let parent = input.parent().filter(|p| !p.as_os_str().is_empty());
+        (
+            parent.unwrap_or(Path::new(".")),
+            Path::new(input.file_name()?),
+        )
+    };
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("log")
+        .arg(format!("--format={}", format))
+        .args(extra)
+        .arg("--")
+        .arg(target)
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Collects `git.*` template variables for the last commit touching the input.
+/// Returns nothing when the input is not inside a git repository.
+pub fn metadata(input: &Path) -> Variables {
+    let mut variables = Variables::new();
+
+    let Some(log) = git_log(input, "%H%x1f%h%x1f%cs%x1f%an", &["-1"]) else {
+        return variables;
+    };
+
+    let fields: Vec<&str> = log.trim().split('\x1f').collect();
+    if let [hash, short_hash, date, author] = fields[..] {
+        variables.insert("git.commit".to_string(), hash.to_string());
+        variables.insert("git.short_commit".to_string(), short_hash.to_string());
+        variables.insert("git.date".to_string(), date.to_string());
+        variables.insert("git.author".to_string(), author.to_string());
+    }
+
+    variables
+}
+
+/// Builds a "Revision History" markdown table from the git log of the input.
+pub fn revision_history(input: &Path) -> Result<String> {
+    let log = git_log(input, "%h%x1f%cs%x1f%an%x1f%s", &[])
+        .context("Failed to read git history. Is the input inside a git repository?")?;
+
+    let strings = strings::get();
+    let mut table = format!("\n\n# {}\n\n", strings.revision_history);
+    table.push_str(&format!(
+        "| {} | {} | {} | {} |\n",
+        strings.commit, strings.date, strings.author, strings.message
+    ));
+    table.push_str("|---|---|---|---|\n");
+
+    for line in log.lines() {
+        let fields: Vec<String> = line.split('\x1f').map(|f| f.replace('|', "\\|")).collect();
+        if let [hash, date, author, subject] = &fields[..] {
+            table.push_str(&format!(
+                "| `{}` | {} | {} | {} |\n",
+                hash, date, author, subject
+            ));
+        }
+    }
+
+    Ok(table)
+}
+
+/// Whether the input names a git repository rather than a local path.
+pub fn is_repository_url(input: &str) -> bool {
+    input.starts_with("git@")
+        || input.starts_with("ssh://")
+        || input.starts_with("git://")
+        || ((input.starts_with("https://") || input.starts_with("http://"))
+            && input.trim_end_matches('/').ends_with(".git"))
+}
+
+fn run_git(dir: &Path, args: &[&str]) -> Result<()> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .context("Failed to run git. Make sure it is installed.")?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(())
+}
+
+/// Shallow-clones `url` at `reference` (a branch, tag or commit; the default
+/// branch when `None`) into a temporary directory.
+pub fn clone_shallow(url: &str, reference: Option<&str>) -> Result<TempDir> {
+    let checkout = TempDir::new("clone")?;
+    let dir = checkout.path.as_path();
+
+    // Fetching a single ref works for commit hashes too, unlike clone --branch
+    run_git(dir, &["init", "-q"])?;
+    // Links are checked out as plain files holding their target, so a
+    // repository can't point md2pdf at files outside the checkout
+    run_git(dir, &["config", "core.symlinks", "false"])?;
+    run_git(dir, &["remote", "add", "origin", url])?;
+    run_git(
+        dir,
+        &[
+            "fetch",
+            "-q",
+            "--depth",
+            "1",
+            "origin",
+            reference.unwrap_or("HEAD"),
+        ],
+    )?;
+    run_git(dir, &["checkout", "-q", "FETCH_HEAD"])?;
+
+    Ok(checkout)
+}
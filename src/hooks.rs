@@ -0,0 +1,34 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+/// Runs hook commands through the system shell with `MD2PDF_INPUT` and
+/// `MD2PDF_OUTPUT` set, failing on the first unsuccessful command.
+pub fn run(commands: &[String], stage: &str, input: &Path, output: &Path) -> Result<()> {
+    for command in commands {
+        println!("Running {} hook: {}", stage, command);
+
+        let mut shell = if cfg!(windows) {
+            let mut shell = Command::new("cmd");
+            shell.arg("/C");
+            shell
+        } else {
+            let mut shell = Command::new("sh");
+            shell.arg("-c");
+            shell
+        };
+
+        let status = shell
+            .arg(command)
+            .env("MD2PDF_INPUT", input)
+            .env("MD2PDF_OUTPUT", output)
+            .status()
+            .with_context(|| format!("Failed to run {} hook: {}", stage, command))?;
+
+        if !status.success() {
+            anyhow::bail!("{} hook failed ({}): {}", stage, status, command);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,382 @@
+use crate::{anchors, images};
+use anyhow::{Context, Result};
+use base64::Engine;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Directory next to the HTML file that holds its stylesheet, images, and fonts.
+const ASSETS_DIR: &str = "assets";
+
+/// File extension for a `data:` URI's media type.
+fn extension(mime: &str) -> &str {
+    match mime {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/svg+xml" => "svg",
+        "image/webp" => "webp",
+        "image/bmp" => "bmp",
+        "font/otf" => "otf",
+        "font/woff" => "woff",
+        "font/woff2" => "woff2",
+        "font/ttf" => "ttf",
+        _ => "bin",
+    }
+}
+
+/// Calls `rewrite` with every `src="..."` value and substitutes the value
+/// when it returns a replacement.
+fn rewrite_sources(
+    html: &str,
+    mut rewrite: impl FnMut(&str) -> Result<Option<String>>,
+) -> Result<String> {
+    const SRC: &str = "src=\"";
+
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find(SRC) {
+        let value_start = start + SRC.len();
+        let Some(end) = rest[value_start..].find('"').map(|end| value_start + end) else {
+            break;
+        };
+        result.push_str(&rest[..value_start]);
+        let value = &rest[value_start..end];
+        match rewrite(value)? {
+            Some(replacement) => result.push_str(&replacement),
+            None => result.push_str(value),
+        }
+        rest = &rest[end..];
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Calls `rewrite` with the target of every CSS `url(...)` (quoted or not)
+/// and substitutes the target when it returns a replacement.
+fn rewrite_css_urls(
+    css: &str,
+    mut rewrite: impl FnMut(&str) -> Result<Option<String>>,
+) -> Result<String> {
+    let mut result = String::with_capacity(css.len());
+    let mut rest = css;
+
+    while let Some(start) = rest.find("url(") {
+        let value_start = start + "url(".len();
+        let Some(end) = rest[value_start..].find(')').map(|end| value_start + end) else {
+            break;
+        };
+        result.push_str(&rest[..value_start]);
+        let value = rest[value_start..end]
+            .trim()
+            .trim_matches(|c| c == '"' || c == '\'');
+        match rewrite(value)? {
+            Some(replacement) => result.push_str(&format!("\"{}\"", replacement)),
+            None => result.push_str(&rest[value_start..end]),
+        }
+        rest = &rest[end..];
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Rewrites relative `src` values inside combined-file `<section data-source>`
+/// wrappers to be relative to the input directory, as they were written
+/// relative to their own markdown file (`guide/setup.md` showing
+/// `img/step.png` means `guide/img/step.png`).
+pub fn rebase_sources(html: &str) -> String {
+    const SRC: &str = "src=\"";
+
+    let mut result = String::with_capacity(html.len());
+    let mut source = String::new();
+    let mut rest = html;
+
+    while let Some(start) = rest.find(SRC) {
+        let value_start = start + SRC.len();
+        let Some(end) = rest[value_start..].find('"').map(|end| value_start + end) else {
+            break;
+        };
+        for (index, _) in rest[..start].match_indices("<section ") {
+            if let Some(found) = anchors::section_source(&rest[index..]) {
+                source = found;
+            }
+        }
+        result.push_str(&rest[..value_start]);
+        let value = &rest[value_start..end];
+        let relative = !(images::is_remote(value)
+            || value.is_empty()
+            || value.starts_with(['#', '/'])
+            || value.contains(':'));
+        if relative && source.contains('/') {
+            let rebased = anchors::resolve_relative(&source, value);
+            let parts: Vec<_> = rebased
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect();
+            result.push_str(&parts.join("/"));
+        } else {
+            result.push_str(value);
+        }
+        rest = &rest[end..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// A local file referenced by the document, resolved against the directory
+/// of its markdown. Remote URLs, `data:` URIs, and fragments are not files.
+fn local_file(url: &str, source_dir: Option<&Path>) -> Option<PathBuf> {
+    if images::is_remote(url) || url.starts_with('#') || url.is_empty() {
+        return None;
+    }
+    let path = source_dir?.join(images::percent_decode(url));
+    path.is_file().then_some(path)
+}
+
+/// Writes a document's images and fonts into `assets/` under their own names.
+struct Assets {
+    dir: PathBuf,
+    /// Stored names by content hash, so a logo referenced from many files
+    /// (by different paths, or already inlined) is written once
+    written: HashMap<u64, String>,
+}
+
+impl Assets {
+    /// Stores a `data:` URI or local file as `assets/<kind>/asset-N.ext` and
+    /// returns its path relative to `assets/`, or `None` for anything else.
+    fn store(&mut self, url: &str, source_dir: Option<&Path>) -> Result<Option<String>> {
+        let (bytes, ext) = if let Some(data) = url.strip_prefix("data:") {
+            let Some((mime, payload)) = data.split_once(";base64,") else {
+                return Ok(None);
+            };
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(payload)
+                .context("Invalid data URI in HTML")?;
+            (bytes, extension(mime).to_string())
+        } else if let Some(path) = local_file(url, source_dir) {
+            let bytes =
+                fs::read(&path).with_context(|| format!("Failed to read file: {:?}", path))?;
+            let ext = path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+                .unwrap_or_else(|| "bin".to_string());
+            (bytes, ext)
+        } else {
+            return Ok(None);
+        };
+
+        let mut hasher = DefaultHasher::new();
+        (&bytes, &ext).hash(&mut hasher);
+        let hash = hasher.finish();
+        if let Some(stored) = self.written.get(&hash) {
+            return Ok(Some(stored.clone()));
+        }
+
+        let kind = match ext.as_str() {
+            "ttf" | "otf" | "woff" | "woff2" => "fonts",
+            _ => "images",
+        };
+        let name = format!("{}/asset-{}.{}", kind, self.written.len() + 1, ext);
+        let path = self.dir.join(&name);
+        fs::create_dir_all(self.dir.join(kind))
+            .with_context(|| format!("Failed to create directory: {:?}", self.dir.join(kind)))?;
+        fs::write(&path, bytes).with_context(|| format!("Failed to write file: {:?}", path))?;
+
+        self.written.insert(hash, name.clone());
+        Ok(Some(name))
+    }
+}
+
+/// Writes the HTML document to `output` with its stylesheet, images, and
+/// fonts moved into an `assets/` directory beside it and referenced by
+/// relative paths, ready to publish as is. Relative image paths are
+/// resolved against `source_dir`.
+pub fn write_bundle(html: &str, output: &Path, source_dir: Option<&Path>) -> Result<()> {
+    let html = rebase_sources(html);
+    let dir = output.parent().unwrap_or(Path::new(".")).join(ASSETS_DIR);
+    let mut assets = Assets {
+        dir,
+        written: HashMap::new(),
+    };
+
+    // Every <style> block goes into one stylesheet, linked where the first was
+    let mut css = String::new();
+    let mut linked = false;
+    let mut page = String::with_capacity(html.len());
+    let mut rest = html.as_str();
+    while let Some(start) = rest.find("<style>") {
+        let Some(end) = rest[start..].find("</style>").map(|end| start + end) else {
+            break;
+        };
+        page.push_str(&rest[..start]);
+        if !linked {
+            page.push_str(&format!(
+                "<link rel=\"stylesheet\" href=\"{}/style.css\">",
+                ASSETS_DIR
+            ));
+            linked = true;
+        }
+        css.push_str(&rest[start + "<style>".len()..end]);
+        css.push('\n');
+        rest = &rest[end + "</style>".len()..];
+    }
+    page.push_str(rest);
+
+    // Paths in the stylesheet are relative to assets/, paths in the page to its directory
+    let css = rewrite_css_urls(&css, |url| assets.store(url, source_dir))?;
+    let page = rewrite_sources(&page, |url| {
+        Ok(assets
+            .store(url, source_dir)?
+            .map(|name| format!("{}/{}", ASSETS_DIR, name)))
+    })?;
+
+    fs::create_dir_all(&assets.dir)
+        .with_context(|| format!("Failed to create directory: {:?}", assets.dir))?;
+    let stylesheet = assets.dir.join("style.css");
+    fs::write(&stylesheet, css)
+        .with_context(|| format!("Failed to write file: {:?}", stylesheet))?;
+    fs::write(output, page).with_context(|| format!("Failed to save HTML: {:?}", output))
+}
+
+/// Downloads a remote asset as a `data:` URI, typed by its `Content-Type`.
+fn fetch_data_uri(url: &str) -> Result<String> {
+    let url = match url.strip_prefix("//") {
+        Some(rest) => format!("https://{}", rest),
+        None => url.to_string(),
+    };
+    let response = ureq::get(&url)
+        .call()
+        .with_context(|| format!("Failed to fetch: {}", url))?;
+    let mime = response.content_type().to_string();
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("Failed to read response from: {}", url))?;
+    Ok(format!(
+        "data:{};base64,{}",
+        mime,
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    ))
+}
+
+/// The `data:` URI for an image, font, or other asset referenced from
+/// `base_dir`, or `None` when it already is one (or is a fragment). Each
+/// remote URL and resolved local file is read once and kept in `inlined`.
+fn inline_url(
+    url: &str,
+    base_dir: Option<&Path>,
+    inlined: &mut HashMap<String, String>,
+) -> Result<Option<String>> {
+    if url.starts_with("data:") || url.starts_with('#') || url.is_empty() {
+        return Ok(None);
+    }
+    let path = if images::is_remote(url) {
+        None
+    } else {
+        match local_file(url, base_dir) {
+            Some(path) => Some(path),
+            None => anyhow::bail!("Asset not found for self-contained HTML: {}", url),
+        }
+    };
+    let key = path.as_ref().map_or_else(
+        || url.to_string(),
+        |path| path.to_string_lossy().into_owned(),
+    );
+    if let Some(uri) = inlined.get(&key) {
+        return Ok(Some(uri.clone()));
+    }
+    let uri = match &path {
+        Some(path) => images::data_uri(path)?,
+        None => fetch_data_uri(url)?,
+    };
+    inlined.insert(key, uri.clone());
+    Ok(Some(uri))
+}
+
+/// The value of `name="..."` in an HTML tag.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!("{}=\"", name))? + name.len() + 2;
+    let end = tag[start..].find('"')? + start;
+    Some(&tag[start..end])
+}
+
+/// Inlines every image, font, and stylesheet of the document as `data:`
+/// URIs and `<style>` blocks, so the HTML is one portable file with no
+/// outside references. Remote assets are downloaded; relative paths are
+/// resolved against `source_dir`. Images used more than once are stored once
+/// afterwards, like in the rendered document.
+pub fn inline_assets(html: &str, source_dir: Option<&Path>) -> Result<String> {
+    let html = rebase_sources(html);
+    let mut inlined = HashMap::new();
+
+    // <link rel="stylesheet"> becomes a <style> block with its own url()s inlined
+    let mut page = String::with_capacity(html.len());
+    let mut rest = html.as_str();
+    while let Some(start) = rest.find("<link ") {
+        let Some(end) = rest[start..].find('>').map(|end| start + end + 1) else {
+            break;
+        };
+        let tag = &rest[start..end];
+        page.push_str(&rest[..start]);
+        match attribute(tag, "href").filter(|_| attribute(tag, "rel") == Some("stylesheet")) {
+            Some(href) => {
+                let (css, css_dir) = if images::is_remote(href) {
+                    let css = ureq::get(href)
+                        .call()
+                        .with_context(|| format!("Failed to fetch: {}", href))?
+                        .into_string()
+                        .with_context(|| format!("Failed to read response from: {}", href))?;
+                    (css, None)
+                } else {
+                    let Some(path) = local_file(href, source_dir) else {
+                        anyhow::bail!("Stylesheet not found for self-contained HTML: {}", href);
+                    };
+                    let css = fs::read_to_string(&path)
+                        .with_context(|| format!("Failed to read file: {:?}", path))?;
+                    (css, path.parent().map(Path::to_path_buf))
+                };
+                let css = rewrite_css_urls(&css, |url| {
+                    inline_url(url, css_dir.as_deref(), &mut inlined)
+                })?;
+                page.push_str(&format!("<style>\n{}\n</style>", css));
+            }
+            None => page.push_str(tag),
+        }
+        rest = &rest[end..];
+    }
+    page.push_str(rest);
+
+    // Only url()s inside <style> blocks, so prose mentioning `url(` is left alone
+    let mut styled = String::with_capacity(page.len());
+    let mut rest = page.as_str();
+    while let Some(start) = rest.find("<style>") {
+        let Some(end) = rest[start..].find("</style>").map(|end| start + end) else {
+            break;
+        };
+        styled.push_str(&rest[..start]);
+        styled.push_str(&rewrite_css_urls(&rest[start..end], |url| {
+            inline_url(url, source_dir, &mut inlined)
+        })?);
+        rest = &rest[end..];
+    }
+    styled.push_str(rest);
+
+    let page = rewrite_sources(&styled, |url| inline_url(url, source_dir, &mut inlined))?;
+    let (page, shared_images) = images::deduplicate_data_uris(&page);
+    if shared_images.is_empty() {
+        return Ok(page);
+    }
+    Ok(page.replacen(
+        "</head>",
+        &format!("<style>\n{}</style>\n</head>", shared_images),
+        1,
+    ))
+}
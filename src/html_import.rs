@@ -0,0 +1,63 @@
+fn find_ignore_case(haystack: &str, needle: &str, from: usize) -> Option<usize> {
+    // ASCII lowercasing keeps byte offsets intact
+    haystack[from..]
+        .to_ascii_lowercase()
+        .find(needle)
+        .map(|index| from + index)
+}
+
+fn remove_elements(html: &str, tag: &str) -> String {
+    let open = format!("<{}", tag);
+    let close = format!("</{}>", tag);
+    let mut result = String::with_capacity(html.len());
+    let mut position = 0;
+
+    while let Some(start) = find_ignore_case(html, &open, position) {
+        result.push_str(&html[position..start]);
+        match find_ignore_case(html, &close, start) {
+            Some(end) => position = end + close.len(),
+            None => {
+                position = html.len();
+                break;
+            }
+        }
+    }
+
+    result.push_str(&html[position..]);
+    result
+}
+
+fn body(html: &str) -> &str {
+    let start = find_ignore_case(html, "<body", 0)
+        .and_then(|body| html[body..].find('>').map(|end| body + end + 1))
+        .unwrap_or(0);
+    let end = find_ignore_case(html, "</body>", start).unwrap_or(html.len());
+    &html[start..end]
+}
+
+/// Turns an exported HTML page (e.g. from Confluence) into a raw HTML block
+/// that passes through the markdown pipeline unchanged. Scripts and styles
+/// are dropped, and blank lines are filled with a zero-width space because a
+/// blank line would end the HTML block.
+pub fn to_markdown_block(html: &str) -> String {
+    let content = remove_elements(body(html), "script");
+    let content = remove_elements(&content, "style");
+
+    let mut block = String::from("<div class=\"html-import\">\n");
+    for line in content.lines() {
+        if line.trim().is_empty() {
+            block.push_str("&#8203;");
+        } else {
+            block.push_str(line);
+        }
+        block.push('\n');
+    }
+    block.push_str("</div>\n");
+    block
+}
+
+pub fn is_html(path: &std::path::Path) -> bool {
+    path.extension().map_or(false, |ext| {
+        ext.eq_ignore_ascii_case("html") || ext.eq_ignore_ascii_case("htm")
+    })
+}
@@ -0,0 +1,250 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use pulldown_cmark::{Event, Tag};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+pub fn mime_type(path: &Path) -> &'static str {
+    match path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("webp") => "image/webp",
+        Some("bmp") => "image/bmp",
+        _ => "application/octet-stream",
+    }
+}
+
+pub fn is_remote(url: &str) -> bool {
+    url.contains("://") || url.starts_with("data:") || url.starts_with("//")
+}
+
+/// Encodes a local file as a `data:` URI so it renders without a file server.
+pub fn data_uri(path: &Path) -> Result<String> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read image: {:?}", path))?;
+    Ok(format!(
+        "data:{};base64,{}",
+        mime_type(path),
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    ))
+}
+
+/// Decodes `%20`-style escapes in a relative link target.
+pub fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let hex = (bytes[index] == b'%')
+            .then(|| text.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Calls `rewrite` with the target of every `![alt](target "title")` image
+/// and substitutes the target when it returns a replacement.
+pub fn rewrite_image_targets(
+    markdown: &str,
+    mut rewrite: impl FnMut(&str) -> Option<String>,
+) -> String {
+    let mut result = String::with_capacity(markdown.len());
+    let mut rest = markdown;
+
+    while let Some(start) = rest.find("![") {
+        let Some(close) = rest[start..].find("](").map(|i| start + i + 2) else {
+            break;
+        };
+        let Some(end) = rest[close..].find(')').map(|i| close + i) else {
+            break;
+        };
+
+        let inner = &rest[close..end];
+        let (target, title) = match inner.find(" \"") {
+            Some(space) => (&inner[..space], &inner[space..]),
+            None => (inner, ""),
+        };
+        let target = target.trim().trim_start_matches('<').trim_end_matches('>');
+
+        result.push_str(&rest[..close]);
+        match rewrite(target) {
+            Some(replacement) => {
+                result.push_str(&replacement);
+                result.push_str(title);
+            }
+            None => result.push_str(inner),
+        }
+        result.push(')');
+        rest = &rest[end + 1..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// A 1×1 transparent GIF standing in for a deduplicated image's `src`.
+const PLACEHOLDER: &str =
+    "data:image/gif;base64,R0lGODlhAQABAIAAAAAAAP///yH5BAEAAAAALAAAAAABAAEAAAIBRAA7";
+
+/// Moves `data:` URI images used more than once into a single CSS rule each
+/// (`img[data-asset="N"] { content: url(...) }`) so the base64 payload
+/// appears once in the HTML. Returns the rewritten HTML and the CSS. Running
+/// it again on a page that already has such rules (after inlining more
+/// images) numbers the new ones after them.
+pub fn deduplicate_data_uris(html: &str) -> (String, String) {
+    const SRC: &str = "src=\"data:";
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for (start, _) in html.match_indices(SRC) {
+        let uri_start = start + SRC.len() - "data:".len();
+        if let Some(end) = html[uri_start..].find('"') {
+            *counts.entry(&html[uri_start..uri_start + end]).or_default() += 1;
+        }
+    }
+    counts.remove(PLACEHOLDER);
+    let numbered = html.matches("img[data-asset=\"").count();
+
+    let mut assets: HashMap<&str, usize> = HashMap::new();
+    let mut css = String::new();
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find(SRC) {
+        let uri_start = start + SRC.len() - "data:".len();
+        let Some(end) = rest[uri_start..].find('"').map(|end| uri_start + end) else {
+            break;
+        };
+        let uri = &rest[uri_start..end];
+        result.push_str(&rest[..start]);
+
+        if counts.get(uri).copied().unwrap_or(0) > 1 {
+            let next = numbered + assets.len() + 1;
+            let index = *assets.entry(uri).or_insert_with(|| {
+                css.push_str(&format!(
+                    "img[data-asset=\"{}\"] {{ content: url(\"{}\"); }}\n",
+                    next, uri
+                ));
+                next
+            });
+            result.push_str(&format!("src=\"{}\" data-asset=\"{}\"", PLACEHOLDER, index));
+        } else {
+            result.push_str(&rest[start..=end]);
+        }
+        rest = &rest[end + 1..];
+    }
+
+    result.push_str(rest);
+    (result, css)
+}
+
+/// Inline styles and classes for an image's `{width=50% align=right .class}`
+/// attributes, or `None` when the text isn't an attribute block.
+fn image_attributes(block: &str) -> Option<(String, Vec<String>)> {
+    let mut styles = Vec::new();
+    let mut classes = vec!["sized-image".to_string()];
+
+    for attribute in block.split_whitespace() {
+        if let Some(class) = attribute.strip_prefix('.') {
+            classes.push(class.to_string());
+            continue;
+        }
+        let (key, value) = attribute.split_once('=')?;
+        let value = value.trim_matches('"');
+        match (key, value) {
+            ("width", _) => {
+                styles.push(format!("width: {}", value));
+                classes.push("image-width".to_string());
+            }
+            ("height", _) => {
+                styles.push(format!("height: {}", value));
+                classes.push("image-height".to_string());
+            }
+            ("align", "center") => classes.push("image-center".to_string()),
+            ("align" | "float", "left" | "right") => {
+                classes.push(format!("image-{}", value));
+            }
+            _ => return None,
+        }
+    }
+
+    Some((styles.join("; "), classes))
+}
+
+/// Applies `{width=50% align=right}` attribute blocks written right after
+/// an image: `width` and `height` size it, `align=left|right` floats it
+/// with text flowing around, `align=center` centers it, and `.name` adds a
+/// class. The image is wrapped in a styled `<span>` so later passes (figure
+/// numbering) still see it as an image.
+pub fn apply_attributes(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut result: Vec<Event> = Vec::with_capacity(events.len());
+    let mut image_start = None;
+    let mut events = events.into_iter().peekable();
+
+    while let Some(event) = events.next() {
+        match event {
+            Event::Start(Tag::Image(..)) => {
+                image_start = Some(result.len());
+                result.push(event);
+            }
+            Event::End(Tag::Image(..)) => {
+                result.push(event);
+                let Some(start) = image_start.take() else {
+                    continue;
+                };
+                let Some(Event::Text(text)) = events.peek() else {
+                    continue;
+                };
+                let Some(block) = text.strip_prefix('{') else {
+                    continue;
+                };
+                let Some(end) = block.find('}') else {
+                    continue;
+                };
+                let Some((style, classes)) = image_attributes(&block[..end]) else {
+                    continue;
+                };
+
+                let rest = block[end + 1..].to_string();
+                events.next();
+                result.insert(
+                    start,
+                    Event::Html(
+                        format!(
+                            "<span class=\"{}\" style=\"{}\">",
+                            classes.join(" "),
+                            style.replace('"', "")
+                        )
+                        .into(),
+                    ),
+                );
+                result.push(Event::Html("</span>".into()));
+                if !rest.is_empty() {
+                    result.push(Event::Text(rest.into()));
+                }
+            }
+            other => result.push(other),
+        }
+    }
+
+    result
+}
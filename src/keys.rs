@@ -0,0 +1,92 @@
+fn key_label(key: &str) -> String {
+    match key.to_ascii_lowercase().as_str() {
+        "ctrl" | "control" => "Ctrl".to_string(),
+        "alt" => "Alt".to_string(),
+        "shift" => "Shift".to_string(),
+        "cmd" | "command" => "Cmd".to_string(),
+        "meta" | "win" | "windows" => "Win".to_string(),
+        "opt" | "option" => "Option".to_string(),
+        "enter" | "return" => "Enter".to_string(),
+        "esc" | "escape" => "Esc".to_string(),
+        "del" | "delete" => "Del".to_string(),
+        "backspace" => "Backspace".to_string(),
+        "tab" => "Tab".to_string(),
+        "space" => "Space".to_string(),
+        "up" | "arrow-up" => "↑".to_string(),
+        "down" | "arrow-down" => "↓".to_string(),
+        "left" | "arrow-left" => "←".to_string(),
+        "right" | "arrow-right" => "→".to_string(),
+        _ => {
+            let mut chars = key.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        }
+    }
+}
+
+fn render_keys(combo: &str) -> String {
+    combo
+        .split('+')
+        .map(|key| format!("<kbd>{}</kbd>", key_label(key.trim())))
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+fn expand_line(line: &str) -> String {
+    let mut result = String::new();
+    let mut rest = line;
+
+    while let Some(start) = rest.find("++") {
+        // Leave inline code spans untouched
+        let before = &rest[..start];
+        if before.matches('`').count() % 2 == 1 {
+            match rest[start..].find('`') {
+                Some(tick) => {
+                    result.push_str(&rest[..start + tick + 1]);
+                    rest = &rest[start + tick + 1..];
+                    continue;
+                }
+                None => break,
+            }
+        }
+
+        let after = &rest[start + 2..];
+        match after.find("++") {
+            Some(end) if end > 0 && !after[..end].contains(char::is_whitespace) => {
+                result.push_str(before);
+                result.push_str(&render_keys(&after[..end]));
+                rest = &after[end + 2..];
+            }
+            _ => {
+                result.push_str(&rest[..start + 2]);
+                rest = after;
+            }
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Renders MkDocs-style `++Ctrl+Alt+Del++` key combinations as `<kbd>` elements.
+pub fn expand_keys(markdown: &str) -> String {
+    let mut result = String::new();
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        if line.trim().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+
+        if in_code_block || !line.contains("++") {
+            result.push_str(line);
+        } else {
+            result.push_str(&expand_line(line));
+        }
+        result.push('\n');
+    }
+
+    result
+}
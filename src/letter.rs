@@ -0,0 +1,143 @@
+use crate::frontmatter::Frontmatter;
+use crate::template::{self, escape_html, Variables};
+use clap::ValueEnum;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum LetterFormat {
+    /// A4 with the address window of DIN 5008 form B (DL/C6 window envelopes)
+    #[default]
+    Din,
+    /// US Letter with the address window of a #10 window envelope
+    Us,
+}
+
+impl LetterFormat {
+    fn date_format(self) -> &'static str {
+        match self {
+            LetterFormat::Din => "%d.%m.%Y",
+            LetterFormat::Us => "%B %-d, %Y",
+        }
+    }
+}
+
+/// Lines of a frontmatter address: a list, or one value with lines
+/// separated by ` | `.
+fn address_lines(frontmatter: &Frontmatter, key: &str) -> Vec<String> {
+    match frontmatter.list(key) {
+        [single] => single.split(" | ").map(str::to_string).collect(),
+        lines => lines.to_vec(),
+    }
+}
+
+/// Address lines with variables substituted, escaped once for HTML.
+fn address_html(lines: &[String], variables: &Variables, locale: Option<&str>) -> String {
+    lines
+        .iter()
+        .map(|line| escape_html(&template::render_document(line, variables, locale)))
+        .collect::<Vec<_>>()
+        .join("<br>")
+}
+
+/// The first-page block of a letter, from the frontmatter fields `sender`
+/// and `recipient` (addresses), `date` (today when unset) and `subject`.
+/// The recipient lands in the envelope window, with the sender's address
+/// in one small line above it as the return address.
+pub fn letter_head(
+    frontmatter: &Frontmatter,
+    format: LetterFormat,
+    variables: &Variables,
+    locale: Option<&str>,
+) -> String {
+    let sender = address_lines(frontmatter, "sender");
+    let recipient = address_lines(frontmatter, "recipient");
+    let date = match frontmatter.get("date") {
+        Some(date) => template::render_document(date, variables, locale),
+        None => template::render_document(
+            &format!("{{date:{}}}", format.date_format()),
+            variables,
+            locale,
+        ),
+    };
+
+    let mut html = String::from("<div class=\"letter-head\">\n");
+    if !sender.is_empty() {
+        html.push_str(&format!(
+            "<div class=\"letter-sender\">{}</div>\n",
+            address_html(&sender, variables, locale)
+        ));
+    }
+    html.push_str("<div class=\"letter-window\">");
+    if !sender.is_empty() {
+        html.push_str(&format!(
+            "<div class=\"letter-return\">{}</div>",
+            escape_html(&template::render_document(
+                &sender.join(" · "),
+                variables,
+                locale
+            ))
+        ));
+    }
+    html.push_str(&format!(
+        "<div class=\"letter-recipient\">{}</div></div>\n",
+        address_html(&recipient, variables, locale)
+    ));
+    html.push_str(&format!(
+        "<div class=\"letter-date\">{}</div>\n</div>\n",
+        escape_html(&date)
+    ));
+    if let Some(subject) = frontmatter.get("subject") {
+        html.push_str(&format!(
+            "<p class=\"letter-subject\"><strong>{}</strong></p>\n",
+            escape_html(&template::render_document(subject, variables, locale))
+        ));
+    }
+    html.push('\n');
+    html
+}
+
+/// Page size, margins, and block positions for the format. The first page
+/// has no top margin so positions are measured from the paper's edge.
+pub fn css(format: LetterFormat) -> String {
+    let (page, left_margin, head_height, sender_top, window, date) = match format {
+        // Form B: window 20mm from the left and 45mm from the top, 85×45mm;
+        // the information block starts 125mm from the left
+        LetterFormat::Din => (
+            "size: A4; margin: 20mm 20mm 20mm 25mm;",
+            "25mm",
+            "98.5mm",
+            "15mm",
+            ("45mm", "20mm", "85mm", "45mm"),
+            ("50mm", "125mm"),
+        ),
+        // #10 envelope window: 7/8in from the left, 2in from the top, 4×1.125in
+        LetterFormat::Us => (
+            "size: letter; margin: 1in;",
+            "1in",
+            "3.5in",
+            "0.5in",
+            ("2in", "0.875in", "4in", "1.125in"),
+            ("1.5in", "1in"),
+        ),
+    };
+    let (window_top, window_left, window_width, window_height) = window;
+    let (date_top, date_left) = date;
+    format!(
+        r#"
+        @page {{ {page} }}
+        @page :first {{ margin-top: 0; }}
+        body {{ max-width: none; margin: 0; padding: 0; }}
+        .letter-head {{ position: relative; height: {head_height}; }}
+        .letter-head > div {{ position: absolute; line-height: 1.3; }}
+        .letter-sender {{ top: {sender_top}; right: 0; text-align: right; font-size: 0.85em; }}
+        .letter-window {{
+            top: {window_top};
+            left: calc({window_left} - {left_margin});
+            width: {window_width};
+            height: {window_height};
+            overflow: hidden;
+        }}
+        .letter-return {{ font-size: 7pt; text-decoration: underline; margin-bottom: 2mm; }}
+        .letter-date {{ top: {date_top}; left: calc({date_left} - {left_margin}); }}
+        .letter-subject {{ margin-top: 0; }}"#
+    )
+}
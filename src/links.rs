@@ -0,0 +1,261 @@
+use crate::anchors;
+use crate::images;
+use crate::strings;
+use crate::template::escape_html;
+use clap::ValueEnum;
+use pulldown_cmark::{CowStr, Event, Tag};
+use std::collections::HashSet;
+use std::path::Path;
+
+fn is_external(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+/// What happens to links pointing at files that aren't part of the PDF.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum RelativeLinks {
+    /// Keep the link text, drop the link
+    #[default]
+    Text,
+    /// Leave the link as written (it only works next to the source files)
+    Keep,
+}
+
+/// `mailto:`, `tel:`, `http:` and similar: anything with a scheme.
+fn has_scheme(url: &str) -> bool {
+    url.split_once(':').map_or(false, |(scheme, _)| {
+        !scheme.is_empty()
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    })
+}
+
+/// Icon for a downloadable file type, or `None` for web pages and documents
+/// that open in the browser.
+fn file_icon(extension: &str) -> Option<&'static str> {
+    Some(match extension {
+        "zip" | "tar" | "gz" | "tgz" | "bz2" | "xz" | "7z" | "rar" => "🗜",
+        "mp3" | "wav" | "ogg" | "flac" | "m4a" | "aac" => "🎵",
+        "mp4" | "mov" | "mkv" | "avi" | "webm" => "🎬",
+        "xlsx" | "xls" | "ods" | "csv" => "📊",
+        "docx" | "doc" | "odt" | "rtf" | "pdf" | "epub" => "📄",
+        "pptx" | "ppt" | "odp" | "key" => "📽",
+        "exe" | "msi" | "dmg" | "pkg" | "deb" | "rpm" | "apk" | "iso" | "img" => "💾",
+        _ => return None,
+    })
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Adds a file-type icon and label after links to downloads (archives,
+/// audio, video, office documents, installers), with the file size for
+/// local files, which are looked up relative to the linking file under
+/// `root`. Printed copies then show what the reference is.
+pub fn annotate_downloads<'a>(events: Vec<Event<'a>>, root: Option<&Path>) -> Vec<Event<'a>> {
+    let mut result = Vec::with_capacity(events.len());
+    let mut source = String::new();
+    let mut annotation: Option<String> = None;
+
+    for event in events {
+        match &event {
+            Event::Html(html) if html.trim_start().starts_with("<section ") => {
+                if let Some(found) = anchors::section_source(html) {
+                    source = found;
+                }
+            }
+            Event::Start(Tag::Link(_, dest, _)) => {
+                let path = dest.split(['#', '?']).next().unwrap_or("");
+                let extension = Path::new(path)
+                    .extension()
+                    .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+                annotation = extension.and_then(|extension| {
+                    let icon = file_icon(&extension)?;
+                    let size = (!has_scheme(dest))
+                        .then_some(root)
+                        .flatten()
+                        .map(|root| root.join(anchors::resolve_relative(&source, path)))
+                        .and_then(|file| std::fs::metadata(file).ok())
+                        .map(|metadata| format!(", {}", format_size(metadata.len())))
+                        .unwrap_or_default();
+                    Some(format!(
+                        " <span class=\"file-annotation\">{} {}{}</span>",
+                        icon,
+                        extension.to_ascii_uppercase(),
+                        size
+                    ))
+                });
+            }
+            _ => {}
+        }
+        let closes_link = matches!(event, Event::End(Tag::Link(..)));
+        result.push(event);
+        if closes_link {
+            if let Some(html) = annotation.take() {
+                result.push(Event::Html(html.into()));
+            }
+        }
+    }
+
+    result
+}
+
+/// Handles links to files that didn't end up in the document (`LICENSE`,
+/// `scripts/build.sh`, markdown outside the input). With `link_base` they
+/// become absolute URLs, resolved from the linking file's path; otherwise
+/// `policy` decides. Anchors and links with a scheme (`mailto:`, `tel:`,
+/// `https:`) are left alone.
+pub fn apply_relative_link_policy<'a>(
+    events: Vec<Event<'a>>,
+    policy: RelativeLinks,
+    link_base: Option<&str>,
+) -> Vec<Event<'a>> {
+    if link_base.is_none() && policy == RelativeLinks::Keep {
+        return events;
+    }
+
+    let mut result = Vec::with_capacity(events.len());
+    let mut source = String::new();
+    let mut unlinked = false;
+
+    for event in events {
+        match event {
+            Event::Html(ref html) if html.trim_start().starts_with("<section ") => {
+                if let Some(found) = anchors::section_source(html) {
+                    source = found;
+                }
+                result.push(event);
+            }
+            Event::Start(Tag::Link(link_type, dest, title))
+                if !dest.is_empty() && !dest.starts_with('#') && !has_scheme(&dest) =>
+            {
+                match link_base {
+                    Some(base) => {
+                        let target = if let Some(absolute) = dest.strip_prefix('/') {
+                            absolute.to_string()
+                        } else {
+                            let (path, fragment) = dest.split_once('#').unwrap_or((&*dest, ""));
+                            let resolved = anchors::resolve_relative(&source, path)
+                                .to_string_lossy()
+                                .replace('\\', "/");
+                            if fragment.is_empty() {
+                                resolved
+                            } else {
+                                format!("{}#{}", resolved, fragment)
+                            }
+                        };
+                        let url = format!("{}/{}", base.trim_end_matches('/'), target);
+                        result.push(Event::Start(Tag::Link(link_type, CowStr::from(url), title)));
+                    }
+                    None => unlinked = true,
+                }
+            }
+            Event::End(Tag::Link(..)) if unlinked => unlinked = false,
+            other => result.push(other),
+        }
+    }
+
+    result
+}
+
+/// Every value of `attribute="..."` in rendered HTML.
+fn attribute_values<'a>(html: &'a str, attribute: &str) -> Vec<&'a str> {
+    let marker = format!(" {}=\"", attribute);
+    html.match_indices(&marker)
+        .filter_map(|(start, _)| {
+            let value_start = start + marker.len();
+            let end = html[value_start..].find('"')?;
+            Some(&html[value_start..value_start + end])
+        })
+        .collect()
+}
+
+/// In-document link targets (`href="#slug"`) that no element's `id` (or
+/// `name`) matches, each listed once.
+pub fn broken_anchors(html: &str) -> Vec<String> {
+    let targets: HashSet<String> = attribute_values(html, "id")
+        .into_iter()
+        .chain(attribute_values(html, "name"))
+        .map(images::percent_decode)
+        .collect();
+
+    let mut seen = HashSet::new();
+    attribute_values(html, "href")
+        .into_iter()
+        .filter_map(|href| href.strip_prefix('#'))
+        .filter(|anchor| !anchor.is_empty())
+        .map(images::percent_decode)
+        .filter(|anchor| !targets.contains(anchor) && seen.insert(anchor.clone()))
+        .collect()
+}
+
+/// Replaces external link targets with numbered superscripts pointing into a
+/// "Links" appendix, so printed copies keep the destination URLs.
+pub fn number_external_links<'a>(events: Vec<Event<'a>>) -> (Vec<Event<'a>>, Vec<String>) {
+    let mut urls: Vec<String> = Vec::new();
+    let mut current: Option<usize> = None;
+    let mut result = Vec::with_capacity(events.len());
+
+    for event in events {
+        match event {
+            Event::Start(Tag::Link(_, ref url, _)) if is_external(url) => {
+                let url = url.to_string();
+                let number = match urls.iter().position(|u| *u == url) {
+                    Some(index) => index + 1,
+                    None => {
+                        urls.push(url);
+                        urls.len()
+                    }
+                };
+                current = Some(number);
+            }
+            Event::End(Tag::Link(..)) if current.is_some() => {
+                let number = current.take().unwrap_or_default();
+                result.push(Event::Html(
+                    format!(
+                        "<sup class=\"link-ref\"><a href=\"#link-{0}\">[{0}]</a></sup>",
+                        number
+                    )
+                    .into(),
+                ));
+            }
+            other => result.push(other),
+        }
+    }
+
+    (result, urls)
+}
+
+pub fn appendix_html(urls: &[String]) -> String {
+    if urls.is_empty() {
+        return String::new();
+    }
+
+    let mut html = format!(
+        "<section class=\"link-appendix\">\n<h1>{}</h1>\n<ol>\n",
+        escape_html(&strings::get().links)
+    );
+    for (index, url) in urls.iter().enumerate() {
+        let url = escape_html(url);
+        html.push_str(&format!(
+            "<li id=\"link-{}\"><a href=\"{}\">{}</a></li>\n",
+            index + 1,
+            url,
+            url
+        ));
+    }
+    html.push_str("</ol>\n</section>\n");
+    html
+}
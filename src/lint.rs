@@ -0,0 +1,96 @@
+use crate::config::{HeadingCase, Lint};
+use crate::warnings;
+
+/// A lint rule: inspects one line (with whether it sits inside a code
+/// block) and returns a problem description when the line breaks the rule.
+type Rule = fn(&Lint, &str, bool) -> Option<String>;
+
+/// Every rule in the lint stage, by name. Rules do nothing unless their
+/// setting in the `[lint]` section of md2pdf.toml is present.
+const RULES: [(&str, Rule); 3] = [
+    ("code-line-length", code_line_length),
+    ("heading-case", heading_case),
+    ("banned-words", banned_words),
+];
+
+fn code_line_length(lint: &Lint, line: &str, in_code_block: bool) -> Option<String> {
+    let max = lint.max_code_line_length?;
+    let length = line.chars().count();
+    (in_code_block && length > max)
+        .then(|| format!("code line is {} characters (max {})", length, max))
+}
+
+/// Words kept lowercase in title case.
+const MINOR_WORDS: [&str; 12] = [
+    "a", "an", "and", "as", "at", "but", "by", "for", "in", "of", "on", "or",
+];
+
+fn heading_case(lint: &Lint, line: &str, in_code_block: bool) -> Option<String> {
+    let case = lint.heading_case?;
+    if in_code_block || !line.starts_with('#') {
+        return None;
+    }
+    let text = line.trim_start_matches('#');
+    if !text.starts_with(' ') {
+        return None;
+    }
+
+    let words: Vec<&str> = text
+        .split_whitespace()
+        .filter(|word| word.chars().next().map_or(false, char::is_alphabetic))
+        .collect();
+    let capitalized = |word: &str| word.chars().next().map_or(false, char::is_uppercase);
+    // Acronyms and names with inner capitals (API, GitHub) are fine either way
+    let plain = |word: &str| word.chars().skip(1).all(|c| !c.is_uppercase());
+
+    let wrong = words.iter().enumerate().find(|(index, word)| {
+        plain(word)
+            && match case {
+                HeadingCase::Sentence => *index > 0 && capitalized(word),
+                HeadingCase::Title => {
+                    let minor = *index > 0 && MINOR_WORDS.contains(&word.to_lowercase().as_str());
+                    capitalized(word) == minor
+                }
+            }
+    })?;
+    Some(format!(
+        "heading is not in {} case (\"{}\")",
+        match case {
+            HeadingCase::Sentence => "sentence",
+            HeadingCase::Title => "title",
+        },
+        wrong.1
+    ))
+}
+
+fn banned_words(lint: &Lint, line: &str, in_code_block: bool) -> Option<String> {
+    if in_code_block {
+        return None;
+    }
+    let found: Vec<&str> = line
+        .split(|c: char| !c.is_alphanumeric() && c != '\'' && c != '-')
+        .filter(|word| {
+            lint.banned_words
+                .iter()
+                .any(|banned| banned.eq_ignore_ascii_case(word))
+        })
+        .collect();
+    (!found.is_empty()).then(|| format!("avoid {}", found.join(", ")))
+}
+
+/// Runs every lint rule over a file's markdown and reports problems as
+/// warnings (`source:line: [rule] message`).
+pub fn check(markdown: &str, source: &str, lint: &Lint) {
+    let mut in_code_block = false;
+    for (index, line) in markdown.lines().enumerate() {
+        if line.trim().starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        for (name, rule) in RULES {
+            if let Some(message) = rule(lint, line, in_code_block) {
+                warnings::warn(format!("{}:{}: [{}] {}", source, index + 1, name, message));
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use crate::page_refs;
+use crate::strings;
+use crate::template::escape_html;
+use pulldown_cmark::{Event, HeadingLevel, Tag};
+
+/// Prefix of a paragraph right above a table that captions it (as in pandoc).
+const TABLE_CAPTION: &str = "Table:";
+
+fn listing_html(class: &str, title: &str, entries: &[(String, String)]) -> String {
+    let mut html = format!(
+        "<nav class=\"listing {}\">\n<h2>{}</h2>\n<ol>\n",
+        class, title
+    );
+    for (id, caption) in entries {
+        html.push_str(&format!(
+            "<li><a href=\"#{}\">{}</a>{}</li>\n",
+            id,
+            escape_html(caption),
+            page_refs::placeholder(id)
+        ));
+    }
+    html.push_str("</ol>\n</nav>\n");
+    html
+}
+
+/// Numbers figure captions (an image's alt text) and table captions (a
+/// `Table: ...` paragraph right above a table), and lists them with links
+/// after the document title.
+pub fn add_listings(events: Vec<Event<'_>>, figures: bool, tables: bool) -> Vec<Event<'_>> {
+    if !figures && !tables {
+        return events;
+    }
+    let strings = strings::get();
+
+    let mut result: Vec<Event> = Vec::with_capacity(events.len());
+    let mut figure_entries: Vec<(String, String)> = Vec::new();
+    let mut table_entries: Vec<(String, String)> = Vec::new();
+    let mut alt_text: Option<String> = None;
+    let mut index = 0;
+
+    while index < events.len() {
+        let event = events[index].clone();
+        index += 1;
+        match event {
+            Event::Start(Tag::Image(..)) if figures => {
+                alt_text = Some(String::new());
+                result.push(event);
+            }
+            Event::Text(ref text) if alt_text.is_some() => {
+                if let Some(alt) = alt_text.as_mut() {
+                    alt.push_str(text);
+                }
+                result.push(event);
+            }
+            Event::End(Tag::Image(..)) if alt_text.is_some() => {
+                result.push(event);
+                let alt = alt_text.take().unwrap_or_default();
+                if !alt.trim().is_empty() {
+                    let number = figure_entries.len() + 1;
+                    let id = format!("figure-{}", number);
+                    let caption = format!("{} {}: {}", strings.figure, number, alt.trim());
+                    result.push(Event::Html(
+                        format!(
+                            "<span class=\"caption figure-caption\" id=\"{}\">{}</span>",
+                            id,
+                            escape_html(&caption)
+                        )
+                        .into(),
+                    ));
+                    figure_entries.push((id, caption));
+                }
+            }
+            Event::Start(Tag::Paragraph) if tables => {
+                // Only a plain-text `Table: ...` paragraph directly followed by a table
+                // (or by a styled table's wrapper)
+                let caption = match &events[index..] {
+                    [Event::Text(text), Event::End(Tag::Paragraph), Event::Start(Tag::Table(_)), ..]
+                    | [Event::Text(text), Event::End(Tag::Paragraph), Event::Html(_), Event::Start(Tag::Table(_)), ..] => {
+                        text.strip_prefix(TABLE_CAPTION)
+                    }
+                    _ => None,
+                };
+                match caption {
+                    Some(caption) => {
+                        let number = table_entries.len() + 1;
+                        let id = format!("table-{}", number);
+                        let caption = format!("{} {}: {}", strings.table, number, caption.trim());
+                        result.push(Event::Html(
+                            format!(
+                                "<p class=\"caption table-caption\" id=\"{}\">{}</p>\n",
+                                id,
+                                escape_html(&caption)
+                            )
+                            .into(),
+                        ));
+                        table_entries.push((id, caption));
+                        index += 2;
+                    }
+                    None => result.push(event),
+                }
+            }
+            other => result.push(other),
+        }
+    }
+
+    let mut listings = String::new();
+    if figures && !figure_entries.is_empty() {
+        listings.push_str(&listing_html(
+            "list-of-figures",
+            &strings.list_of_figures,
+            &figure_entries,
+        ));
+    }
+    if tables && !table_entries.is_empty() {
+        listings.push_str(&listing_html(
+            "list-of-tables",
+            &strings.list_of_tables,
+            &table_entries,
+        ));
+    }
+    if listings.is_empty() {
+        return result;
+    }
+
+    // Right after the document title, or at the very top without one
+    let position = result
+        .iter()
+        .position(|event| {
+            matches!(event, Event::Html(html) if html.starts_with("</h1>"))
+                || matches!(event, Event::End(Tag::Heading(HeadingLevel::H1, ..)))
+        })
+        .map_or(0, |index| index + 1);
+    result.insert(position, Event::Html(listings.into()));
+    result
+}
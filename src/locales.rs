@@ -0,0 +1,69 @@
+use crate::template;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Whether a directory name looks like a language code: `de`, `pt-BR`,
+/// `zh_Hans`.
+fn is_locale_code(name: &str) -> bool {
+    let mut parts = name.split(|c| c == '-' || c == '_');
+    let language = parts.next().unwrap_or("");
+    let region = parts.next();
+    (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_lowercase())
+        && region.map_or(true, |region| {
+            (2..=4).contains(&region.len()) && region.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+        && parts.next().is_none()
+}
+
+/// Per-language subdirectories of the input (`docs/en`, `docs/de`, ...),
+/// sorted by code. Only names of supported locales count, so `api`, `img`,
+/// or `src` next to them are left alone.
+pub fn directories(input: &Path) -> Vec<(String, PathBuf)> {
+    let mut found: Vec<(String, PathBuf)> = fs::read_dir(input)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|path| {
+            let name = path.file_name()?.to_str()?.to_string();
+            (is_locale_code(&name) && date_locale(&name).is_some()).then_some((name, path))
+        })
+        .collect();
+    found.sort();
+    found
+}
+
+/// The locale used for dates: the code itself when known, otherwise its
+/// most common regional variant (`de` -> `de_DE`, `en` -> `en_US`).
+pub fn date_locale(code: &str) -> Option<String> {
+    if template::is_valid_locale(code) {
+        return Some(code.to_string());
+    }
+    let language = code.split(|c| c == '-' || c == '_').next()?;
+    let region = match language {
+        "en" => "US".to_string(),
+        "ja" => "JP".to_string(),
+        "zh" => "CN".to_string(),
+        "ko" => "KR".to_string(),
+        "sv" => "SE".to_string(),
+        "da" => "DK".to_string(),
+        "cs" => "CZ".to_string(),
+        "uk" => "UA".to_string(),
+        other => other.to_uppercase(),
+    };
+    let candidate = format!("{}_{}", language, region);
+    template::is_valid_locale(&candidate).then_some(candidate)
+}
+
+/// `manual.pdf` -> `manual.de.pdf`.
+pub fn output_path(output: &Path, code: &str) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    match output.extension() {
+        Some(extension) => {
+            output.with_file_name(format!("{}.{}.{}", stem, code, extension.to_string_lossy()))
+        }
+        None => output.with_file_name(format!("{}.{}", stem, code)),
+    }
+}
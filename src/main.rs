@@ -1,81 +1,651 @@
 use anyhow::{Context, Result};
 use base64::Engine;
-use clap::Parser;
-use headless_chrome::{Browser, LaunchOptions};
-use pulldown_cmark::{html, Options, Parser as MdParser};
-use std::collections::BTreeMap;
+use clap::{Parser, ValueEnum};
+use exit_codes::Failure;
+use pulldown_cmark::{html, Event, Options, Parser as MdParser};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use walkdir::WalkDir;
 
-#[derive(Parser)]
+mod abbreviations;
+mod anchors;
+mod appendix;
+mod archive;
+mod chrome;
+mod chunks;
+mod code;
+mod config;
+mod containers;
+mod critic;
+mod diffs;
+mod directives;
+mod docusaurus;
+mod epub;
+mod exit_codes;
+mod filters;
+mod flatten;
+mod fonts;
+mod footnotes;
+mod forms;
+mod frontmatter;
+mod git;
+mod hooks;
+mod html_assets;
+mod html_import;
+mod images;
+mod keys;
+mod letter;
+mod links;
+mod lint;
+mod listings;
+mod locales;
+mod manifest;
+mod maps;
+mod mdbook;
+mod media;
+mod mkdocs;
+mod music;
+mod network;
+mod notion;
+mod orientation;
+mod output;
+mod overrides;
+mod page_breaks;
+mod page_refs;
+mod pdf_tools;
+mod plugins;
+mod qr;
+mod readiness;
+mod redact;
+mod remote;
+mod screenshot;
+mod sections;
+mod server;
+mod signatures;
+mod slides;
+mod spellcheck;
+mod static_site;
+mod strings;
+mod tables;
+mod tasks;
+mod tempdir;
+mod template;
+mod terminal;
+mod theme;
+mod titles;
+mod todos;
+mod warnings;
+mod wiki;
+
+#[derive(Parser, Clone)]
 #[command(name = "markdown-to-pdf")]
 #[command(about = "Convert Markdown files or directories to PDF")]
 struct Args {
-    /// Input Markdown file or directory path
+    /// Input Markdown file, directory, .zip/.tar.gz archive, http(s) URL, or git repository URL
     #[arg(short, long)]
     input: PathBuf,
 
-    /// Output PDF file path
-    #[arg(short, long)]
-    output: PathBuf,
+    /// Output file path; repeat to render several formats at once (chosen by extension:
+    /// .pdf, .png, .jpg, .html, .epub)
+    #[arg(short, long, required = true)]
+    output: Vec<PathBuf>,
+
+    /// Format for outputs whose extension doesn't name one; image formats capture the
+    /// rendered document with Chrome screenshots
+    #[arg(long, value_enum, default_value_t = output::OutputFormat::Pdf)]
+    format: output::OutputFormat,
+
+    /// Keep the stylesheet and fonts inside .html outputs (default); with `--standalone=false`
+    /// they and local images go into an `assets/` directory next to the HTML instead
+    #[arg(
+        long,
+        default_value_t = true,
+        action = clap::ArgAction::Set,
+        num_args = 0..=1,
+        default_missing_value = "true"
+    )]
+    standalone: bool,
+
+    /// Make .html outputs a single portable file: every image, font, and stylesheet,
+    /// local or remote, is inlined as a data URI or style block
+    #[arg(long, conflicts_with = "standalone")]
+    self_contained: bool,
 
-    /// Enable dark mode theme
+    /// Lay out a single file as a business letter: sender, recipient, date, and subject
+    /// come from its frontmatter, with the address placed for window envelopes
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "din")]
+    letter: Option<letter::LetterFormat>,
+
+    /// Heading levels that start a new page, e.g. "h1,h2" (default: none; a single
+    /// file's frontmatter `page_break_before: [h1, h2]` applies without the flag).
+    /// `# Heading {.newpage}` or `{.no-newpage}` overrides it per heading
+    #[arg(long, value_delimiter = ',', value_parser = page_breaks::parse_level)]
+    page_break_before: Vec<u8>,
+
+    /// Print a talk outline as a deck: a new landscape page at every `---` rule and H2 heading
     #[arg(long)]
-    dark_mode: bool,
+    slides: bool,
+
+    /// Viewport width in pixels for image output (794 is A4 at 96 dpi)
+    #[arg(long, default_value_t = 794)]
+    image_width: u32,
+
+    /// Capture the whole document as one image instead of one image per page
+    #[arg(long)]
+    full_page: bool,
+
+    /// Enable dark mode theme; "auto" follows the frontmatter `theme`, MD2PDF_THEME,
+    /// or the system colour scheme. Without the flag, frontmatter `theme: dark` still applies
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "on")]
+    dark_mode: Option<theme::DarkMode>,
+
+    /// Font to embed as a fallback for emoji, arrows, and box drawing; repeat for several
+    /// (default: Noto Emoji/Symbols and DejaVu Sans Mono when installed)
+    #[arg(long = "symbol-font")]
+    symbol_fonts: Vec<PathBuf>,
+
+    /// Don't embed fallback fonts for emoji and symbols
+    #[arg(long, conflicts_with = "symbol_fonts")]
+    no_symbol_fonts: bool,
+
+    /// Print fenced code blocks instead of leaving them out; `[code.<language>] keep = true`
+    /// in md2pdf.toml keeps just one language
+    #[arg(long)]
+    keep_code_blocks: bool,
+
+    /// Font family for code, or a .ttf/.otf/.woff/.woff2 file to embed
+    #[arg(long)]
+    code_font: Option<String>,
+
+    /// Turn off ligatures (e.g. `->` drawn as an arrow) in code
+    #[arg(long)]
+    no_code_ligatures: bool,
+
+    /// Leave out background colors and images when printing (toner-friendly); code blocks
+    /// and elements styled with `print-color-adjust: exact` keep their shading
+    #[arg(long, conflicts_with = "dark_mode")]
+    no_print_background: bool,
+
+    /// Justify paragraphs and list items
+    #[arg(long)]
+    justify: bool,
+
+    /// Hyphenate body text automatically (uses --lang, default "en")
+    #[arg(long)]
+    hyphenate: bool,
+
+    /// Document language (e.g. "de", "en-GB"), used for hyphenation dictionaries
+    #[arg(long)]
+    lang: Option<String>,
+
+    /// Document title for directories (default: "Documentation" or the mdBook title);
+    /// supports the same placeholders as templates
+    #[arg(long)]
+    title: Option<String>,
+
+    /// How CriticMarkup suggestions ({++added++}, {--deleted--}, ...) are rendered
+    #[arg(long, value_enum, default_value_t)]
+    changes: critic::ChangesMode,
+
+    /// Keep ::redact:: spans and ::: redact blocks readable (highlighted) instead of blacking them out
+    #[arg(long)]
+    show_redacted: bool,
+
+    /// How table columns are sized; "fixed" keeps wide tables readable by wrapping cells
+    /// instead of squeezing columns. `<!-- widths: 20% 50% 30% -->` before a table sets
+    /// its column widths either way
+    #[arg(long, value_enum, default_value_t = tables::TableLayout::Auto)]
+    table_layout: tables::TableLayout,
+
+    /// Look of every table: striped, compact, bordered or borderless (comma-separated);
+    /// `<!-- table: striped compact -->` before a table styles just that table
+    #[arg(long, value_enum, value_delimiter = ',')]
+    table_style: Vec<tables::TableStyle>,
+
+    /// Tile server URL template for the base map under ```geojson blocks, e.g.
+    /// https://tile.openstreetmap.org/{z}/{x}/{y}.png (ignored with --offline)
+    #[arg(long)]
+    map_tiles: Option<String>,
+
+    /// What to do with ANSI color codes in code blocks (pasted terminal output);
+    /// console and ansi blocks are always printed, others need --keep-code-blocks
+    #[arg(long, value_enum, default_value_t)]
+    ansi: terminal::AnsiMode,
+
+    /// Number figures (images with alt text) and list them after the title
+    #[arg(long)]
+    list_of_figures: bool,
+
+    /// Number tables captioned with a "Table: ..." line above them and list them after the title
+    #[arg(long)]
+    list_of_tables: bool,
+
+    /// Replace external links with numbered references listed in a "Links" appendix
+    #[arg(long)]
+    link_appendix: bool,
+
+    /// Spell-check prose with hunspell using this dictionary (e.g. en_US) and report
+    /// unknown words as warnings
+    #[arg(long, value_name = "LANGUAGE")]
+    spellcheck: Option<String>,
+
+    /// Extra words the spell checker accepts, one per line
+    #[arg(long, requires = "spellcheck")]
+    dictionary: Option<PathBuf>,
+
+    /// Links to files that aren't part of the PDF (e.g. LICENSE, scripts/build.sh)
+    #[arg(long, value_enum, default_value_t)]
+    relative_links: links::RelativeLinks,
+
+    /// Turn links to files outside the PDF into absolute URLs under this base,
+    /// e.g. https://github.com/org/repo/blob/main/
+    #[arg(long)]
+    link_base: Option<String>,
+
+    /// Print the URL in parentheses after each external link
+    #[arg(long)]
+    print_urls: bool,
+
+    /// Don't generate a heading from each file name in directory mode
+    #[arg(long)]
+    no_file_headings: bool,
+
+    /// Where generated file headings take their text from
+    #[arg(long, value_enum, default_value_t = titles::SectionTitles::Filename)]
+    section_titles: titles::SectionTitles,
+
+    /// Heading for files at the top of the input directory (default: none)
+    #[arg(long)]
+    root_section_title: Option<String>,
+
+    /// Where files at the top of the input directory go in directory mode
+    #[arg(long, value_enum, default_value_t = RootPlacement::Sorted)]
+    root_files: RootPlacement,
+
+    /// Separator inserted between files in directory mode
+    #[arg(long, value_enum, default_value_t = FileSeparator::Rule)]
+    file_separator: FileSeparator,
+
+    /// Where footnote definitions are placed when combining a directory
+    #[arg(long, value_enum, default_value_t = footnotes::FootnotePlacement::PerFile)]
+    footnote_placement: footnotes::FootnotePlacement,
+
+    /// Don't add ↩ links from footnotes back to where they are referenced
+    #[arg(long)]
+    no_footnote_backrefs: bool,
+
+    /// How to handle collapsible <details> blocks
+    #[arg(long, value_enum, default_value_t = flatten::DetailsMode::Expand)]
+    details: flatten::DetailsMode,
+
+    /// How to handle HTML comments in the markdown
+    #[arg(long, value_enum, default_value_t = directives::CommentMode::Strip)]
+    comments: directives::CommentMode,
+
+    /// Append an "Open Items" section listing TODO/FIXME markers and unchecked tasks
+    #[arg(long)]
+    collect_todos: bool,
+
+    /// Render a task list completion summary table at the start of the document
+    #[arg(long)]
+    task_summary: bool,
+
+    /// Locale for dates in templates and hyphenation, e.g. de-DE. When the input directory
+    /// has per-language subdirectories (docs/en, docs/de), the matching one is converted
+    #[arg(long)]
+    locale: Option<String>,
+
+    /// Build one PDF per language subdirectory of the input (docs/en, docs/de, ...),
+    /// named e.g. manual.de.pdf
+    #[arg(long, conflicts_with = "locale")]
+    all_locales: bool,
+
+    /// WASM plugin that transforms each file's markdown (repeatable)
+    #[arg(long = "plugin")]
+    plugins: Vec<PathBuf>,
+
+    /// Rhai script that rewrites the markdown event stream (repeatable)
+    #[arg(long = "filter")]
+    filters: Vec<PathBuf>,
+
+    /// Clean up Notion exports: strip page ids, embed images, render CSV databases as tables
+    #[arg(long)]
+    notion: bool,
+
+    /// Also convert .html/.htm pages (e.g. Confluence exports) found in the input directory
+    #[arg(long)]
+    include_html: bool,
+
+    /// Order files by Docusaurus _category_.json and sidebar_position metadata
+    /// (detected automatically next to a docusaurus.config.js)
+    #[arg(long)]
+    docusaurus: bool,
+
+    /// GitHub wiki clone: order by _Sidebar.md, resolve [[Page]] links, skip sidebar/footer
+    /// (detected automatically when _Sidebar.md or _Footer.md exists)
+    #[arg(long)]
+    wiki: bool,
+
+    /// Hugo/Jekyll content: expand shortcodes and order by weight/title frontmatter
+    /// (detected automatically from hugo.toml or _config.yml)
+    #[arg(long)]
+    static_site: bool,
+
+    /// Branch, tag, or commit to check out when the input is a git repository
+    #[arg(long = "ref")]
+    git_ref: Option<String>,
+
+    /// Fail on any warning: missing images, unresolved includes, unknown code block
+    /// languages, skipped files, or content wider than the page
+    #[arg(long)]
+    strict: bool,
+
+    /// Serve the document and the files around it from a localhost server while
+    /// rendering, so relative CSS, images, and fonts in raw HTML resolve
+    #[arg(long)]
+    asset_server: bool,
+
+    /// Wait until an element matching this CSS selector exists before rendering
+    #[arg(long)]
+    wait_for_selector: Option<String>,
+
+    /// Wait until no new resources have loaded for 500 ms and web fonts are ready
+    #[arg(long)]
+    wait_network_idle: bool,
+
+    /// Extra delay in milliseconds before rendering
+    #[arg(long, default_value_t = 0)]
+    render_delay_ms: u64,
+
+    /// Disable JavaScript in the rendering page (for untrusted input)
+    #[arg(long)]
+    no_js: bool,
+
+    /// Block all network requests from the rendering page
+    #[arg(long)]
+    offline: bool,
+
+    /// URL pattern the rendering page may fetch, `*` as wildcard (repeatable);
+    /// everything else is blocked
+    #[arg(long = "allow-url")]
+    allow_urls: Vec<String>,
+
+    /// URL pattern the rendering page must not fetch, `*` as wildcard (repeatable)
+    #[arg(long = "deny-url")]
+    deny_urls: Vec<String>,
+
+    /// Chrome profile directory to reuse across runs (default: a fresh temporary one)
+    #[arg(long)]
+    chrome_user_data_dir: Option<PathBuf>,
+
+    /// Run Chrome without its sandbox (needed in some containers running as root)
+    #[arg(long)]
+    no_sandbox: bool,
+
+    /// How often to relaunch Chrome and retry when it crashes while rendering
+    #[arg(long, default_value_t = 2)]
+    chrome_retries: u32,
+
+    /// Render the PDF this many top-level chapters at a time and merge the parts
+    /// (for documents too large for one Chrome tab; requires qpdf)
+    #[arg(long, default_value_t = 0)]
+    chunk_chapters: usize,
+
+    /// Config file (defaults to md2pdf.toml next to the input or in the working directory)
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Run hooks, plugins, and filters from an md2pdf.toml inside a cloned repository or
+    /// extracted archive (they are ignored by default)
+    #[arg(long)]
+    trust_config: bool,
+
+    /// Set when the input was cloned or extracted to a temporary directory
+    #[arg(skip)]
+    untrusted_input: bool,
 
-    /// Document title for directories
-    #[arg(long, default_value = "Documentation")]
-    title: String,
+    /// Shell command to run before collecting files (repeatable)
+    #[arg(long = "pre-hook")]
+    pre_hooks: Vec<String>,
+
+    /// Shell command to run after the PDF is written (repeatable); MD2PDF_OUTPUT holds its path
+    #[arg(long = "post-hook")]
+    post_hooks: Vec<String>,
+
+    /// Read name, version, and description from a nearby Cargo.toml or package.json
+    #[arg(long)]
+    manifest_vars: bool,
+
+    /// HTML template for the page header; supports {title}, {date:...}, {git.*} and {project.*}
+    #[arg(long)]
+    header_template: Option<String>,
+
+    /// HTML template for the page footer; supports {title}, {date:...}, {git.*} and {project.*}
+    #[arg(long)]
+    footer_template: Option<String>,
+
+    /// Header template for the first page instead of --header-template ("" for none)
+    #[arg(long)]
+    first_page_header_template: Option<String>,
+
+    /// Footer template for the first page instead of --footer-template ("" for none)
+    #[arg(long)]
+    first_page_footer_template: Option<String>,
+
+    /// No header or footer on the first page, e.g. for a title page
+    #[arg(long, conflicts_with_all = ["first_page_header_template", "first_page_footer_template"])]
+    plain_first_page: bool,
+
+    /// Append a revision history table generated from git log
+    #[arg(long)]
+    revision_history: bool,
+
+    /// Write a JSON list of headings (id, level, title) with the PDF page each lands on
+    #[arg(long, conflicts_with = "chunk_chapters")]
+    page_map: Option<PathBuf>,
+
+    /// Also write a first-page preview image (.png or .jpg) of the generated PDF
+    #[arg(long)]
+    thumbnail: Option<PathBuf>,
+
+    /// Thumbnail width in pixels
+    #[arg(long, default_value_t = 400)]
+    thumbnail_width: u32,
+
+    /// Print 2, 4, 8, 9 or 16 pages on each A4 sheet for compact handouts (needs pdfjam)
+    #[arg(long, conflicts_with = "page_map")]
+    nup: Option<u32>,
+
+    /// Impose pages for saddle-stitch booklets: two pages per landscape sheet, in folding
+    /// order, for duplex printing flipped on the short edge (needs pdfjam)
+    #[arg(long, conflicts_with_all = ["nup", "page_map"])]
+    booklet: bool,
+
+    /// Pages per folded bundle for thick booklets (a multiple of 4); one bundle by default
+    #[arg(long, requires = "booklet")]
+    booklet_signature: Option<u32>,
+
+    /// Optimize and linearize the generated PDF for fast web viewing
+    #[arg(long)]
+    optimize: bool,
+
+    /// Digitally sign the generated PDF (PAdES)
+    #[arg(long, requires = "cert")]
+    sign: bool,
+
+    /// PKCS#12 certificate bundle used for signing; its password is read
+    /// from the MD2PDF_CERT_PASSWORD environment variable
+    #[arg(long)]
+    cert: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone)]
 struct MarkdownFile {
     path: PathBuf,
-    content: String,
+    /// Shared so cloning a file or handing it between passes never copies the text
+    content: Arc<str>,
     name: String,
+    relative_path: PathBuf,
+    frontmatter: frontmatter::Frontmatter,
 }
 
-fn collect_markdown_files(dir: &Path) -> Result<BTreeMap<String, Vec<MarkdownFile>>> {
-    let mut files_by_dir = BTreeMap::new();
+/// Files grouped by directory, in document order.
+type FileGroups = Vec<(String, Vec<MarkdownFile>)>;
 
-    for entry in WalkDir::new(dir)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .filter(|e| e.path().extension().map_or(false, |ext| ext == "md"))
-    {
-        let path = entry.path();
-        let content =
-            fs::read_to_string(path).with_context(|| format!("Failed to read file: {:?}", path))?;
-
-        let name = path
-            .file_stem()
-            .and_then(|n| n.to_str())
-            .unwrap_or("Untitled")
-            .to_string();
-
-        let parent_dir = path
-            .parent()
-            .and_then(|p| p.strip_prefix(dir).ok())
-            .and_then(|p| p.to_str())
-            .unwrap_or("")
-            .to_string();
-
-        let dir_key = if parent_dir.is_empty() {
-            "Root".to_string()
+#[derive(Debug, Default)]
+struct CollectOptions {
+    resolve_includes: bool,
+    notion: bool,
+    include_html: bool,
+}
+
+/// Reads and prepares one file, returning its directory key and contents.
+fn read_markdown_file(
+    dir: &Path,
+    path: &Path,
+    collect_options: &CollectOptions,
+) -> Result<(String, MarkdownFile)> {
+    let is_csv = path.extension().map_or(false, |ext| ext == "csv");
+    let raw_content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read file: {:?}", path))?;
+    let (frontmatter, body) = frontmatter::split(&raw_content);
+    let mut content = body.to_string();
+    if collect_options.resolve_includes {
+        content = directives::resolve_includes(&content, path.parent().unwrap_or(dir), dir)?;
+    }
+    if html_import::is_html(path) {
+        content = html_import::to_markdown_block(&raw_content);
+    }
+    if collect_options.notion {
+        content = if is_csv {
+            notion::csv_to_markdown(&raw_content)
         } else {
-            parent_dir.replace('/', " > ")
+            notion::resolve_assets(&content, path.parent().unwrap_or(dir))
         };
+    }
 
+    let name = path
+        .file_stem()
+        .and_then(|n| n.to_str())
+        .unwrap_or("Untitled")
+        .to_string();
+    let name = if collect_options.notion {
+        notion::clean_name(&name)
+    } else {
+        name
+    };
+
+    let parent_dir = path
+        .parent()
+        .and_then(|p| p.strip_prefix(dir).ok())
+        .and_then(|p| p.to_str())
+        .unwrap_or("")
+        .to_string();
+
+    let dir_key = if parent_dir.is_empty() {
+        "Root".to_string()
+    } else if collect_options.notion {
+        parent_dir
+            .split('/')
+            .map(notion::clean_name)
+            .collect::<Vec<_>>()
+            .join(" > ")
+    } else {
+        parent_dir.replace('/', " > ")
+    };
+
+    Ok((
+        dir_key,
+        MarkdownFile {
+            path: path.to_path_buf(),
+            content: content.into(),
+            name,
+            relative_path: path.strip_prefix(dir).unwrap_or(path).to_path_buf(),
+            frontmatter,
+        },
+    ))
+}
+
+/// Runs the per-file steps shared by directory and book mode: plugins,
+/// frontmatter overrides (confined to `input`), warnings, lint, spellcheck,
+/// and, when `open_items` is given, collecting TODOs.
+fn prepare_files(
+    mut files: Vec<&mut MarkdownFile>,
+    input: &Path,
+    plugins: &mut plugins::Plugins,
+    lint: &config::Lint,
+    spellcheck: Option<&spellcheck::Spellcheck>,
+    open_items: Option<&mut Vec<todos::OpenItem>>,
+) -> Result<()> {
+    // Plugin instances aren't shareable across threads, so they run in order
+    for file in files.iter_mut() {
+        file.content = plugins.transform(file.content.to_string())?.into();
+    }
+
+    files.par_iter_mut().try_for_each(|file| -> Result<()> {
+        let base_dir = file.path.parent().unwrap_or(input);
+        let scope = format!(
+            "section[data-source=\"{}\"]",
+            file.relative_path.to_string_lossy().replace('\\', "/")
+        );
+        file.content =
+            overrides::apply(&file.content, &file.frontmatter, base_dir, input, &scope)?.into();
+        let source = file.relative_path.display().to_string();
+        warnings::check_markdown(&file.content, base_dir, &source);
+        lint::check(&file.content, &source, lint);
+        if let Some(spellcheck) = spellcheck {
+            spellcheck::check(&file.content, &source, spellcheck)?;
+        }
+        Ok(())
+    })?;
+
+    if let Some(open_items) = open_items {
+        for file in files.iter_mut() {
+            file.content = todos::collect_open_items(
+                &file.content,
+                &file.relative_path.display().to_string(),
+                open_items,
+            )
+            .into();
+        }
+    }
+
+    Ok(())
+}
+
+fn collect_markdown_files(dir: &Path, collect_options: &CollectOptions) -> Result<FileGroups> {
+    let paths: Vec<PathBuf> = WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| {
+            e.map_err(|err| warnings::warn(format!("Skipped unreadable entry: {}", err)))
+                .ok()
+        })
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| {
+            e.path().extension().map_or(false, |ext| {
+                ext == "md" || (collect_options.notion && ext == "csv")
+            }) || (collect_options.include_html && html_import::is_html(e.path()))
+        })
+        // Notion exports every database twice; keep the plain export only
+        .filter(|e| !e.path().to_string_lossy().ends_with("_all.csv"))
+        .map(|e| e.into_path())
+        .collect();
+
+    // Reading and preparing files is independent per file, so spread it over all cores
+    let files: Vec<(String, MarkdownFile)> = paths
+        .par_iter()
+        .map(|path| read_markdown_file(dir, path, collect_options))
+        .collect::<Result<_>>()?;
+
+    let mut files_by_dir = BTreeMap::new();
+    for (dir_key, file) in files {
         files_by_dir
             .entry(dir_key)
             .or_insert_with(Vec::new)
-            .push(MarkdownFile {
-                path: path.to_path_buf(),
-                content,
-                name,
-            });
+            .push(file);
     }
 
     // Sort files alphabetically within each directory
@@ -83,47 +653,287 @@ fn collect_markdown_files(dir: &Path) -> Result<BTreeMap<String, Vec<MarkdownFil
         files.sort_by(|a, b| a.name.cmp(&b.name));
     }
 
-    Ok(files_by_dir)
+    Ok(files_by_dir.into_iter().collect())
+}
+
+/// The id (and class) of the `<section>` wrapping a file in directory mode,
+/// e.g. `doc-guide-install` for `guide/install.md`.
+fn section_id(relative_path: &Path) -> String {
+    let source = relative_path.to_string_lossy().replace('\\', "/");
+    format!(
+        "doc-{}",
+        anchors::slugify(&source.trim_end_matches(".md").replace('/', "-"))
+    )
+}
+
+/// The `<section>` tag wrapping a combined file, so custom CSS can target it
+/// and the HTML maps back to its source.
+fn section_open(file: &MarkdownFile) -> String {
+    let id = section_id(&file.relative_path);
+    format!(
+        "<section id=\"{}\" class=\"doc {}{}{}\" data-source=\"{}\">\n\n",
+        id,
+        id,
+        theme::section_class(&file.frontmatter),
+        orientation::section_class(&file.frontmatter),
+        template::escape_html(&file.relative_path.to_string_lossy().replace('\\', "/"))
+    )
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum FileSeparator {
+    /// No separator between files
+    None,
+    /// A horizontal rule
+    #[default]
+    Rule,
+    /// Start every file on a new page
+    Pagebreak,
+}
+
+impl FileSeparator {
+    fn markdown(self) -> &'static str {
+        match self {
+            FileSeparator::None => "\n\n",
+            FileSeparator::Rule => "\n\n---\n\n",
+            FileSeparator::Pagebreak => "\n\n<div class=\"page-break\"></div>\n\n",
+        }
+    }
+}
+
+/// Where files at the top of the input directory go relative to subdirectories.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum RootPlacement {
+    /// Sorted among the directories as "Root"
+    #[default]
+    Sorted,
+    /// Before all directories
+    First,
+    /// After all directories
+    Last,
+}
+
+/// Moves the group of top-level files to the front or back.
+fn place_root_files(files_by_dir: &mut FileGroups, placement: RootPlacement) {
+    let Some(index) = files_by_dir.iter().position(|(dir, _)| dir == "Root") else {
+        return;
+    };
+    match placement {
+        RootPlacement::Sorted => {}
+        RootPlacement::First => {
+            let root = files_by_dir.remove(index);
+            files_by_dir.insert(0, root);
+        }
+        RootPlacement::Last => {
+            let root = files_by_dir.remove(index);
+            files_by_dir.push(root);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct CombineOptions {
+    footnotes: footnotes::FootnotePlacement,
+    file_headings: bool,
+    separator: FileSeparator,
+    section_titles: titles::SectionTitles,
+    root_title: Option<String>,
+    /// `[sections] appendices` patterns
+    appendices: Vec<String>,
+}
+
+fn combine_options(args: &Args, config: &config::Config) -> CombineOptions {
+    CombineOptions {
+        footnotes: args.footnote_placement,
+        file_headings: !args.no_file_headings,
+        separator: args.file_separator,
+        section_titles: args.section_titles,
+        root_title: args.root_section_title.clone(),
+        appendices: config.sections.appendices.clone(),
+    }
+}
+
+/// Writes files as sections of the combined document, numbering footnotes
+/// and appendices across all of them. Shared by directory and book mode.
+struct SectionWriter<'a> {
+    options: &'a CombineOptions,
+    file_index: usize,
+    appendix_count: usize,
+    chapter_notes: String,
+    document_notes: String,
+}
+
+impl<'a> SectionWriter<'a> {
+    fn new(options: &'a CombineOptions) -> Self {
+        SectionWriter {
+            options,
+            file_index: 0,
+            appendix_count: 0,
+            chapter_notes: String::new(),
+            document_notes: String::new(),
+        }
+    }
+
+    /// Appends `file` as a section whose heading sits at `file_level`.
+    fn file(&mut self, combined: &mut String, mut file: MarkdownFile, file_level: usize) {
+        use footnotes::FootnotePlacement;
+
+        let options = self.options;
+        let source = file.relative_path.to_string_lossy().replace('\\', "/");
+        combined.push_str(&section_open(&file));
+
+        let appendix_letter =
+            appendix::is_appendix(&source, &file.frontmatter, &options.appendices).then(|| {
+                self.appendix_count += 1;
+                appendix::letter(self.appendix_count)
+            });
+        let file_heading = |heading: String| match &appendix_letter {
+            Some(letter) => appendix::title(letter, &heading),
+            None => heading,
+        };
+
+        let heading_offset = if options.file_headings {
+            let heading = titles::section_title(
+                &file.name,
+                &file.content,
+                &file.frontmatter,
+                options.section_titles,
+            );
+            // A first H1 that repeats the section heading is dropped, and
+            // `--section-titles promote` always takes it
+            let promoted = titles::take_first_h1(&file.content).filter(|(first, _)| {
+                options.section_titles == titles::SectionTitles::Promote || *first == heading
+            });
+            let (heading, offset) = match promoted {
+                Some((heading, rest)) => {
+                    file.content = rest.into();
+                    // The remaining headings sit below the promoted H1
+                    (heading, file_level - 1)
+                }
+                None => (heading, file_level),
+            };
+            combined.push_str(&format!(
+                "{} {}\n\n",
+                "#".repeat(file_level),
+                file_heading(heading)
+            ));
+            offset
+        } else {
+            // The file's own H1 takes the place of the generated heading
+            file_level - 1
+        };
+
+        self.file_index += 1;
+        let (body, notes) =
+            footnotes::extract_definitions(&file.content, &format!("f{}", self.file_index));
+
+        let mut processed_content = preprocess_markdown(&body, heading_offset);
+        if let Some(letter) = &appendix_letter {
+            // A file H1 standing in for the file heading lands on `file_level`
+            processed_content = appendix::number_headings(&processed_content, letter, file_level);
+        }
+        combined.push_str(&processed_content);
+
+        match options.footnotes {
+            FootnotePlacement::PerFile => {
+                combined.push('\n');
+                combined.push_str(&notes);
+            }
+            FootnotePlacement::PerChapter => self.chapter_notes.push_str(&notes),
+            FootnotePlacement::EndOfDocument => self.document_notes.push_str(&notes),
+        }
+        combined.push_str("\n</section>\n");
+        combined.push_str(options.separator.markdown());
+    }
+
+    /// Appends the notes collected since the last chapter under a heading at `level`.
+    fn chapter_notes(&mut self, combined: &mut String, level: usize) {
+        let notes = std::mem::take(&mut self.chapter_notes);
+        if !notes.trim().is_empty() {
+            combined.push_str(&format!(
+                "{} {}\n\n",
+                "#".repeat(level.min(6)),
+                strings::get().notes
+            ));
+            combined.push_str(&notes);
+            combined.push_str(self.options.separator.markdown());
+        }
+    }
+
+    /// Appends the notes collected for the end of the document.
+    fn document_notes(self, combined: &mut String) {
+        if !self.document_notes.trim().is_empty() {
+            combined.push_str(&format!("# {}\n\n", strings::get().notes));
+            combined.push_str(&self.document_notes);
+        }
+    }
 }
 
 fn create_combined_markdown(
-    files_by_dir: BTreeMap<String, Vec<MarkdownFile>>,
+    files_by_dir: FileGroups,
     title: &str,
+    preface: &str,
+    combine_options: &CombineOptions,
 ) -> String {
     let mut combined = String::new();
+    let mut sections = SectionWriter::new(combine_options);
+
+    combined.push_str(&format!("# {}\n\n", template::escape_html(title)));
+    combined.push_str(preface);
 
-    combined.push_str(&format!("# {}\n\n", title));
+    let mut previous_components: Vec<String> = Vec::new();
 
     for (dir_name, files) in files_by_dir {
-        if dir_name != "Root" {
-            combined.push_str(&format!("# {}\n\n", dir_name));
+        // Nested directories map to deeper heading levels, so emit a heading
+        // for every path component that differs from the previous section
+        let components: Vec<String> = if dir_name == "Root" {
+            // Top-level files only get a heading when the section is named
+            combine_options.root_title.iter().cloned().collect()
+        } else {
+            dir_name.split(" > ").map(str::to_string).collect()
+        };
+        let shared = components
+            .iter()
+            .zip(&previous_components)
+            .take_while(|(a, b)| a == b)
+            .count();
+        for (depth, component) in components.iter().enumerate().skip(shared) {
+            combined.push_str(&format!(
+                "{} {}\n\n",
+                "#".repeat((depth + 1).min(6)),
+                component
+            ));
         }
+        let file_level = (components.len().max(1) + 1).min(6);
+        previous_components = components;
 
         for file in files {
-            combined.push_str(&format!("## {}\n\n", file.name));
-
-            let processed_content = preprocess_markdown(&file.content);
-            combined.push_str(&processed_content);
-            combined.push_str("\n\n---\n\n");
+            sections.file(&mut combined, file, file_level);
         }
+        sections.chapter_notes(&mut combined, file_level);
     }
 
+    sections.document_notes(&mut combined);
     combined
 }
 
 fn preprocess_markdown_single_file(markdown: &str) -> String {
     let mut result = String::new();
     let mut in_code_block = false;
+    let mut keep_code_block = false;
 
     for line in markdown.lines() {
         let trimmed = line.trim();
 
         if trimmed.starts_with("```") {
+            if !in_code_block {
+                keep_code_block = code::keeps(code::fence_language(trimmed));
+            }
             in_code_block = !in_code_block;
-            continue;
-        }
-
-        if in_code_block {
+            if !keep_code_block {
+                continue;
+            }
+        } else if in_code_block && !keep_code_block {
             continue;
         }
 
@@ -134,19 +944,31 @@ fn preprocess_markdown_single_file(markdown: &str) -> String {
     result
 }
 
-fn preprocess_markdown(markdown: &str) -> String {
+fn preprocess_markdown(markdown: &str, heading_offset: usize) -> String {
     let mut result = String::new();
     let mut in_code_block = false;
+    let mut keep_code_block = false;
 
     for line in markdown.lines() {
         let trimmed = line.trim();
 
         if trimmed.starts_with("```") {
+            if !in_code_block {
+                keep_code_block = code::keeps(code::fence_language(trimmed));
+            }
             in_code_block = !in_code_block;
+            if keep_code_block {
+                result.push_str(line);
+                result.push('\n');
+            }
             continue;
         }
 
         if in_code_block {
+            if keep_code_block {
+                result.push_str(line);
+                result.push('\n');
+            }
             continue;
         }
 
@@ -155,30 +977,276 @@ fn preprocess_markdown(markdown: &str) -> String {
             let hash_count = trimmed.chars().take_while(|&c| c == '#').count();
             let rest_of_line = &trimmed[hash_count..];
 
-            // Add 2 levels to maintain document structure
-            let new_line = format!("{}{}", "#".repeat(hash_count + 2), rest_of_line);
+            // Shift below the generated file heading, capped at the deepest level
+            let new_line = format!(
+                "{}{}",
+                "#".repeat((hash_count + heading_offset).min(6)),
+                rest_of_line
+            );
             result.push_str(&new_line);
         } else {
             result.push_str(line);
         }
         result.push('\n');
     }
-
-    result
-}
-
-fn markdown_to_html(markdown: &str, dark_mode: bool) -> String {
-    let processed_markdown = preprocess_markdown_single_file(markdown);
-
-    let mut options = Options::empty();
-    options.insert(Options::ENABLE_STRIKETHROUGH);
-    options.insert(Options::ENABLE_TABLES);
-    options.insert(Options::ENABLE_FOOTNOTES);
-    options.insert(Options::ENABLE_TASKLISTS);
-
-    let parser = MdParser::new_ext(&processed_markdown, options);
-    let mut html_output = String::new();
-    html::push_html(&mut html_output, parser);
+
+    result
+}
+
+#[derive(Debug, Default)]
+struct HtmlOptions {
+    dark_mode: bool,
+    /// Style sections whose frontmatter sets their own `theme`
+    section_themes: bool,
+    link_appendix: bool,
+    print_urls: bool,
+    details: flatten::DetailsMode,
+    comments: directives::CommentMode,
+    filters: Vec<PathBuf>,
+    base_url: Option<String>,
+    footnote_backrefs: bool,
+    symbol_fonts: Vec<PathBuf>,
+    justify: bool,
+    hyphenate: bool,
+    lang: Option<String>,
+    print_background: bool,
+    code_font: Option<String>,
+    code_ligatures: bool,
+    relative_links: links::RelativeLinks,
+    link_base: Option<String>,
+    /// A single file whose frontmatter sets `orientation: landscape`
+    landscape: bool,
+    list_of_figures: bool,
+    list_of_tables: bool,
+    /// Link every heading so its page can be read back from the PDF
+    heading_links: bool,
+    changes: critic::ChangesMode,
+    show_redacted: bool,
+    /// One landscape page per `---`/H2-separated slide
+    slides: bool,
+    letter: Option<letter::LetterFormat>,
+    /// Heading levels with a page break before them
+    page_break_before: Vec<u8>,
+    /// Look up YouTube titles and thumbnails for video placeholders
+    fetch_media: bool,
+    /// Where relative links and images point into (the input directory, or
+    /// the input file's); unset for remote input
+    link_root: Option<PathBuf>,
+    table_layout: tables::TableLayout,
+    table_style: Vec<tables::TableStyle>,
+    ansi: terminal::AnsiMode,
+    /// Tile URL template for maps; unset draws them on a plain background
+    map_tiles: Option<String>,
+}
+
+fn markdown_to_html(markdown: &str, html_options: &HtmlOptions) -> Result<String> {
+    let dark_mode = html_options.dark_mode;
+    // Reassigning (rather than shadowing) frees each intermediate copy right
+    // away, which matters for documents of hundreds of megabytes
+    let mut processed_markdown = preprocess_markdown_single_file(markdown);
+    processed_markdown = directives::apply_conditionals(&processed_markdown);
+    processed_markdown = directives::apply_comments(&processed_markdown, html_options.comments);
+    processed_markdown = critic::apply_changes(&processed_markdown, html_options.changes);
+    processed_markdown = redact::apply_redactions(&processed_markdown, html_options.show_redacted);
+    if html_options.slides {
+        processed_markdown = slides::split_slides(&processed_markdown);
+    }
+    processed_markdown = flatten::flatten_details(&processed_markdown, html_options.details);
+    processed_markdown = flatten::flatten_tabs(&processed_markdown);
+    processed_markdown = containers::expand_containers(&processed_markdown);
+    processed_markdown = keys::expand_keys(&processed_markdown);
+    processed_markdown = media::replace_embeds(&processed_markdown, html_options.fetch_media)?;
+    processed_markdown = qr::expand_qr_codes(&processed_markdown)?;
+    processed_markdown = forms::expand_fields(&processed_markdown);
+    processed_markdown = signatures::expand_signatures(&processed_markdown);
+    processed_markdown = abbreviations::apply_abbreviations(&processed_markdown);
+
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_FOOTNOTES);
+    options.insert(Options::ENABLE_TASKLISTS);
+    options.insert(Options::ENABLE_HEADING_ATTRIBUTES);
+
+    let events: Vec<Event> = MdParser::new_ext(&processed_markdown, options).collect();
+    let events = filters::apply(&html_options.filters, events)?;
+    let events = images::apply_attributes(events);
+    let events = tables::apply_table_comments(events);
+    let events = music::render_abc(events);
+    let events = maps::render_maps(events, html_options.map_tiles.as_deref());
+    let events = terminal::style_prompts(events);
+    let events = diffs::style_diffs(events);
+    let events = terminal::render_ansi(events, html_options.ansi);
+    let events = anchors::assign_heading_ids(events);
+    let events = listings::add_listings(
+        events,
+        html_options.list_of_figures,
+        html_options.list_of_tables,
+    );
+    let mut events = footnotes::render_footnotes(events, html_options.footnote_backrefs);
+    events = links::annotate_downloads(events, html_options.link_root.as_deref());
+    events = links::apply_relative_link_policy(
+        events,
+        html_options.relative_links,
+        html_options.link_base.as_deref(),
+    );
+    let mut link_urls = Vec::new();
+    if html_options.link_appendix {
+        (events, link_urls) = links::number_external_links(events);
+    }
+
+    let mut html_output = String::new();
+    html::push_html(&mut html_output, events.into_iter());
+    html_output.push_str(&links::appendix_html(&link_urls));
+    if html_output.contains("<table") {
+        html_output = tables::normalize_html_tables(&html_output);
+    }
+    if html_options.heading_links {
+        html_output.push_str(&page_refs::heading_links(&page_refs::headings(
+            &html_output,
+        )));
+    }
+    let has_music = html_output.contains("<div class=\"abc-notation\">");
+    if has_music {
+        html_output.push_str(&music::scripts());
+    }
+    let form_fields = forms::fields(&html_output);
+    if !form_fields.is_empty() {
+        html_output.push_str(&forms::field_links(&form_fields));
+    }
+
+    // Images embedded as data URIs in many places are stored once
+    let (html_output, shared_images) = images::deduplicate_data_uris(&html_output);
+
+    let mut extra_css: Vec<String> = vec![
+        shared_images,
+        fonts::fallback_css(&html_output, &html_options.symbol_fonts)?,
+    ];
+    if html_options.link_appendix {
+        extra_css.push(
+            r#"
+        sup.link-ref a { text-decoration: none; }
+        .link-appendix li { word-break: break-all; }"#
+                .to_string(),
+        );
+    }
+    if html_options.print_urls {
+        // Anchors and mailto links are skipped by only matching http(s) targets
+        extra_css.push(
+            r#"
+        a[href^="http://"]::after, a[href^="https://"]::after {
+            content: " (" attr(href) ")";
+            font-size: 0.85em;
+            word-break: break-all;
+        }"#
+            .to_string(),
+        );
+    }
+
+    if html_options.changes == critic::ChangesMode::Show {
+        extra_css.push(
+            r#"
+        ins { background-color: #e6ffec; color: #116329; text-decoration: underline; }
+        del { background-color: #ffebe9; color: #82071e; }
+        mark { background-color: #fff8c5; }
+        .critic-comment { font-size: 0.85em; font-style: italic; color: #6e5a00; background-color: #fff8c5; padding: 0 4px; border-radius: 3px; }
+        .critic-comment::before { content: "💬 "; }"#
+                .to_string(),
+        );
+    }
+    if html_options.list_of_figures || html_options.list_of_tables {
+        extra_css.push(
+            r#"
+        .figure-caption { display: block; text-align: center; font-size: 0.9em; font-style: italic; }
+        .table-caption { font-size: 0.9em; font-style: italic; margin-bottom: 0.3em; }
+        .listing ol { list-style: none; padding-left: 0; }
+        .listing .page-ref { float: right; }"#
+                .to_string(),
+        );
+    }
+    if !form_fields.is_empty() {
+        extra_css.push(
+            r#"
+        .form-field { display: inline-block; vertical-align: bottom; border-bottom: 1px solid #999; }
+        .form-textarea, .form-checkbox { border: 1px solid #999; }"#
+                .to_string(),
+        );
+    }
+    extra_css.push(tables::style_css(&html_options.table_style));
+    extra_css.push(code::css());
+    if html_output.contains("class=\"ansi-") {
+        extra_css.push(terminal::css());
+    }
+    if html_output.contains("class=\"console-") {
+        extra_css.push(terminal::PROMPT_CSS.to_string());
+    }
+    if has_music {
+        extra_css.push(music::MUSIC_CSS.to_string());
+    }
+    if html_output.contains("<figure class=\"map\">") {
+        extra_css.push(maps::MAP_CSS.to_string());
+    }
+    if html_output.contains("class=\"diff-line ") {
+        extra_css.push(diffs::css());
+    }
+    if html_options.table_layout == tables::TableLayout::Fixed {
+        extra_css.push(tables::FIXED_LAYOUT_CSS.to_string());
+    }
+    if html_options.slides {
+        extra_css.push(slides::SLIDES_CSS.to_string());
+    }
+    extra_css.push(page_breaks::css(&html_options.page_break_before));
+    if let Some(format) = html_options.letter {
+        extra_css.push(letter::css(format));
+    }
+    if html_options.section_themes {
+        extra_css.push(theme::SECTION_CSS.to_string());
+    }
+    if !html_options.print_background {
+        // Chrome drops backgrounds when printing without them, except on
+        // elements that ask to keep their exact colors
+        extra_css.push(
+            r#"
+        pre, code, th { -webkit-print-color-adjust: exact; print-color-adjust: exact; }"#
+                .to_string(),
+        );
+    }
+    let code_fonts = match &html_options.code_font {
+        Some(font) => {
+            let (family, face) = fonts::code_font(font)?;
+            extra_css.push(face);
+            format!("{}, {}", family, fonts::DEFAULT_CODE_FONTS)
+        }
+        None => fonts::DEFAULT_CODE_FONTS.to_string(),
+    };
+    if !html_options.code_ligatures {
+        extra_css.push(
+            r#"
+        code, pre { font-variant-ligatures: none; font-feature-settings: "liga" 0, "calt" 0; }"#
+                .to_string(),
+        );
+    }
+    if html_options.justify {
+        extra_css.push(
+            r#"
+        p, li { text-align: justify; }"#
+                .to_string(),
+        );
+    }
+    if html_options.hyphenate {
+        // Code and tables keep their words intact
+        extra_css.push(
+            r#"
+        p, li, blockquote { hyphens: auto; -webkit-hyphens: auto; }"#
+                .to_string(),
+        );
+    }
+    // Chrome only hyphenates text whose language it knows
+    let lang = match (&html_options.lang, html_options.hyphenate) {
+        (Some(lang), _) => format!(" lang=\"{}\"", template::escape_html(lang)),
+        (None, true) => " lang=\"en\"".to_string(),
+        (None, false) => String::new(),
+    };
 
     let theme = if dark_mode {
         "background-color: #1a1a1a; color: #e0e0e0;"
@@ -186,15 +1254,20 @@ fn markdown_to_html(markdown: &str, dark_mode: bool) -> String {
         "background-color: white; color: black;"
     };
 
-    format!(
+    Ok(format!(
         r#"<!DOCTYPE html>
-<html>
+<html{lang} data-theme="{theme_name}">
 <head>
     <meta charset="utf-8">
-    <title>Markdown to PDF</title>
+    <meta name="color-scheme" content="{theme_name}">
+    <title>Markdown to PDF</title>{base}
     <style>
+        @page {{ size: A4 {orientation}; }}
+        @page landscape {{ size: A4 landscape; }}
+        .landscape {{ page: landscape; }}
+
         body {{
-            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif;
+            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif, '{symbol_font}';
             line-height: 1.6;
             max-width: 800px;
             margin: 0 auto;
@@ -214,7 +1287,7 @@ fn markdown_to_html(markdown: &str, dark_mode: bool) -> String {
             background-color: {code_bg};
             padding: 2px 4px;
             border-radius: 3px;
-            font-family: 'Courier New', monospace;
+            font-family: {code_fonts}, '{symbol_font}';
         }}
         
         pre {{
@@ -235,6 +1308,46 @@ fn markdown_to_html(markdown: &str, dark_mode: bool) -> String {
             padding-left: 20px;
             color: #666;
         }}
+
+        blockquote .attribution {{
+            text-align: right;
+            font-style: italic;
+        }}
+
+        abbr {{
+            text-decoration: none;
+        }}
+
+        kbd {{
+            display: inline-block;
+            padding: 1px 6px;
+            font-family: {code_fonts}, '{symbol_font}';
+            font-size: 0.85em;
+            border: 1px solid #bbb;
+            border-radius: 4px;
+            box-shadow: inset 0 -1px 0 #bbb;
+            background-color: {code_bg};
+        }}
+
+        .page-break {{
+            page-break-after: always;
+        }}
+
+        .tab-label {{
+            font-weight: bold;
+            border-bottom: 1px solid #ddd;
+            margin-bottom: 0.5em;
+        }}
+
+        .pullquote {{
+            font-size: 1.4em;
+            font-style: italic;
+            text-align: center;
+            margin: 1.5em 2em;
+            padding: 0.5em 0;
+            border-top: 2px solid #ddd;
+            border-bottom: 2px solid #ddd;
+        }}
         
         table {{
             border-collapse: collapse;
@@ -252,11 +1365,45 @@ fn markdown_to_html(markdown: &str, dark_mode: bool) -> String {
             background-color: {header_bg};
             font-weight: bold;
         }}
+
+        td > table, th > table {{
+            margin: 0;
+        }}
         
         img {{
             max-width: 100%;
             height: auto;
         }}
+
+        .sized-image {{
+            display: inline-block;
+            max-width: 100%;
+        }}
+
+        .sized-image.image-width img {{
+            width: 100%;
+        }}
+
+        .sized-image.image-height img {{
+            height: 100%;
+            width: auto;
+        }}
+
+        .image-center {{
+            display: block;
+            margin: 0 auto;
+            text-align: center;
+        }}
+
+        .image-left {{
+            float: left;
+            margin: 0 1em 1em 0;
+        }}
+
+        .image-right {{
+            float: right;
+            margin: 0 0 1em 1em;
+        }}
         
         ul, ol {{
             margin: 1em 0;
@@ -266,6 +1413,122 @@ fn markdown_to_html(markdown: &str, dark_mode: bool) -> String {
         li {{
             margin: 0.5em 0;
         }}
+
+        sup.footnote-reference a {{
+            font-size: 0.8em;
+            text-decoration: none;
+            padding: 0 1px;
+        }}
+
+        .footnote-definition {{
+            font-size: 0.9em;
+            margin: 0.5em 0;
+        }}
+
+        .footnote-definition p {{
+            display: inline;
+        }}
+
+        .footnote-definition-label {{
+            margin-right: 0.5em;
+        }}
+
+        .footnote-backref {{
+            text-decoration: none;
+        }}
+
+        .redacted {{
+            display: inline-block;
+            height: 1em;
+            vertical-align: text-bottom;
+            background-color: black;
+            -webkit-print-color-adjust: exact;
+            print-color-adjust: exact;
+        }}
+
+        .redacted-block {{
+            background-color: black;
+            margin: 1em 0;
+            -webkit-print-color-adjust: exact;
+            print-color-adjust: exact;
+        }}
+
+        .redacted-visible {{
+            background-color: #ffd7d5;
+            outline: 1px dashed #cf222e;
+        }}
+
+        .keep-together {{
+            break-inside: avoid;
+        }}
+
+        .signature-block {{
+            display: inline-block;
+            width: 45%;
+            margin: 2.5em 4% 1em 0;
+            vertical-align: top;
+            break-inside: avoid;
+        }}
+
+        .signature-block > span {{
+            display: block;
+        }}
+
+        .signature-line {{
+            border-bottom: 1px solid #333;
+            height: 2.5em;
+        }}
+
+        .signature-date {{
+            width: 50%;
+            margin-top: 0.5em;
+        }}
+
+        .signature-label {{
+            font-size: 0.8em;
+            color: #666;
+        }}
+
+        .signature-name {{
+            font-weight: 600;
+        }}
+
+        .file-annotation {{
+            font-size: 0.8em;
+            color: #666;
+            white-space: nowrap;
+        }}
+
+        .media-placeholder {{
+            display: flex;
+            align-items: center;
+            gap: 1em;
+            margin: 1em 0;
+            padding: 0.5em;
+            border: 1px solid #ddd;
+            break-inside: avoid;
+        }}
+
+        .media-thumbnail {{
+            width: 6cm;
+        }}
+
+        .media-placeholder figcaption a {{
+            word-break: break-all;
+        }}
+
+        .media-placeholder img.qr {{
+            display: block;
+            margin-top: 0.5em;
+        }}
+
+        img.qr {{
+            width: 3cm;
+            height: 3cm;
+            vertical-align: middle;
+        }}
+
+        {extra_css}
     </style>
 </head>
 <body>
@@ -275,41 +1538,523 @@ fn markdown_to_html(markdown: &str, dark_mode: bool) -> String {
         theme = theme,
         code_bg = if dark_mode { "#2d2d2d" } else { "#f5f5f5" },
         header_bg = if dark_mode { "#3a3a3a" } else { "#f9f9f9" },
-        html_output = html_output
-    )
+        html_output = html_output,
+        extra_css = extra_css.join("\n"),
+        symbol_font = fonts::FAMILY,
+        code_fonts = code_fonts,
+        lang = lang,
+        orientation = if html_options.landscape {
+            "landscape"
+        } else {
+            "portrait"
+        },
+        theme_name = if dark_mode { "dark" } else { "light" },
+        base = html_options
+            .base_url
+            .as_deref()
+            .map(|url| format!("\n    <base href=\"{}\">", template::escape_html(url)))
+            .unwrap_or_default()
+    ))
+}
+
+/// A4 print settings shared by every PDF this tool renders.
+fn print_options(
+    display_header_footer: bool,
+    header_template: &str,
+    footer_template: &str,
+    print_background: bool,
+) -> headless_chrome::types::PrintToPdfOptions {
+    headless_chrome::types::PrintToPdfOptions {
+        landscape: Some(false),
+        display_header_footer: Some(display_header_footer),
+        print_background: Some(print_background),
+        scale: Some(1.0),
+        paper_width: Some(8.27),  // A4 width in inches
+        paper_height: Some(11.7), // A4 height in inches
+        margin_top: Some(0.4),
+        margin_bottom: Some(0.4),
+        margin_left: Some(0.4),
+        margin_right: Some(0.4),
+        page_ranges: None,
+        ignore_invalid_page_ranges: Some(false),
+        header_template: Some(header_template.to_string()),
+        footer_template: Some(footer_template.to_string()),
+        // Lets landscape sections switch to their own named page size
+        prefer_css_page_size: Some(true),
+        transfer_mode: None,
+        generate_document_outline: Some(false),
+        generate_tagged_pdf: Some(false),
+    }
+}
+
+fn warn_oversized(tab: &headless_chrome::Tab, strict: bool) -> Result<()> {
+    let oversized = chrome::oversized_elements(tab);
+    if oversized > 0 {
+        warnings::warn(format!("{} element(s) wider than the page", oversized));
+        if strict {
+            return Err(Failure::Strict(warnings::count()).into());
+        }
+    }
+    Ok(())
+}
+
+/// Renders the document a few chapters at a time with a fresh Chrome per
+/// chunk and merges the parts into `output`, so Chrome never holds the whole
+/// document. The header and footer are stamped over the merged PDF, so page
+/// numbers run on across chunks.
+fn render_chunked(
+    chunks: Vec<String>,
+    html_options: &HtmlOptions,
+    chrome_options: &chrome::ChromeOptions,
+    document_url: impl Fn(&str) -> Result<String>,
+    header_footer: Option<&HeaderFooter>,
+    strict: bool,
+    output: &Path,
+) -> Result<()> {
+    let workspace = tempdir::TempDir::new("chunks")?;
+    let mut parts = Vec::new();
+
+    for (index, chunk) in chunks.iter().enumerate() {
+        println!("Rendering chunk {}/{}...", index + 1, chunks.len());
+        let url = document_url(&markdown_to_html(chunk, html_options)?)?;
+        let part = workspace.path.join(format!("part-{}.pdf", index + 1));
+        let mut session = None;
+        // Checked outside the retried print, so a relaunch doesn't warn twice
+        let loaded =
+            chrome::with_retries(&mut session, &url, chrome_options, |s| Ok(s.tab.clone()))?;
+        warn_oversized(&loaded, strict)?;
+        chrome::with_retries(&mut session, &url, chrome_options, |s| {
+            chrome::print_to_file(
+                &s.tab,
+                print_options(false, "", "", html_options.print_background),
+                &part,
+            )
+        })?;
+        parts.push(part);
+    }
+
+    println!("Merging {} chunks...", parts.len());
+    pdf_tools::merge(&parts, output)?;
+
+    if let Some(header_footer) = header_footer {
+        stamp_header_footer(output, chrome_options, &document_url, header_footer)?;
+    }
+
+    Ok(())
+}
+
+/// Rendered header and footer templates, plus a different pair for the
+/// first page when one is configured.
+struct HeaderFooter {
+    header: String,
+    footer: String,
+    first_page: Option<(String, String)>,
+}
+
+/// Prints the header and footer over an already rendered PDF. They come from
+/// a blank document with as many pages as the PDF, so page numbers and the
+/// page total match, and are laid over the matching pages with qpdf.
+fn stamp_header_footer(
+    pdf: &Path,
+    chrome_options: &chrome::ChromeOptions,
+    document_url: impl Fn(&str) -> Result<String>,
+    header_footer: &HeaderFooter,
+) -> Result<()> {
+    let workspace = tempdir::TempDir::new("stamp")?;
+    let pages = pdf_tools::page_count(pdf)?;
+    let blank_pages = format!(
+        "<!DOCTYPE html><html><body style=\"margin: 0\">{}</body></html>",
+        "<div style=\"page-break-after: always\"></div>".repeat(pages.saturating_sub(1))
+    );
+    let url = document_url(&blank_pages)?;
+    let mut session = None;
+
+    let mut stamp = |name: &str, header: &str, footer: &str, page_range: &str| -> Result<()> {
+        let stamp = workspace.path.join(name);
+        chrome::with_retries(&mut session, &url, chrome_options, |s| {
+            chrome::print_to_file(&s.tab, print_options(true, header, footer, false), &stamp)
+        })?;
+        pdf_tools::overlay(pdf, &stamp, page_range)
+    };
+
+    match &header_footer.first_page {
+        Some((header, footer)) => {
+            if pages > 1 {
+                stamp(
+                    "header-footer.pdf",
+                    &header_footer.header,
+                    &header_footer.footer,
+                    "2-z",
+                )?;
+            }
+            stamp("first-page.pdf", header, footer, "1")
+        }
+        None => stamp(
+            "header-footer.pdf",
+            &header_footer.header,
+            &header_footer.footer,
+            "1-z",
+        ),
+    }
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    let args = Args::parse();
+async fn main() {
+    if let Err(error) = run().await {
+        eprintln!("Error: {:?}", error);
+        std::process::exit(exit_codes::for_error(&error));
+    }
+}
+
+async fn run() -> Result<()> {
+    let mut args = Args::parse();
+
+    // Repository and archive inputs are converted from a temporary directory;
+    // a repository's docs/ directory is used when there is one
+    let repository = args
+        .input
+        .to_str()
+        .filter(|s| git::is_repository_url(s))
+        .map(str::to_string);
+    let _workspace = match repository {
+        Some(url) => {
+            println!("Cloning repository: {}", url);
+            let checkout = git::clone_shallow(&url, args.git_ref.as_deref())?;
+            let docs = checkout.path.join("docs");
+            args.input = if docs.is_dir() {
+                docs
+            } else {
+                checkout.path.clone()
+            };
+            args.untrusted_input = true;
+            Some(checkout)
+        }
+        None if archive::is_archive(&args.input) => {
+            println!("Extracting archive: {:?}", args.input);
+            let extracted = archive::extract(&args.input)?;
+            args.input = archive::content_root(&extracted.path);
+            args.untrusted_input = true;
+            Some(extracted)
+        }
+        None => None,
+    };
+
+    if args.all_locales {
+        let languages = locales::directories(&args.input);
+        if languages.is_empty() {
+            anyhow::bail!(
+                "No language subdirectories (e.g. en, de) found in {:?}",
+                args.input
+            );
+        }
+        for (code, dir) in languages {
+            println!("Building locale: {}", code);
+            let mut localized = args.clone();
+            localized.input = dir;
+            localized.output = args
+                .output
+                .iter()
+                .map(|output| locales::output_path(output, &code))
+                .collect();
+            localized.locale = Some(code);
+            convert(localized)?;
+        }
+        return Ok(());
+    }
 
-    if !args.input.exists() {
-        anyhow::bail!("Input path does not exist: {:?}", args.input);
+    if let Some(locale) = &args.locale {
+        if let Some((_, dir)) = locales::directories(&args.input)
+            .into_iter()
+            .find(|(code, _)| code == locale)
+        {
+            args.input = dir;
+        }
+    }
+
+    convert(args)
+}
+
+/// Converts one input to every requested output.
+fn convert(mut args: Args) -> Result<()> {
+    let config = config::load(
+        args.config.as_deref(),
+        &args.input,
+        args.untrusted_input && !args.trust_config,
+    )?;
+    let pre_hooks: Vec<String> = config
+        .hooks
+        .pre
+        .iter()
+        .chain(&args.pre_hooks)
+        .cloned()
+        .collect();
+    let post_hooks: Vec<String> = config
+        .hooks
+        .post
+        .iter()
+        .chain(&args.post_hooks)
+        .cloned()
+        .collect();
+    let plugin_paths: Vec<PathBuf> = config
+        .plugins
+        .iter()
+        .chain(&args.plugins)
+        .cloned()
+        .collect();
+    let filter_paths: Vec<PathBuf> = config
+        .filters
+        .iter()
+        .chain(&args.filters)
+        .cloned()
+        .collect();
+
+    // Hooks see the first output as MD2PDF_OUTPUT
+    let primary_output = args.output[0].clone();
+    hooks::run(&pre_hooks, "pre", &args.input, &primary_output)?;
+
+    let remote_url = remote::as_url(&args.input);
+    if remote_url.is_none() && !args.input.exists() {
+        return Err(Failure::InputNotFound(args.input.clone()).into());
+    }
+
+    // Hyphenation follows the locale unless --lang says otherwise
+    if args.lang.is_none() {
+        args.lang = args.locale.clone();
+    }
+    code::set(config.code.clone(), args.keep_code_blocks);
+    strings::set(
+        strings::Strings::built_in(args.locale.as_deref().unwrap_or("en"))
+            .with_overrides(&config.strings)?,
+    );
+    if let Some(locale) = &args.locale {
+        match locales::date_locale(locale) {
+            Some(date_locale) => args.locale = Some(date_locale),
+            None => anyhow::bail!("Unknown locale: {}", locale),
+        }
+    }
+
+    let mut variables = git::metadata(&args.input);
+    if remote_url.is_none() {
+        variables.extend(template::file_variables(&args.input));
+    }
+    if args.manifest_vars {
+        variables.extend(manifest::project_variables(&args.input)?);
+    }
+    let book = if args.input.is_dir() {
+        match mdbook::load(&args.input)? {
+            Some(book) => Some(book),
+            None => mkdocs::load(&args.input)?,
+        }
+    } else {
+        None
+    };
+    if let Some(book) = &book {
+        variables.insert("authors".to_string(), book.authors.join(", "));
     }
 
-    let html_content = if args.input.is_file() {
+    // Kept unescaped; header, footer, and markdown escape it where it lands.
+    // Only a title given on the command line has its placeholders filled in,
+    // as a book's title may come from someone else's repository
+    let title = match &args.title {
+        Some(title) => template::render_plain(title, &variables, args.locale.as_deref()),
+        None => book
+            .as_ref()
+            .and_then(|b| b.title.clone())
+            .unwrap_or_else(|| strings::get().documentation),
+    };
+    variables.insert("title".to_string(), title.clone());
+
+    let mut plugins = plugins::Plugins::load(&plugin_paths)?;
+    let mut open_items = Vec::new();
+    let spellcheck = args
+        .spellcheck
+        .clone()
+        .map(|language| spellcheck::Spellcheck {
+            language,
+            dictionary: args.dictionary.clone(),
+        });
+
+    // Set from a single file's frontmatter `theme`, `orientation` and `page_break_before`
+    let mut document_theme = None;
+    let mut document_landscape = false;
+    let mut document_page_breaks = Vec::new();
+    let mut markdown = if remote_url.is_some() || args.input.is_file() {
         // Single file mode
-        if args.input.extension().map_or(true, |ext| ext != "md") {
-            anyhow::bail!("File must have .md extension: {:?}", args.input);
+        let raw_content = match remote_url {
+            Some(url) => {
+                println!("Fetching markdown from: {}", url);
+                remote::fetch(url)?
+            }
+            None => {
+                if html_import::is_html(&args.input) {
+                    println!("Reading HTML file: {:?}", args.input);
+                    let html = fs::read_to_string(&args.input)
+                        .with_context(|| format!("Failed to read file: {:?}", args.input))?;
+                    html_import::to_markdown_block(&html)
+                } else {
+                    if args.input.extension().map_or(true, |ext| ext != "md") {
+                        anyhow::bail!("File must have .md or .html extension: {:?}", args.input);
+                    }
+
+                    println!("Reading markdown file: {:?}", args.input);
+                    fs::read_to_string(&args.input)
+                        .with_context(|| format!("Failed to read file: {:?}", args.input))?
+                }
+            }
+        };
+        let (frontmatter, body) = frontmatter::split(&raw_content);
+        document_theme = theme::from_frontmatter(&frontmatter);
+        document_landscape = orientation::is_landscape(&frontmatter);
+        document_page_breaks = page_breaks::from_frontmatter(&frontmatter);
+        let base_dir = args.input.parent().unwrap_or(Path::new("."));
+        let mut markdown_content = plugins.transform(body.to_string())?;
+        if args.static_site {
+            markdown_content = static_site::expand_shortcodes(&markdown_content);
+        }
+        markdown_content =
+            overrides::apply(&markdown_content, &frontmatter, base_dir, base_dir, "body")?;
+        if args.comments == directives::CommentMode::Directives {
+            markdown_content = directives::resolve_includes(&markdown_content, base_dir, base_dir)?;
+        }
+        warnings::check_markdown(
+            &markdown_content,
+            base_dir,
+            &args.input.file_name().unwrap_or_default().to_string_lossy(),
+        );
+        lint::check(
+            &markdown_content,
+            &args.input.file_name().unwrap_or_default().to_string_lossy(),
+            &config.lint,
+        );
+        if let Some(spellcheck) = &spellcheck {
+            spellcheck::check(
+                &markdown_content,
+                &args.input.file_name().unwrap_or_default().to_string_lossy(),
+                spellcheck,
+            )?;
+        }
+        if args.collect_todos {
+            let source = args.input.file_name().unwrap_or_default().to_string_lossy();
+            markdown_content =
+                todos::collect_open_items(&markdown_content, &source, &mut open_items);
+        }
+
+        let mut markdown = String::new();
+        if let Some(format) = args.letter {
+            markdown.push_str(&letter::letter_head(
+                &frontmatter,
+                format,
+                &variables,
+                args.locale.as_deref(),
+            ));
+        }
+        if args.task_summary {
+            let rows = tasks::progress_by_section(&markdown_content, &title);
+            markdown.push_str(&tasks::summary_table(&rows));
+        }
+        markdown.push_str(&preprocess_markdown_single_file(&markdown_content));
+        markdown
+    } else if let Some(book) = &book {
+        // mdBook/MkDocs mode: SUMMARY.md or the nav decides order and nesting
+        println!("Reading book from: {:?}", book.src);
+        let collect_options = CollectOptions {
+            resolve_includes: args.comments == directives::CommentMode::Directives,
+            ..CollectOptions::default()
+        };
+        // Paths are relative to the input, like in directory mode, when the
+        // book's sources are inside it
+        let root = if book.src.starts_with(&args.input) {
+            args.input.as_path()
+        } else {
+            book.src.as_path()
+        };
+        let mut chapters = HashMap::new();
+        for path in book.chapter_paths() {
+            if !chapters.contains_key(path) {
+                let (_, file) = read_markdown_file(root, &book.src.join(path), &collect_options)?;
+                chapters.insert(path.to_path_buf(), file);
+            }
         }
+        prepare_files(
+            chapters.values_mut().collect(),
+            root,
+            &mut plugins,
+            &config.lint,
+            spellcheck.as_ref(),
+            args.collect_todos.then_some(&mut open_items),
+        )?;
 
-        println!("Reading markdown file: {:?}", args.input);
-        let markdown_content = fs::read_to_string(&args.input)
-            .with_context(|| format!("Failed to read file: {:?}", args.input))?;
+        let mut preface = String::new();
+        if args.task_summary {
+            let rows: Vec<_> = book
+                .chapter_paths()
+                .into_iter()
+                .filter_map(|path| chapters.get(path))
+                .map(|file| tasks::progress(&file.content, &file.name))
+                .filter(|row| row.total > 0)
+                .collect();
+            preface.push_str(&tasks::summary_table(&rows));
+        }
 
-        println!("Converting markdown to HTML...");
-        let processed_markdown = preprocess_markdown_single_file(&markdown_content);
-        markdown_to_html(&processed_markdown, args.dark_mode)
+        let combine_options = combine_options(&args, &config);
+        let mut sections = SectionWriter::new(&combine_options);
+        let mut combined = mdbook::combine(book, &title, &preface, |section| {
+            let mut markdown = String::new();
+            match section {
+                mdbook::Section::Chapter(path, title, level) => {
+                    // The summary's title stands in for the file name
+                    let mut file = chapters[path].clone();
+                    file.name = title.to_string();
+                    sections.file(&mut markdown, file, level);
+                }
+                mdbook::Section::End(level) => sections.chapter_notes(&mut markdown, level + 1),
+            }
+            markdown
+        });
+        sections.document_notes(&mut combined);
+        combined
     } else if args.input.is_dir() {
         // Directory mode
         println!("Scanning for markdown files in: {:?}", args.input);
-        let files_by_dir = collect_markdown_files(&args.input)?;
+        let collect_options = CollectOptions {
+            resolve_includes: args.comments == directives::CommentMode::Directives,
+            notion: args.notion,
+            include_html: args.include_html,
+        };
+        let mut files_by_dir = collect_markdown_files(&args.input, &collect_options)?;
+
+        if files_by_dir.is_empty() {
+            return Err(Failure::NoMarkdownFiles.into());
+        }
+
+        if args.docusaurus || docusaurus::detect(&args.input) {
+            println!("Ordering files by Docusaurus sidebar positions");
+            docusaurus::apply_sidebar_order(&args.input, &mut files_by_dir);
+        }
+
+        if args.static_site || static_site::detect(&args.input) {
+            println!("Ordering files by Hugo/Jekyll weight and title");
+            static_site::apply_weights(&args.input, &mut files_by_dir);
+            for file in files_by_dir.iter_mut().flat_map(|(_, files)| files) {
+                file.content = static_site::expand_shortcodes(&file.content).into();
+            }
+        }
+
+        if args.wiki || wiki::detect(&args.input) {
+            println!("Ordering wiki pages by _Sidebar.md");
+            let sections = wiki::apply_sidebar_order(&args.input, &mut files_by_dir);
+            for file in files_by_dir.iter_mut().flat_map(|(_, files)| files) {
+                file.content = wiki::resolve_links(&file.content, &sections).into();
+            }
+        }
 
+        place_root_files(&mut files_by_dir, args.root_files);
+        sections::apply_order(&mut files_by_dir, &config.sections);
         if files_by_dir.is_empty() {
-            anyhow::bail!("No .md files found in directory");
+            return Err(Failure::NoMarkdownFiles.into());
         }
 
-        let total_files: usize = files_by_dir.values().map(|v| v.len()).sum();
+        let total_files: usize = files_by_dir.iter().map(|(_, v)| v.len()).sum();
         println!(
             "Found {} markdown files in {} directories",
             total_files,
@@ -323,65 +2068,416 @@ async fn main() -> Result<()> {
             }
         }
 
-        println!("Combining all files into single document...");
-        let combined_markdown = create_combined_markdown(files_by_dir, &args.title);
+        prepare_files(
+            files_by_dir
+                .iter_mut()
+                .flat_map(|(_, files)| files.iter_mut())
+                .collect(),
+            &args.input,
+            &mut plugins,
+            &config.lint,
+            spellcheck.as_ref(),
+            args.collect_todos.then_some(&mut open_items),
+        )?;
+
+        let mut preface = String::new();
+        if args.task_summary {
+            let rows: Vec<_> = files_by_dir
+                .iter()
+                .flat_map(|(_, files)| files)
+                .map(|file| tasks::progress(&file.content, &file.name))
+                .filter(|row| row.total > 0)
+                .collect();
+            preface.push_str(&tasks::summary_table(&rows));
+        }
 
-        println!("Converting combined markdown to HTML...");
-        markdown_to_html(&combined_markdown, args.dark_mode)
+        println!("Combining all files into single document...");
+        let combine_options = combine_options(&args, &config);
+        create_combined_markdown(files_by_dir, &title, &preface, &combine_options)
     } else {
         anyhow::bail!("Input path is neither file nor directory: {:?}", args.input);
     };
 
-    println!("Starting Chrome for PDF generation...");
-    let browser = Browser::new(
-        LaunchOptions::default_builder()
-            .headless(true)
-            .build()
-            .expect("Could not configure Chrome"),
-    )
-    .context("Failed to start Chrome. Make sure Chrome or Chromium is installed.")?;
+    if args.collect_todos {
+        println!("Found {} open items", open_items.len());
+        markdown.push_str(&todos::open_items_section(&open_items));
+    }
 
-    let tab = browser.new_tab().context("Failed to create new tab")?;
+    if args.revision_history {
+        println!("Generating revision history from git log...");
+        markdown.push_str(&git::revision_history(&args.input)?);
+    }
 
-    println!("Loading HTML content...");
-    let data_uri = format!(
-        "data:text/html;charset=utf-8;base64,{}",
-        base64::engine::general_purpose::STANDARD.encode(&html_content)
-    );
-    tab.navigate_to(&data_uri)
-        .context("Failed to load HTML content")?;
-
-    tab.wait_until_navigated()
-        .context("Page navigation timeout")?;
-
-    println!("Generating PDF: {:?}", args.output);
-    let pdf_data = tab
-        .print_to_pdf(Some(headless_chrome::types::PrintToPdfOptions {
-            landscape: Some(false),
-            display_header_footer: Some(false),
-            print_background: Some(true),
-            scale: Some(1.0),
-            paper_width: Some(8.27),  // A4 width in inches
-            paper_height: Some(11.7), // A4 height in inches
-            margin_top: Some(0.4),
-            margin_bottom: Some(0.4),
-            margin_left: Some(0.4),
-            margin_right: Some(0.4),
-            page_ranges: None,
-            ignore_invalid_page_ranges: Some(false),
-            header_template: None,
-            footer_template: None,
-            prefer_css_page_size: Some(false),
-            transfer_mode: None,
-            generate_document_outline: Some(false),
-            generate_tagged_pdf: Some(false),
-        }))
-        .context("Failed to generate PDF")?;
-
-    fs::write(&args.output, pdf_data)
-        .with_context(|| format!("Failed to save PDF: {:?}", args.output))?;
-
-    println!("✅ PDF successfully created: {:?}", args.output);
+    println!("Converting markdown to HTML...");
+    let html_options = HtmlOptions {
+        dark_mode: theme::is_dark(args.dark_mode, document_theme),
+        section_themes: !matches!(
+            args.dark_mode,
+            Some(theme::DarkMode::On | theme::DarkMode::Off)
+        ),
+        link_appendix: args.link_appendix,
+        print_urls: args.print_urls,
+        details: args.details,
+        comments: args.comments,
+        filters: filter_paths,
+        base_url: remote_url.map(remote::base_url),
+        footnote_backrefs: !args.no_footnote_backrefs,
+        symbol_fonts: if args.no_symbol_fonts {
+            Vec::new()
+        } else if args.symbol_fonts.is_empty() {
+            fonts::find_installed()
+        } else {
+            args.symbol_fonts.clone()
+        },
+        justify: args.justify,
+        hyphenate: args.hyphenate,
+        lang: args.lang.clone(),
+        print_background: !args.no_print_background,
+        code_font: args.code_font.clone(),
+        code_ligatures: !args.no_code_ligatures,
+        // Remote documents resolve relative links against their <base>
+        relative_links: if remote_url.is_some() {
+            links::RelativeLinks::Keep
+        } else {
+            args.relative_links
+        },
+        link_base: args.link_base.clone(),
+        landscape: document_landscape || args.slides,
+        list_of_figures: args.list_of_figures,
+        list_of_tables: args.list_of_tables,
+        heading_links: args.page_map.is_some(),
+        changes: args.changes,
+        show_redacted: args.show_redacted,
+        slides: args.slides,
+        letter: args.letter,
+        fetch_media: !args.offline,
+        table_layout: args.table_layout,
+        table_style: args.table_style.clone(),
+        ansi: args.ansi,
+        map_tiles: args.map_tiles.clone().filter(|_| !args.offline),
+        link_root: remote_url.is_none().then(|| {
+            if args.input.is_dir() {
+                args.input.clone()
+            } else {
+                args.input.parent().unwrap_or(Path::new(".")).to_path_buf()
+            }
+        }),
+        page_break_before: if args.page_break_before.is_empty() {
+            document_page_breaks
+        } else {
+            args.page_break_before.clone()
+        },
+    };
+    let html_content = markdown_to_html(&markdown, &html_options)?;
+    for anchor in links::broken_anchors(&html_content) {
+        warnings::broken_link(format!(
+            "Broken link: no heading or anchor matches #{}",
+            anchor
+        ));
+    }
+
+    // Chrome falls back to its own header/footer when only one is given
+    let display_header_footer = args.header_template.is_some() || args.footer_template.is_some();
+    let header_template = args
+        .header_template
+        .as_deref()
+        .map(|t| template::render(t, &variables, args.locale.as_deref()))
+        .unwrap_or_else(|| "<span></span>".to_string());
+    let footer_template = args
+        .footer_template
+        .as_deref()
+        .map(|t| template::render(t, &variables, args.locale.as_deref()))
+        .unwrap_or_else(|| "<span></span>".to_string());
+    let render_first_page = |first: &Option<String>, fallback: &str| match first.as_deref() {
+        Some("") => "<span></span>".to_string(),
+        Some(t) => template::render(t, &variables, args.locale.as_deref()),
+        None => fallback.to_string(),
+    };
+    let first_page = if args.plain_first_page {
+        Some(("<span></span>".to_string(), "<span></span>".to_string()))
+    } else if args.first_page_header_template.is_some() || args.first_page_footer_template.is_some()
+    {
+        Some((
+            render_first_page(&args.first_page_header_template, &header_template),
+            render_first_page(&args.first_page_footer_template, &footer_template),
+        ))
+    } else {
+        None
+    };
+    let header_footer = (display_header_footer || first_page.is_some()).then(|| HeaderFooter {
+        header: header_template,
+        footer: footer_template,
+        first_page,
+    });
+
+    if args.strict && warnings::broken_link_count() > 0 {
+        return Err(Failure::BrokenLinks(warnings::broken_link_count()).into());
+    }
+    if args.strict && warnings::count() > 0 {
+        return Err(Failure::Strict(warnings::count()).into());
+    }
+
+    let outputs: Vec<(PathBuf, output::OutputFormat)> = args
+        .output
+        .iter()
+        .map(|path| {
+            (
+                path.clone(),
+                output::OutputFormat::for_path(path, args.format),
+            )
+        })
+        .collect();
+
+    // Relative image paths in the document point into here
+    let source_dir = html_options.link_root.clone();
+
+    for (path, format) in &outputs {
+        match format {
+            output::OutputFormat::Html if !args.standalone => {
+                println!("Writing HTML with assets: {:?}", path);
+                html_assets::write_bundle(&html_content, path, source_dir.as_deref())?;
+            }
+            output::OutputFormat::Html if args.self_contained => {
+                println!("Writing self-contained HTML: {:?}", path);
+                let html = html_assets::inline_assets(&html_content, source_dir.as_deref())?;
+                fs::write(path, html)
+                    .with_context(|| format!("Failed to save HTML: {:?}", path))?;
+            }
+            output::OutputFormat::Html => {
+                println!("Writing HTML: {:?}", path);
+                fs::write(path, &html_content)
+                    .with_context(|| format!("Failed to save HTML: {:?}", path))?;
+            }
+            output::OutputFormat::Epub => {
+                println!("Writing EPUB: {:?}", path);
+                let authors = variables.get("authors").map_or("", String::as_str);
+                epub::write(path, &html_content, &title, authors)?;
+            }
+            _ => {}
+        }
+    }
+
+    if outputs.iter().any(|(_, format)| format.needs_chrome()) {
+        // Each document (or chunk) gets its own asset server, all on 127.0.0.1
+        let serve_root = source_dir.clone().filter(|_| args.asset_server);
+        let document_url = |html: &str| -> Result<String> {
+            match &serve_root {
+                Some(root) => {
+                    let server =
+                        server::AssetServer::start(root, html_assets::rebase_sources(html))?;
+                    println!("Serving assets from {:?} at {}", root, server.document_url);
+                    Ok(server.document_url)
+                }
+                None => Ok(format!(
+                    "data:text/html;charset=utf-8;base64,{}",
+                    base64::engine::general_purpose::STANDARD.encode(html)
+                )),
+            }
+        };
+        let chunked = args.chunk_chapters > 0;
+        let full_document_url = document_url(&html_content)?;
+
+        let chrome_options = chrome::ChromeOptions {
+            user_data_dir: args.chrome_user_data_dir.clone(),
+            sandbox: !args.no_sandbox,
+            window_width: args.image_width,
+            javascript: !args.no_js,
+            dark: html_options.dark_mode,
+            policy: network::RequestPolicy {
+                offline: args.offline,
+                local_server: serve_root.is_some(),
+                allow: config
+                    .network
+                    .allow
+                    .iter()
+                    .chain(&args.allow_urls)
+                    .cloned()
+                    .collect(),
+                deny: config
+                    .network
+                    .deny
+                    .iter()
+                    .chain(&args.deny_urls)
+                    .cloned()
+                    .collect(),
+            },
+            readiness: readiness::Readiness {
+                selector: args.wait_for_selector.clone(),
+                network_idle: args.wait_network_idle,
+                delay: std::time::Duration::from_millis(args.render_delay_ms),
+            },
+            retries: args.chrome_retries,
+        };
+        let mut session = None;
+
+        if !chunked {
+            let loaded =
+                chrome::with_retries(&mut session, &full_document_url, &chrome_options, |s| {
+                    Ok(s.tab.clone())
+                })?;
+            warn_oversized(&loaded, args.strict)?;
+        }
+
+        // Printed once; further PDF outputs are copies of the finished first one
+        let mut rendered_pdf: Option<&Path> = None;
+
+        for (path, format) in &outputs {
+            match format {
+                output::OutputFormat::Pdf => {
+                    match rendered_pdf {
+                        Some(first) => {
+                            println!("Writing PDF: {:?}", path);
+                            fs::copy(first, path)
+                                .with_context(|| format!("Failed to save PDF: {:?}", path))?;
+                            continue;
+                        }
+                        None if chunked => {
+                            let chunks = chunks::split_chapters(&markdown, args.chunk_chapters);
+                            render_chunked(
+                                chunks,
+                                &html_options,
+                                &chrome_options,
+                                &document_url,
+                                header_footer.as_ref(),
+                                args.strict,
+                                path,
+                            )?;
+                        }
+                        None => {
+                            println!("Generating PDF: {:?}", path);
+                            // A different first page needs the header and
+                            // footer stamped on afterwards
+                            let (inline, stamped) = match &header_footer {
+                                Some(hf) if hf.first_page.is_some() => (None, Some(hf)),
+                                other => (other.as_ref(), None),
+                            };
+                            let print = |s: &chrome::Session| {
+                                chrome::print_to_file(
+                                    &s.tab,
+                                    print_options(
+                                        inline.is_some(),
+                                        inline.map_or("", |hf| hf.header.as_str()),
+                                        inline.map_or("", |hf| hf.footer.as_str()),
+                                        html_options.print_background,
+                                    ),
+                                    path,
+                                )
+                            };
+                            chrome::with_retries(
+                                &mut session,
+                                &full_document_url,
+                                &chrome_options,
+                                print,
+                            )?;
+                            // Second pass: page numbers are only known once
+                            // the document has been laid out on pages
+                            if page_refs::present(&html_content) {
+                                println!("Filling in page numbers...");
+                                let pages = pdf_tools::named_destinations(path)?;
+                                let url = document_url(&page_refs::fill(&html_content, &pages))?;
+                                let mut numbered = None;
+                                chrome::with_retries(&mut numbered, &url, &chrome_options, print)?;
+                            }
+                            if let Some(header_footer) = stamped {
+                                stamp_header_footer(
+                                    path,
+                                    &chrome_options,
+                                    &document_url,
+                                    header_footer,
+                                )?;
+                            }
+                            let form_fields = forms::fields(&html_content);
+                            if !form_fields.is_empty() && (args.nup.is_some() || args.booklet) {
+                                // pdfjam re-imposes the pages and drops the AcroForm
+                                warnings::warn(format!(
+                                    "{} form fields left out: --nup and --booklet do not keep them",
+                                    form_fields.len()
+                                ));
+                            } else if !form_fields.is_empty() {
+                                println!("Adding {} form fields...", form_fields.len());
+                                forms::add_fields(path, &form_fields)?;
+                            }
+                        }
+                    }
+
+                    if let Some(pages_per_sheet) = args.nup {
+                        println!("Placing {} pages per sheet...", pages_per_sheet);
+                        pdf_tools::nup_pdf(path, pages_per_sheet)?;
+                    }
+
+                    if args.booklet {
+                        println!("Imposing booklet pages...");
+                        pdf_tools::booklet_pdf(path, args.booklet_signature)?;
+                    }
+
+                    if args.optimize {
+                        println!("Optimizing PDF...");
+                        pdf_tools::optimize_pdf(path)?;
+                    }
+
+                    if args.sign {
+                        if let Some(cert) = &args.cert {
+                            println!("Signing PDF with certificate: {:?}", cert);
+                            let password = std::env::var(pdf_tools::CERT_PASSWORD_ENV).ok();
+                            pdf_tools::sign_pdf(path, cert, password.as_deref())?;
+                        }
+                    }
+
+                    rendered_pdf = Some(path);
+                }
+                output::OutputFormat::Png | output::OutputFormat::Jpeg => {
+                    println!("Capturing images: {:?}", path);
+                    let image_options = screenshot::ImageOptions {
+                        format: *format,
+                        width: args.image_width,
+                        full_page: args.full_page,
+                    };
+                    let images = chrome::with_retries(
+                        &mut session,
+                        &full_document_url,
+                        &chrome_options,
+                        |s| screenshot::capture(&s.tab, path, &image_options),
+                    )?;
+                    println!("  {} image(s) written", images.len());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if let Some(thumbnail) = &args.thumbnail {
+        let Some((pdf, _)) = outputs
+            .iter()
+            .find(|(_, format)| *format == output::OutputFormat::Pdf)
+        else {
+            anyhow::bail!("--thumbnail needs a PDF output");
+        };
+        println!("Rendering thumbnail: {:?}", thumbnail);
+        pdf_tools::thumbnail(pdf, thumbnail, args.thumbnail_width)?;
+    }
+
+    if let Some(page_map) = &args.page_map {
+        let Some((pdf, _)) = outputs
+            .iter()
+            .find(|(_, format)| *format == output::OutputFormat::Pdf)
+        else {
+            anyhow::bail!("--page-map needs a PDF output");
+        };
+        println!("Writing page map: {:?}", page_map);
+        let pages = pdf_tools::named_destinations(pdf)?;
+        let mut headings = page_refs::headings(&html_content);
+        for heading in &mut headings {
+            heading.page = pages.get(&heading.id).copied();
+        }
+        let json = serde_json::to_string_pretty(&headings)?;
+        fs::write(page_map, json)
+            .with_context(|| format!("Failed to write page map: {:?}", page_map))?;
+    }
+
+    hooks::run(&post_hooks, "post", &args.input, &primary_output)?;
+
+    for (path, _) in &outputs {
+        println!("✅ Successfully created: {:?}", path);
+    }
     Ok(())
 }
-
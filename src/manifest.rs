@@ -0,0 +1,71 @@
+use crate::template::Variables;
+use crate::warnings;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Finds the nearest `Cargo.toml` or `package.json` at or above the input and
+/// exposes its name, version, and description as `project.*` variables.
+pub fn project_variables(input: &Path) -> Result<Variables> {
+    let start = if input.is_dir() {
+        input
+    } else {
+        input.parent().unwrap_or(Path::new("."))
+    };
+    let start = start.canonicalize().unwrap_or_else(|_| start.to_path_buf());
+
+    for dir in start.ancestors() {
+        let cargo = dir.join("Cargo.toml");
+        if cargo.is_file() {
+            let content = fs::read_to_string(&cargo)
+                .with_context(|| format!("Failed to read manifest: {:?}", cargo))?;
+            let manifest: toml::Value = toml::from_str(&content)
+                .with_context(|| format!("Failed to parse manifest: {:?}", cargo))?;
+            // A workspace root shares its fields through [workspace.package]
+            let sections = [
+                manifest.get("package"),
+                manifest.get("workspace").and_then(|w| w.get("package")),
+            ];
+            let variables = collect(|key| {
+                sections
+                    .iter()
+                    .flatten()
+                    .find_map(|section| section.get(key).and_then(|v| v.as_str()))
+                    .map(str::to_string)
+            });
+            if variables.is_empty() {
+                warnings::warn(format!(
+                    "{:?} has no name, version, or description in [package] or [workspace.package]",
+                    cargo
+                ));
+            }
+            return Ok(variables);
+        }
+
+        let package_json = dir.join("package.json");
+        if package_json.is_file() {
+            let content = fs::read_to_string(&package_json)
+                .with_context(|| format!("Failed to read manifest: {:?}", package_json))?;
+            let manifest: serde_json::Value = serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse manifest: {:?}", package_json))?;
+            return Ok(collect(|key| {
+                manifest
+                    .get(key)
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+            }));
+        }
+    }
+
+    anyhow::bail!("No Cargo.toml or package.json found for {:?}", input)
+}
+
+fn collect(field: impl Fn(&str) -> Option<String>) -> Variables {
+    let mut variables = Variables::new();
+    for key in ["name", "version", "description"] {
+        if let Some(value) = field(key) {
+            variables.insert(format!("project.{}", key), value);
+        }
+    }
+    variables
+}
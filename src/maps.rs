@@ -0,0 +1,464 @@
+use crate::template::escape_html;
+use crate::warnings;
+use anyhow::{Context, Result};
+use pulldown_cmark::{CodeBlockKind, Event, Tag};
+use serde_json::{json, Value};
+use std::f64::consts::PI;
+
+/// Size of a rendered map in CSS pixels.
+const WIDTH: f64 = 640.0;
+const HEIGHT: f64 = 400.0;
+const PADDING: f64 = 24.0;
+const TILE_SIZE: f64 = 256.0;
+/// Closest zoom level, so a single point still shows its surroundings.
+const MAX_ZOOM: i32 = 17;
+/// Leaflet's default feature color.
+const DEFAULT_COLOR: &str = "#3388ff";
+
+pub const MAP_CSS: &str = r#"
+        .map { margin: 1em 0; }
+        .map-canvas { position: relative; overflow: hidden; background-color: #eef2f5; border: 1px solid #ddd; break-inside: avoid; }
+        .map-tile { position: absolute; width: 256px; height: 256px; max-width: none; }
+        .map-canvas svg { position: absolute; left: 0; top: 0; }
+        .map-attribution { font-size: 0.75em; color: #888; text-align: right; }"#;
+
+/// A longitude/latitude pair.
+type Position = [f64; 2];
+
+enum Shape {
+    Point(Position),
+    Line(Vec<Position>),
+    /// Outer ring, then holes
+    Polygon(Vec<Vec<Position>>),
+}
+
+struct Feature {
+    shapes: Vec<Shape>,
+    properties: Value,
+}
+
+fn position(value: &Value) -> Option<Position> {
+    Some([value.get(0)?.as_f64()?, value.get(1)?.as_f64()?])
+}
+
+fn positions(value: &Value) -> Vec<Position> {
+    value
+        .as_array()
+        .map(|values| values.iter().filter_map(position).collect())
+        .unwrap_or_default()
+}
+
+fn rings(value: &Value) -> Vec<Vec<Position>> {
+    value
+        .as_array()
+        .map(|values| values.iter().map(positions).collect())
+        .unwrap_or_default()
+}
+
+fn items(value: &Value) -> &[Value] {
+    value.as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+/// The shapes of a GeoJSON geometry, with multi-geometries and collections flattened.
+fn shapes(geometry: &Value, shapes_out: &mut Vec<Shape>) {
+    let coordinates = &geometry["coordinates"];
+    match geometry["type"].as_str().unwrap_or("") {
+        "Point" => shapes_out.extend(position(coordinates).map(Shape::Point)),
+        "MultiPoint" => shapes_out.extend(positions(coordinates).into_iter().map(Shape::Point)),
+        "LineString" => shapes_out.push(Shape::Line(positions(coordinates))),
+        "MultiLineString" => shapes_out.extend(
+            items(coordinates)
+                .iter()
+                .map(|line| Shape::Line(positions(line))),
+        ),
+        "Polygon" => shapes_out.push(Shape::Polygon(rings(coordinates))),
+        "MultiPolygon" => shapes_out.extend(
+            items(coordinates)
+                .iter()
+                .map(|polygon| Shape::Polygon(rings(polygon))),
+        ),
+        "GeometryCollection" => {
+            for geometry in items(&geometry["geometries"]) {
+                shapes(geometry, shapes_out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The features of a GeoJSON FeatureCollection, Feature, or bare geometry.
+fn features(geojson: &Value) -> Vec<Feature> {
+    match geojson["type"].as_str().unwrap_or("") {
+        "FeatureCollection" => items(&geojson["features"])
+            .iter()
+            .flat_map(features)
+            .collect(),
+        "Feature" => {
+            let mut shapes_out = Vec::new();
+            shapes(&geojson["geometry"], &mut shapes_out);
+            vec![Feature {
+                shapes: shapes_out,
+                properties: geojson["properties"].clone(),
+            }]
+        }
+        _ => {
+            let mut shapes_out = Vec::new();
+            shapes(geojson, &mut shapes_out);
+            vec![Feature {
+                shapes: shapes_out,
+                properties: Value::Null,
+            }]
+        }
+    }
+}
+
+/// Converts a TopoJSON topology into a GeoJSON FeatureCollection: arcs are
+/// un-quantized and stitched back into the lines and rings that use them.
+fn topojson_to_geojson(topology: &Value) -> Value {
+    let transform = &topology["transform"];
+    let scale = position(&transform["scale"]).unwrap_or([1.0, 1.0]);
+    let translate = position(&transform["translate"]).unwrap_or([0.0, 0.0]);
+    let quantized = transform.is_object();
+    let decode = |[x, y]: Position| [x * scale[0] + translate[0], y * scale[1] + translate[1]];
+
+    // Quantized arcs are delta-encoded from their first position
+    let arcs: Vec<Vec<Position>> = items(&topology["arcs"])
+        .iter()
+        .map(|arc| {
+            let mut current = [0.0, 0.0];
+            positions(arc)
+                .into_iter()
+                .map(|delta| {
+                    if quantized {
+                        current = [current[0] + delta[0], current[1] + delta[1]];
+                        decode(current)
+                    } else {
+                        delta
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    // A negative index ~i is arc i reversed; consecutive arcs share their end point
+    let line = |indices: &Value| -> Value {
+        let mut coordinates: Vec<Position> = Vec::new();
+        for index in items(indices).iter().filter_map(Value::as_i64) {
+            let (arc, reversed) = if index < 0 {
+                (!index as usize, true)
+            } else {
+                (index as usize, false)
+            };
+            let mut points = arcs.get(arc).cloned().unwrap_or_default();
+            if reversed {
+                points.reverse();
+            }
+            let skip = usize::from(!coordinates.is_empty());
+            coordinates.extend(points.into_iter().skip(skip));
+        }
+        json!(coordinates)
+    };
+    let lines = |value: &Value| -> Value { Value::Array(items(value).iter().map(line).collect()) };
+    let point = |value: &Value| -> Value {
+        match position(value) {
+            Some(position) if quantized => json!(decode(position)),
+            _ => value.clone(),
+        }
+    };
+
+    fn convert(
+        object: &Value,
+        line: &dyn Fn(&Value) -> Value,
+        lines: &dyn Fn(&Value) -> Value,
+        point: &dyn Fn(&Value) -> Value,
+        out: &mut Vec<Value>,
+    ) {
+        let arcs = &object["arcs"];
+        let kind = object["type"].as_str().unwrap_or("");
+        let coordinates = match kind {
+            "GeometryCollection" => {
+                for geometry in items(&object["geometries"]) {
+                    convert(geometry, line, lines, point, out);
+                }
+                return;
+            }
+            "Point" => point(&object["coordinates"]),
+            "MultiPoint" => Value::Array(items(&object["coordinates"]).iter().map(point).collect()),
+            "LineString" => line(arcs),
+            "MultiLineString" | "Polygon" => lines(arcs),
+            "MultiPolygon" => Value::Array(items(arcs).iter().map(lines).collect()),
+            _ => return,
+        };
+        out.push(json!({
+            "type": "Feature",
+            "properties": object["properties"].clone(),
+            "geometry": { "type": kind, "coordinates": coordinates },
+        }));
+    }
+
+    let mut features = Vec::new();
+    if let Some(objects) = topology["objects"].as_object() {
+        for object in objects.values() {
+            convert(object, &line, &lines, &point, &mut features);
+        }
+    }
+    json!({ "type": "FeatureCollection", "features": features })
+}
+
+/// Web Mercator: longitude and latitude to x/y in 0..1, north at the top.
+fn project([longitude, latitude]: Position) -> (f64, f64) {
+    let latitude = latitude.clamp(-85.0511, 85.0511).to_radians();
+    let x = (longitude + 180.0) / 360.0;
+    let y = (1.0 - (latitude.tan() + 1.0 / latitude.cos()).ln() / PI) / 2.0;
+    (x, y)
+}
+
+fn all_positions(shape: &Shape) -> Vec<Position> {
+    match shape {
+        Shape::Point(position) => vec![*position],
+        Shape::Line(line) => line.clone(),
+        Shape::Polygon(rings) => rings.iter().flatten().copied().collect(),
+    }
+}
+
+/// Where the map's view lies: its scale in pixels per world unit and the
+/// world coordinates of its center.
+struct View {
+    scale: f64,
+    center: (f64, f64),
+}
+
+impl View {
+    /// Fits every feature into the map; with tiles the scale snaps to a zoom level.
+    fn fit(features: &[Feature], tiles: bool) -> Option<View> {
+        let points: Vec<(f64, f64)> = features
+            .iter()
+            .flat_map(|feature| &feature.shapes)
+            .flat_map(all_positions)
+            .map(project)
+            .collect();
+        if points.is_empty() {
+            return None;
+        }
+        let min_x = points.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+        let max_x = points.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+        let min_y = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+        let max_y = points.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+
+        let max_scale = TILE_SIZE * 2f64.powi(MAX_ZOOM);
+        let mut scale = ((WIDTH - 2.0 * PADDING) / (max_x - min_x))
+            .min((HEIGHT - 2.0 * PADDING) / (max_y - min_y))
+            .min(max_scale);
+        if tiles {
+            let zoom = (scale / TILE_SIZE)
+                .log2()
+                .floor()
+                .clamp(0.0, MAX_ZOOM as f64);
+            scale = TILE_SIZE * 2f64.powf(zoom);
+        }
+        Some(View {
+            scale,
+            center: ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0),
+        })
+    }
+
+    /// A position in map pixels.
+    fn pixel(&self, position: Position) -> (f64, f64) {
+        let (x, y) = project(position);
+        (
+            (x - self.center.0) * self.scale + WIDTH / 2.0,
+            (y - self.center.1) * self.scale + HEIGHT / 2.0,
+        )
+    }
+
+    /// `<img>` tiles covering the map, from a `{z}/{x}/{y}` URL template.
+    fn tiles_html(&self, template: &str) -> String {
+        let zoom = (self.scale / TILE_SIZE).log2().round() as i32;
+        let count = 1i64 << zoom;
+        let left = self.center.0 * self.scale - WIDTH / 2.0;
+        let top = self.center.1 * self.scale - HEIGHT / 2.0;
+
+        let mut html = String::new();
+        let first_x = (left / TILE_SIZE).floor() as i64;
+        let last_x = ((left + WIDTH) / TILE_SIZE).floor() as i64;
+        let first_y = ((top / TILE_SIZE).floor() as i64).max(0);
+        let last_y = (((top + HEIGHT) / TILE_SIZE).floor() as i64).min(count - 1);
+        for y in first_y..=last_y {
+            for x in first_x..=last_x {
+                // Maps crossing the antimeridian wrap around
+                let url = template
+                    .replace("{z}", &zoom.to_string())
+                    .replace("{x}", &x.rem_euclid(count).to_string())
+                    .replace("{y}", &y.to_string())
+                    .replace("{s}", "a");
+                html.push_str(&format!(
+                    "<img class=\"map-tile\" src=\"{}\" alt=\"\" style=\"left: {:.0}px; top: {:.0}px\">",
+                    escape_html(&url),
+                    x as f64 * TILE_SIZE - left,
+                    y as f64 * TILE_SIZE - top
+                ));
+            }
+        }
+        html
+    }
+}
+
+/// A simplestyle property (`stroke`, `fill`, `marker-color`) or the default color.
+fn color(properties: &Value, name: &str) -> String {
+    escape_html(properties[name].as_str().unwrap_or(DEFAULT_COLOR))
+}
+
+fn path_data(points: &[Position], view: &View, close: bool) -> String {
+    let mut data = String::new();
+    for (index, point) in points.iter().enumerate() {
+        let (x, y) = view.pixel(*point);
+        let command = if index == 0 { 'M' } else { 'L' };
+        data.push_str(&format!("{}{:.1} {:.1} ", command, x, y));
+    }
+    if close && !data.is_empty() {
+        data.push('Z');
+    }
+    data
+}
+
+fn svg(features: &[Feature], view: &View) -> String {
+    let mut svg = format!(
+        "<svg width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" xmlns=\"http://www.w3.org/2000/svg\">",
+        w = WIDTH,
+        h = HEIGHT
+    );
+    for feature in features {
+        let properties = &feature.properties;
+        let stroke = color(properties, "stroke");
+        for shape in &feature.shapes {
+            match shape {
+                Shape::Polygon(rings) => {
+                    let data: String = rings
+                        .iter()
+                        .map(|ring| path_data(ring, view, true))
+                        .collect();
+                    svg.push_str(&format!(
+                        "<path d=\"{}\" fill=\"{}\" fill-opacity=\"0.25\" fill-rule=\"evenodd\" stroke=\"{}\" stroke-width=\"2\"/>",
+                        data,
+                        color(properties, "fill"),
+                        stroke
+                    ));
+                }
+                Shape::Line(line) => svg.push_str(&format!(
+                    "<path d=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"3\" stroke-linejoin=\"round\"/>",
+                    path_data(line, view, false),
+                    stroke
+                )),
+                Shape::Point(point) => {
+                    let (x, y) = view.pixel(*point);
+                    svg.push_str(&format!(
+                        "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"6\" fill=\"{}\" stroke=\"white\" stroke-width=\"2\"/>",
+                        x,
+                        y,
+                        color(properties, "marker-color")
+                    ));
+                    if let Some(label) = properties["name"]
+                        .as_str()
+                        .or_else(|| properties["title"].as_str())
+                    {
+                        svg.push_str(&format!(
+                            "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"12\" paint-order=\"stroke\" stroke=\"white\" stroke-width=\"3\">{}</text>",
+                            x + 9.0,
+                            y + 4.0,
+                            escape_html(label)
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Renders a GeoJSON (or TopoJSON) document as a static map: the features
+/// drawn as SVG, over map tiles when a tile URL template is given.
+fn render_map(source: &str, topojson: bool, tiles: Option<&str>) -> Result<String> {
+    let json: Value = serde_json::from_str(source).context("Invalid map data")?;
+    let json = if topojson || json["type"] == "Topology" {
+        topojson_to_geojson(&json)
+    } else {
+        json
+    };
+    let features = features(&json);
+    let Some(view) = View::fit(&features, tiles.is_some()) else {
+        anyhow::bail!("Map has no coordinates");
+    };
+
+    let mut html = format!(
+        "<figure class=\"map\">\n<div class=\"map-canvas\" style=\"width: {}px; height: {}px\">",
+        WIDTH, HEIGHT
+    );
+    if let Some(template) = tiles {
+        html.push_str(&view.tiles_html(template));
+    }
+    html.push_str(&svg(&features, &view));
+    html.push_str("</div>\n");
+    if let Some(template) = tiles {
+        let host = template
+            .split("://")
+            .nth(1)
+            .and_then(|rest| rest.split('/').next())
+            .unwrap_or(template);
+        html.push_str(&format!(
+            "<figcaption class=\"map-attribution\">Map tiles: {}</figcaption>\n",
+            escape_html(host)
+        ));
+    }
+    html.push_str("</figure>\n");
+    Ok(html)
+}
+
+/// Replaces ```` ```geojson ```` and ```` ```topojson ```` blocks with a
+/// static map of their features. `tiles` is a tile server URL template
+/// (`https://tile.openstreetmap.org/{z}/{x}/{y}.png`) for a base map; without
+/// one the features are drawn on a plain background. Blocks that don't parse
+/// stay code and are reported as warnings.
+pub fn render_maps<'a>(events: Vec<Event<'a>>, tiles: Option<&str>) -> Vec<Event<'a>> {
+    let mut result = Vec::with_capacity(events.len());
+    let mut block: Option<(Vec<Event<'a>>, String, bool)> = None;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(ref info))) => {
+                let language = info.split_whitespace().next().unwrap_or("");
+                if language == "geojson" || language == "topojson" {
+                    block = Some((Vec::new(), String::new(), language == "topojson"));
+                }
+                match block.as_mut() {
+                    Some((pending, _, _)) => pending.push(event),
+                    None => result.push(event),
+                }
+            }
+            Event::End(Tag::CodeBlock(_)) if block.is_some() => {
+                let Some((mut pending, source, topojson)) = block.take() else {
+                    continue;
+                };
+                match render_map(&source, topojson, tiles) {
+                    Ok(html) => result.push(Event::Html(html.into())),
+                    Err(err) => {
+                        warnings::warn(format!("Map not rendered: {:#}", err));
+                        pending.push(event);
+                        result.extend(pending);
+                    }
+                }
+            }
+            other => match block.as_mut() {
+                Some((pending, source, _)) => {
+                    if let Event::Text(text) = &other {
+                        source.push_str(text);
+                    }
+                    pending.push(other);
+                }
+                None => result.push(other),
+            },
+        }
+    }
+
+    result
+}
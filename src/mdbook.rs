@@ -0,0 +1,206 @@
+use crate::template::escape_html;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct BookToml {
+    book: BookSection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct BookSection {
+    title: Option<String>,
+    authors: Vec<String>,
+    src: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub enum SummaryItem {
+    Part(String),
+    Chapter {
+        depth: usize,
+        title: String,
+        path: Option<PathBuf>,
+    },
+    Separator,
+}
+
+/// An ordered table of contents, from mdBook's SUMMARY.md or an MkDocs nav.
+#[derive(Debug)]
+pub struct Book {
+    pub title: Option<String>,
+    pub authors: Vec<String>,
+    pub src: PathBuf,
+    pub items: Vec<SummaryItem>,
+}
+
+fn parse_link(text: &str) -> Option<(String, Option<PathBuf>)> {
+    let text = text.trim();
+    let rest = text.strip_prefix('[')?;
+    let (title, rest) = rest.split_once("](")?;
+    let target = rest.strip_suffix(')')?.trim();
+    let path = (!target.is_empty()).then(|| PathBuf::from(target));
+    Some((title.trim().to_string(), path))
+}
+
+fn parse_summary(summary: &str) -> Vec<SummaryItem> {
+    let mut items = Vec::new();
+    let mut indents: Vec<usize> = Vec::new();
+    let mut seen_item = false;
+
+    for line in summary.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        if trimmed.chars().all(|c| c == '-') && trimmed.len() >= 3 {
+            items.push(SummaryItem::Separator);
+        } else if let Some(part) = trimmed.strip_prefix("# ") {
+            // The first heading is the summary's own title
+            if seen_item {
+                items.push(SummaryItem::Part(part.trim().to_string()));
+                indents.clear();
+            }
+        } else if let Some(entry) = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+        {
+            let indent = line.len() - line.trim_start().len();
+            while indents.last().map_or(false, |&last| last > indent) {
+                indents.pop();
+            }
+            if indents.last() != Some(&indent) {
+                indents.push(indent);
+            }
+            if let Some((title, path)) = parse_link(entry) {
+                items.push(SummaryItem::Chapter {
+                    depth: indents.len(),
+                    title,
+                    path,
+                });
+            }
+        } else if let Some((title, path)) = parse_link(trimmed) {
+            // Prefix and suffix chapters are unnumbered top-level links
+            items.push(SummaryItem::Chapter {
+                depth: 1,
+                title,
+                path,
+            });
+        }
+
+        seen_item = true;
+    }
+
+    items
+}
+
+/// Loads an mdBook project when `book.toml` exists at the input root.
+pub fn load(root: &Path) -> Result<Option<Book>> {
+    let book_toml = root.join("book.toml");
+    if !book_toml.is_file() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(&book_toml)
+        .with_context(|| format!("Failed to read {:?}", book_toml))?;
+    let config: BookToml =
+        toml::from_str(&content).with_context(|| format!("Failed to parse {:?}", book_toml))?;
+
+    let src = root.join(config.book.src.unwrap_or_else(|| PathBuf::from("src")));
+    let summary_path = src.join("SUMMARY.md");
+    let summary = fs::read_to_string(&summary_path)
+        .with_context(|| format!("Failed to read {:?}", summary_path))?;
+
+    Ok(Some(Book {
+        title: config.book.title,
+        authors: config.book.authors,
+        src,
+        items: parse_summary(&summary),
+    }))
+}
+
+impl Book {
+    /// Files of the chapters in reading order, relative to `src`.
+    pub fn chapter_paths(&self) -> Vec<&Path> {
+        self.items
+            .iter()
+            .filter_map(|item| match item {
+                SummaryItem::Chapter {
+                    path: Some(path), ..
+                } => Some(path.as_path()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// A piece of the book that `combine` leaves to the caller.
+pub enum Section<'a> {
+    /// A chapter's file, its title from the summary, and its heading level
+    Chapter(&'a Path, &'a str, usize),
+    /// The end of a top-level chapter at this level and the chapters below it
+    End(usize),
+}
+
+/// Builds the combined markdown in SUMMARY.md order. Parts become H1
+/// headings, chapters are nested below them by their list depth, and draft
+/// chapters (without a file) only contribute their heading. `section` writes
+/// the markdown for chapters with a file and for the end of each top-level
+/// chapter.
+pub fn combine(
+    book: &Book,
+    title: &str,
+    preface: &str,
+    mut section: impl FnMut(Section) -> String,
+) -> String {
+    let mut combined = format!("# {}\n\n", escape_html(title));
+    if !book.authors.is_empty() {
+        combined.push_str(&format!("*{}*\n\n", book.authors.join(", ")));
+    }
+    combined.push_str(preface);
+
+    let mut in_part = false;
+    let mut open_chapter = None;
+    for item in &book.items {
+        let starts_chapter = matches!(
+            item,
+            SummaryItem::Part(_) | SummaryItem::Chapter { depth: 1, .. }
+        );
+        if starts_chapter {
+            if let Some(level) = open_chapter.take() {
+                combined.push_str(&section(Section::End(level)));
+            }
+        }
+
+        match item {
+            SummaryItem::Part(name) => {
+                combined.push_str(&format!("# {}\n\n", name));
+                in_part = true;
+            }
+            SummaryItem::Separator => combined.push_str("\n---\n\n"),
+            SummaryItem::Chapter { depth, title, path } => {
+                let level = (depth + usize::from(in_part)).min(6);
+                if *depth == 1 {
+                    open_chapter = Some(level);
+                }
+                match path {
+                    Some(path) => {
+                        combined.push_str(&section(Section::Chapter(path, title, level)));
+                        combined.push_str("\n\n");
+                    }
+                    None => combined.push_str(&format!("{} {}\n\n", "#".repeat(level), title)),
+                }
+            }
+        }
+    }
+    if let Some(level) = open_chapter {
+        combined.push_str(&section(Section::End(level)));
+    }
+
+    combined
+}
@@ -0,0 +1,182 @@
+use crate::qr;
+use crate::template::escape_html;
+use anyhow::Result;
+use base64::Engine;
+use std::io::Read;
+
+/// A video embed replaced by a printable placeholder.
+struct Embed {
+    url: String,
+    title: Option<String>,
+    thumbnail: Option<String>,
+}
+
+/// The value of `name="..."` (or `name='...'`) in an HTML tag.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    ['"', '\''].iter().find_map(|quote| {
+        let start = tag.find(&format!(" {}={}", name, quote))? + name.len() + 3;
+        let end = tag[start..].find(*quote)? + start;
+        Some(&tag[start..end])
+    })
+}
+
+/// The video id of a YouTube embed or watch URL.
+fn youtube_id(url: &str) -> Option<&str> {
+    let id = [
+        "youtube.com/embed/",
+        "youtube-nocookie.com/embed/",
+        "youtu.be/",
+    ]
+    .iter()
+    .find_map(|prefix| url.split_once(prefix).map(|(_, id)| id))
+    .or_else(|| url.split_once("youtube.com/watch?v=").map(|(_, id)| id))?;
+    let id = id.split(['?', '&', '#', '/']).next().unwrap_or("");
+    (!id.is_empty()).then_some(id)
+}
+
+fn fetch_bytes(url: &str) -> Option<(String, Vec<u8>)> {
+    let response = ureq::get(url).call().ok()?;
+    let mime = response.content_type().to_string();
+    let mut bytes = Vec::new();
+    response.into_reader().read_to_end(&mut bytes).ok()?;
+    Some((mime, bytes))
+}
+
+/// The embed's link, title, and thumbnail. YouTube titles and thumbnails
+/// are looked up online unless `fetch` is off; a `<video>`'s poster is
+/// used as is.
+fn describe(tag: &str, inner: &str, fetch: bool) -> Option<Embed> {
+    let source = attribute(tag, "src")
+        .or_else(|| {
+            let start = inner.find("<source ")?;
+            attribute(&inner[start..], "src")
+        })?
+        .to_string();
+    let mut embed = Embed {
+        url: source.clone(),
+        title: attribute(tag, "title").map(str::to_string),
+        thumbnail: attribute(tag, "poster").map(str::to_string),
+    };
+
+    if let Some(id) = youtube_id(&source) {
+        embed.url = format!("https://www.youtube.com/watch?v={}", id);
+        if fetch {
+            if embed.title.is_none() {
+                embed.title = fetch_bytes(&format!(
+                    "https://www.youtube.com/oembed?format=json&url={}",
+                    embed.url
+                ))
+                .and_then(|(_, json)| serde_json::from_slice::<serde_json::Value>(&json).ok())
+                .and_then(|json| json["title"].as_str().map(str::to_string));
+            }
+            embed.thumbnail =
+                fetch_bytes(&format!("https://img.youtube.com/vi/{}/hqdefault.jpg", id)).map(
+                    |(mime, bytes)| {
+                        format!(
+                            "data:{};base64,{}",
+                            mime,
+                            base64::engine::general_purpose::STANDARD.encode(bytes)
+                        )
+                    },
+                );
+        }
+    }
+
+    Some(embed)
+}
+
+fn placeholder(embed: &Embed) -> Result<String> {
+    let url = escape_html(&embed.url);
+    let mut html = String::from("\n<figure class=\"media-placeholder\">\n");
+    if let Some(thumbnail) = &embed.thumbnail {
+        html.push_str(&format!(
+            "<a href=\"{}\"><img class=\"media-thumbnail\" src=\"{}\" alt=\"\"></a>\n",
+            url,
+            escape_html(thumbnail)
+        ));
+    }
+    html.push_str("<figcaption>");
+    if let Some(title) = &embed.title {
+        html.push_str(&format!("<strong>▶ {}</strong><br>", escape_html(title)));
+    }
+    html.push_str(&format!("<a href=\"{}\">{}</a>", url, url));
+    if embed.url.starts_with("http") {
+        html.push_str(&qr::qr_image(&embed.url)?);
+    }
+    html.push_str("</figcaption>\n</figure>\n\n");
+    Ok(html)
+}
+
+/// Replaces `<iframe>` video embeds and `<video>` tags, which print as blank
+/// boxes, with a placeholder: the thumbnail (a YouTube still or the video's
+/// poster), its title, and the link as text and QR code. Other iframes
+/// (maps, forms) get the same treatment without a thumbnail.
+pub fn replace_embeds(markdown: &str, fetch: bool) -> Result<String> {
+    let mut result = String::with_capacity(markdown.len());
+    let mut in_code_block = false;
+    let mut pending = String::new();
+
+    for line in markdown.lines() {
+        if pending.is_empty() && line.trim().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block
+            || (pending.is_empty() && !line.contains("<iframe") && !line.contains("<video"))
+        {
+            result.push_str(line);
+            result.push('\n');
+            continue;
+        }
+
+        // An embed can span several lines; collect until it is closed
+        pending.push_str(line);
+        pending.push('\n');
+        let mut rest = pending.as_str();
+        let mut replaced = String::new();
+        let mut complete = true;
+        loop {
+            let start = match (rest.find("<iframe"), rest.find("<video")) {
+                (Some(a), Some(b)) => a.min(b),
+                (Some(a), None) | (None, Some(a)) => a,
+                (None, None) => break,
+            };
+            let name = if rest[start..].starts_with("<iframe") {
+                "iframe"
+            } else {
+                "video"
+            };
+            let close = format!("</{}>", name);
+            let Some(tag_end) = rest[start..].find('>').map(|end| start + end + 1) else {
+                complete = false;
+                break;
+            };
+            let tag = &rest[start..tag_end];
+            let end = if tag.ends_with("/>") {
+                tag_end
+            } else {
+                match rest[tag_end..].find(&close) {
+                    Some(end) => tag_end + end + close.len(),
+                    None => {
+                        complete = false;
+                        break;
+                    }
+                }
+            };
+
+            replaced.push_str(&rest[..start]);
+            match describe(tag, &rest[tag_end..end], fetch) {
+                Some(embed) => replaced.push_str(&placeholder(&embed)?),
+                None => replaced.push_str(&rest[start..end]),
+            }
+            rest = &rest[end..];
+        }
+        if complete {
+            replaced.push_str(rest);
+            result.push_str(&replaced);
+            pending.clear();
+        }
+    }
+    result.push_str(&pending);
+
+    Ok(result)
+}
@@ -0,0 +1,100 @@
+use crate::mdbook::{Book, SummaryItem};
+use crate::titles;
+use anyhow::{Context, Result};
+use serde_yaml::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn nav_items(entries: &[Value], depth: usize, docs_dir: &Path, items: &mut Vec<SummaryItem>) {
+    for entry in entries {
+        match entry {
+            // A bare path takes its title from the page itself
+            Value::String(path) => {
+                let title = fs::read_to_string(docs_dir.join(path))
+                    .ok()
+                    .and_then(|content| titles::first_heading(&content))
+                    .unwrap_or_else(|| {
+                        let stem = Path::new(path).file_stem().unwrap_or_default();
+                        titles::prettify(&stem.to_string_lossy())
+                    });
+                items.push(SummaryItem::Chapter {
+                    depth,
+                    title,
+                    path: Some(PathBuf::from(path)),
+                });
+            }
+            Value::Mapping(mapping) => {
+                for (title, target) in mapping {
+                    let title = title.as_str().unwrap_or_default().to_string();
+                    match target {
+                        Value::String(path) if path.contains("://") => {}
+                        Value::String(path) => items.push(SummaryItem::Chapter {
+                            depth,
+                            title,
+                            path: Some(PathBuf::from(path)),
+                        }),
+                        Value::Sequence(children) => {
+                            items.push(SummaryItem::Chapter {
+                                depth,
+                                title,
+                                path: None,
+                            });
+                            nav_items(children, depth + 1, docs_dir, items);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Loads the `nav:` of an MkDocs site, looking for `mkdocs.yml` in the input
+/// directory or next to it (when the input is the site's docs directory).
+pub fn load(input: &Path) -> Result<Option<Book>> {
+    let candidates = [Some(input), input.parent()];
+    let Some(config_path) = candidates
+        .into_iter()
+        .flatten()
+        .map(|dir| dir.join("mkdocs.yml"))
+        .find(|path| path.is_file())
+    else {
+        return Ok(None);
+    };
+
+    let content = fs::read_to_string(&config_path)
+        .with_context(|| format!("Failed to read {:?}", config_path))?;
+    let config: Value = serde_yaml::from_str(&content)
+        .with_context(|| format!("Failed to parse {:?}", config_path))?;
+
+    let Some(nav) = config.get("nav").and_then(Value::as_sequence) else {
+        // Without a nav MkDocs orders pages alphabetically, like directory mode
+        return Ok(None);
+    };
+
+    let site_dir = config_path.parent().unwrap_or(Path::new("."));
+    let docs_dir = site_dir.join(
+        config
+            .get("docs_dir")
+            .and_then(Value::as_str)
+            .unwrap_or("docs"),
+    );
+
+    let mut items = Vec::new();
+    nav_items(nav, 1, &docs_dir, &mut items);
+
+    Ok(Some(Book {
+        title: config
+            .get("site_name")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        authors: config
+            .get("site_author")
+            .and_then(Value::as_str)
+            .map(|author| vec![author.to_string()])
+            .unwrap_or_default(),
+        src: docs_dir,
+        items,
+    }))
+}
@@ -0,0 +1,61 @@
+use crate::template::escape_html;
+use pulldown_cmark::{CodeBlockKind, Event, Tag};
+
+/// abcjs, which engraves ABC notation as SVG in the page.
+const ABCJS_URL: &str = "https://cdn.jsdelivr.net/npm/abcjs@6.4.4/dist/abcjs-basic-min.js";
+
+pub const MUSIC_CSS: &str = r#"
+        .abc-notation { margin: 1em 0; break-inside: avoid; }
+        .abc-notation svg { max-width: 100%; }"#;
+
+/// Scripts that replace every notation block's source with its engraved
+/// score; without JavaScript or network access the source stays as code.
+pub fn scripts() -> String {
+    format!(
+        r#"<script src="{}"></script>
+<script>
+document.querySelectorAll('.abc-notation').forEach(function (element) {{
+    if (window.ABCJS) {{
+        ABCJS.renderAbc(element, element.textContent, {{ responsive: 'resize' }});
+    }}
+}});
+</script>
+"#,
+        ABCJS_URL
+    )
+}
+
+/// Wraps ```` ```abc ```` blocks in a `<div class="abc-notation">` that
+/// [`scripts`] engraves into staves.
+pub fn render_abc(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut result = Vec::with_capacity(events.len());
+    let mut source: Option<String> = None;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(ref info)))
+                if info.split_whitespace().next() == Some("abc") =>
+            {
+                source = Some(String::new());
+            }
+            Event::End(Tag::CodeBlock(_)) if source.is_some() => {
+                let abc = source.take().unwrap_or_default();
+                result.push(Event::Html(
+                    format!(
+                        "<div class=\"abc-notation\"><pre><code class=\"language-abc\">{}</code></pre></div>\n",
+                        escape_html(&abc)
+                    )
+                    .into(),
+                ));
+            }
+            Event::Text(text) if source.is_some() => {
+                if let Some(abc) = source.as_mut() {
+                    abc.push_str(&text);
+                }
+            }
+            other => result.push(other),
+        }
+    }
+
+    result
+}
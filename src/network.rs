@@ -0,0 +1,91 @@
+use crate::server;
+use anyhow::{Context, Result};
+use headless_chrome::browser::tab::RequestPausedDecision;
+use headless_chrome::protocol::cdp::Fetch::FailRequest;
+use headless_chrome::protocol::cdp::Network::ErrorReason;
+use headless_chrome::Tab;
+use std::sync::Arc;
+
+/// Which requests the rendering page may make.
+#[derive(Debug, Clone, Default)]
+pub struct RequestPolicy {
+    /// Block every network request
+    pub offline: bool,
+    /// Let requests to the asset servers md2pdf started through, even offline
+    pub local_server: bool,
+    /// URL patterns that may be fetched; when empty, anything not denied may
+    pub allow: Vec<String>,
+    /// URL patterns that are always blocked
+    pub deny: Vec<String>,
+}
+
+/// Matches a URL against a pattern where `*` stands for any run of characters,
+/// e.g. `https://cdn.example.com/*`.
+fn wildcard_match(pattern: &str, url: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(first) = parts.next() else {
+        return url.is_empty();
+    };
+    let Some(mut rest) = url.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No wildcard: the pattern must match exactly
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+impl RequestPolicy {
+    fn restricts(&self) -> bool {
+        self.offline || !self.allow.is_empty() || !self.deny.is_empty()
+    }
+
+    fn allows(&self, url: &str) -> bool {
+        if self.local_server && server::serves(url) {
+            return true;
+        }
+        if self.offline || self.deny.iter().any(|pattern| wildcard_match(pattern, url)) {
+            return false;
+        }
+        self.allow.is_empty()
+            || self
+                .allow
+                .iter()
+                .any(|pattern| wildcard_match(pattern, url))
+    }
+}
+
+/// Intercepts the tab's requests and fails those the policy doesn't allow.
+/// `data:` URLs never reach the network and are unaffected.
+pub fn enforce(tab: &Tab, policy: RequestPolicy) -> Result<()> {
+    if !policy.restricts() {
+        return Ok(());
+    }
+
+    tab.enable_fetch(None, None)
+        .context("Failed to enable request interception")?;
+    tab.enable_request_interception(Arc::new(move |_transport, _session_id, intercepted| {
+        let url = &intercepted.params.request.url;
+        if policy.allows(url) {
+            RequestPausedDecision::Continue(None)
+        } else {
+            eprintln!("Blocked request: {}", url);
+            RequestPausedDecision::Fail(FailRequest {
+                request_id: intercepted.params.request_id,
+                error_reason: ErrorReason::BlockedByClient,
+            })
+        }
+    }))
+    .context("Failed to enable request interception")?;
+
+    Ok(())
+}
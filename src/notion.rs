@@ -0,0 +1,79 @@
+use crate::images;
+use std::path::Path;
+
+/// Strips the 32-hex-digit id Notion appends to exported page and folder names.
+pub fn clean_name(name: &str) -> String {
+    match name.rsplit_once(' ') {
+        Some((title, id)) if id.len() == 32 && id.chars().all(|c| c.is_ascii_hexdigit()) => {
+            title.trim().to_string()
+        }
+        _ => name.to_string(),
+    }
+}
+
+/// Embeds images referenced relative to the page. Notion URL-encodes these
+/// paths and places them in a folder named after the page.
+pub fn resolve_assets(markdown: &str, page_dir: &Path) -> String {
+    images::rewrite_image_targets(markdown, |target| {
+        if images::is_remote(target) {
+            return None;
+        }
+        let path = page_dir.join(images::percent_decode(target));
+        images::data_uri(&path).ok()
+    })
+}
+
+fn parse_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+fn table_cell(field: &str) -> String {
+    field.trim().replace('|', "\\|").replace('\n', "<br>")
+}
+
+/// Renders an exported Notion database (CSV) as a markdown table.
+pub fn csv_to_markdown(csv: &str) -> String {
+    let mut rows = csv
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(parse_csv_line);
+
+    let Some(header) = rows.next() else {
+        return String::new();
+    };
+
+    let mut table = String::new();
+    table.push_str(&format!(
+        "| {} |\n",
+        header
+            .iter()
+            .map(|h| table_cell(h))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    ));
+    table.push_str(&format!("|{}\n", "---|".repeat(header.len())));
+    for row in rows {
+        let mut cells: Vec<String> = row.iter().map(|c| table_cell(c)).collect();
+        cells.resize(header.len(), String::new());
+        table.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+    table
+}
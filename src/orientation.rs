@@ -0,0 +1,18 @@
+use crate::frontmatter::Frontmatter;
+
+/// Whether the frontmatter asks for `orientation: landscape`.
+pub fn is_landscape(frontmatter: &Frontmatter) -> bool {
+    frontmatter.get("orientation").map_or(false, |value| {
+        value.trim().eq_ignore_ascii_case("landscape")
+    })
+}
+
+/// Class added to a combined file's `<section>` so it prints on landscape
+/// pages while the rest of the document stays portrait.
+pub fn section_class(frontmatter: &Frontmatter) -> &'static str {
+    if is_landscape(frontmatter) {
+        " landscape"
+    } else {
+        ""
+    }
+}
@@ -0,0 +1,43 @@
+use clap::ValueEnum;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// A paginated PDF
+    #[default]
+    Pdf,
+    /// PNG images, one per page (or one for the whole document with --full-page)
+    Png,
+    /// JPEG images, one per page (or one for the whole document with --full-page)
+    Jpeg,
+    /// The intermediate HTML document
+    Html,
+    /// An EPUB 3 e-book
+    Epub,
+}
+
+impl OutputFormat {
+    /// The format implied by the file extension, or `default` when the
+    /// extension is missing or unknown.
+    pub fn for_path(path: &Path, default: OutputFormat) -> OutputFormat {
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("pdf") => OutputFormat::Pdf,
+            Some("png") => OutputFormat::Png,
+            Some("jpg" | "jpeg") => OutputFormat::Jpeg,
+            Some("html" | "htm") => OutputFormat::Html,
+            Some("epub") => OutputFormat::Epub,
+            _ => default,
+        }
+    }
+
+    /// Whether producing this format needs the page rendered in Chrome.
+    pub fn needs_chrome(self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Pdf | OutputFormat::Png | OutputFormat::Jpeg
+        )
+    }
+}
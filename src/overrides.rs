@@ -0,0 +1,57 @@
+use crate::directives;
+use crate::frontmatter::Frontmatter;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Applies `css:` and `template:` frontmatter overrides to one document.
+/// The stylesheet is scoped to `scope` with `@scope`, and the template is
+/// an HTML file whose `{content}` placeholder receives the document. Both
+/// are resolved against `base_dir` and must lie inside `root`.
+pub fn apply(
+    content: &str,
+    frontmatter: &Frontmatter,
+    base_dir: &Path,
+    root: &Path,
+    scope: &str,
+) -> Result<String> {
+    let mut result = String::new();
+
+    if let Some(css) = frontmatter.get("css") {
+        let path = directives::confine(root, &base_dir.join(css))
+            .context("Stylesheet from frontmatter `css` not allowed")?;
+        let stylesheet = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read stylesheet: {:?}", path))?;
+        // Kept on one line so heading preprocessing never sees `#id` selectors
+        let stylesheet = stylesheet
+            .lines()
+            .map(str::trim)
+            .collect::<Vec<_>>()
+            .join(" ");
+        result.push_str(&format!(
+            "<style>@scope ({}) {{ {} }}</style>\n\n",
+            scope, stylesheet
+        ));
+    }
+
+    match frontmatter.get("template") {
+        Some(template) => {
+            let path = directives::confine(root, &base_dir.join(template))
+                .context("Template from frontmatter `template` not allowed")?;
+            let html = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read template: {:?}", path))?;
+            let (before, after) = html
+                .split_once("{content}")
+                .with_context(|| format!("Template has no {{content}} placeholder: {:?}", path))?;
+            result.push_str(before.trim());
+            result.push_str("\n\n");
+            result.push_str(content);
+            result.push_str("\n\n");
+            result.push_str(after.trim());
+            result.push('\n');
+        }
+        None => result.push_str(content),
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,41 @@
+use crate::frontmatter::Frontmatter;
+
+/// Parses a heading level written as `h2` or `2`.
+pub fn parse_level(value: &str) -> Result<u8, String> {
+    let trimmed = value.trim();
+    let digits = trimmed
+        .strip_prefix('h')
+        .or_else(|| trimmed.strip_prefix('H'))
+        .unwrap_or(trimmed);
+    match digits.parse() {
+        Ok(level @ 1..=6) => Ok(level),
+        _ => Err(format!("expected a heading level h1-h6, got {:?}", value)),
+    }
+}
+
+/// Heading levels listed in the frontmatter's `page_break_before: [h1, h2]`.
+pub fn from_frontmatter(frontmatter: &Frontmatter) -> Vec<u8> {
+    frontmatter
+        .list("page_break_before")
+        .iter()
+        .filter_map(|value| parse_level(value).ok())
+        .collect()
+}
+
+/// Starts a page before every heading of the given levels, except a heading
+/// marked `{.no-newpage}`. A heading marked `{.newpage}` always starts one.
+pub fn css(levels: &[u8]) -> String {
+    let mut css = String::from(
+        r#"
+        .newpage { break-before: page; }
+        .no-newpage { break-before: auto !important; }"#,
+    );
+    if !levels.is_empty() {
+        let selectors: Vec<String> = levels.iter().map(|level| format!("h{}", level)).collect();
+        css.push_str(&format!(
+            "\n        {} {{ break-before: page; }}",
+            selectors.join(", ")
+        ));
+    }
+    css
+}
@@ -0,0 +1,119 @@
+use crate::template::escape_html;
+use serde::Serialize;
+use std::collections::HashMap;
+
+const OPEN: &str = "<span class=\"page-ref\" data-target=\"";
+const CLOSE: &str = "\"></span>";
+
+/// An empty marker that [`fill`] replaces with the page number of the
+/// element with id `target` once the document has been rendered once.
+pub fn placeholder(target: &str) -> String {
+    format!("{}{}{}", OPEN, escape_html(target), CLOSE)
+}
+
+/// Whether the HTML has page references that need a second rendering pass.
+pub fn present(html: &str) -> bool {
+    html.contains(OPEN)
+}
+
+/// Writes page numbers into the placeholders; unknown targets stay empty.
+pub fn fill(html: &str, pages: &HashMap<String, usize>) -> String {
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(OPEN) {
+        let target_start = start + OPEN.len();
+        let Some(end) = rest[target_start..].find(CLOSE) else {
+            break;
+        };
+        let target = &rest[target_start..target_start + end];
+        result.push_str(&rest[..target_start]);
+        result.push_str(target);
+        result.push_str("\">");
+        if let Some(page) = pages.get(&target.replace("&amp;", "&")) {
+            result.push_str(&page.to_string());
+        }
+        result.push_str("</span>");
+        rest = &rest[target_start + end + CLOSE.len()..];
+    }
+    result.push_str(rest);
+    result
+}
+
+#[derive(Debug, Serialize)]
+pub struct Heading {
+    pub id: String,
+    pub level: usize,
+    pub title: String,
+    /// Filled in from the rendered PDF
+    pub page: Option<usize>,
+}
+
+fn strip_tags(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .trim()
+        .to_string()
+}
+
+/// Every `<hN id="...">` in the HTML, in document order.
+pub fn headings(html: &str) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find("<h") {
+        rest = &rest[start + 2..];
+        let Some(level) = rest
+            .chars()
+            .next()
+            .and_then(|c| c.to_digit(10))
+            .filter(|level| (1..=6).contains(level))
+        else {
+            continue;
+        };
+        let Some(attributes) = rest[1..].strip_prefix(" id=\"") else {
+            continue;
+        };
+        let Some(id_end) = attributes.find('"') else {
+            continue;
+        };
+        let close = format!("</h{}>", level);
+        let Some(content_start) = attributes.find('>') else {
+            continue;
+        };
+        let Some(content_end) = attributes[content_start..].find(&close) else {
+            continue;
+        };
+        headings.push(Heading {
+            id: attributes[..id_end].replace("&amp;", "&"),
+            level: level as usize,
+            title: strip_tags(&attributes[content_start + 1..content_start + content_end]),
+            page: None,
+        });
+        rest = &attributes[content_start + content_end..];
+    }
+    headings
+}
+
+/// Invisible links to every heading. Chrome only writes named destinations
+/// for link targets, and those are what the page map is read from.
+pub fn heading_links(headings: &[Heading]) -> String {
+    let mut html = String::from(
+        "<nav class=\"page-map\" aria-hidden=\"true\" style=\"position: absolute; width: 0; height: 0; overflow: hidden\">",
+    );
+    for heading in headings {
+        html.push_str(&format!("<a href=\"#{}\"></a>", escape_html(&heading.id)));
+    }
+    html.push_str("</nav>\n");
+    html
+}
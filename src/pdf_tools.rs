@@ -0,0 +1,392 @@
+use crate::tempdir::TempDir;
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Runs the tool and returns its standard output.
+fn tool_output(mut command: Command, tool: &str) -> Result<String> {
+    let output = command
+        .output()
+        .with_context(|| format!("Failed to run {}. Make sure it is installed.", tool))?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "{} failed: {}",
+            tool,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn run_tool(command: Command, tool: &str) -> Result<()> {
+    tool_output(command, tool).map(|_| ())
+}
+
+fn sibling_path(pdf: &Path, suffix: &str) -> PathBuf {
+    pdf.with_extension(format!("{}.pdf", suffix))
+}
+
+pub fn optimize_pdf(pdf: &Path) -> Result<()> {
+    let optimized = sibling_path(pdf, "optimized");
+
+    let mut command = Command::new("qpdf");
+    command
+        .args([
+            "--linearize",
+            "--object-streams=generate",
+            "--remove-unreferenced-resources=yes",
+            "--recompress-flate",
+            "--compression-level=9",
+            "--optimize-images",
+        ])
+        .arg(pdf)
+        .arg(&optimized);
+    run_tool(command, "qpdf")?;
+
+    fs::rename(&optimized, pdf)
+        .with_context(|| format!("Failed to save optimized PDF: {:?}", pdf))?;
+    Ok(())
+}
+
+/// Environment variable holding the PKCS#12 certificate password; a flag
+/// would show it to every user in `ps`.
+pub const CERT_PASSWORD_ENV: &str = "MD2PDF_CERT_PASSWORD";
+
+/// Writes the password to a new file readable only by this user, inside a
+/// private temporary directory.
+fn write_passfile(dir: &Path, password: &str) -> Result<PathBuf> {
+    let passfile = dir.join("cert.pass");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(&passfile)
+        .and_then(|mut file| file.write_all(password.as_bytes()))
+        .context("Failed to write certificate password file")?;
+    Ok(passfile)
+}
+
+pub fn sign_pdf(pdf: &Path, cert: &Path, password: Option<&str>) -> Result<()> {
+    let signed = sibling_path(pdf, "signed");
+    // Removed with the directory when signing is done
+    let workspace = TempDir::new("sign")?;
+    let passfile = write_passfile(&workspace.path, password.unwrap_or(""))?;
+
+    // pyHanko produces PAdES baseline signatures from a PKCS#12 bundle
+    let mut command = Command::new("pyhanko");
+    command
+        .args([
+            "sign",
+            "addsig",
+            "--field",
+            "Signature1",
+            "--use-pades",
+            "pkcs12",
+        ])
+        .arg("--passfile")
+        .arg(&passfile)
+        .arg(pdf)
+        .arg(&signed)
+        .arg(cert);
+
+    run_tool(command, "pyhanko")?;
+
+    fs::rename(&signed, pdf).with_context(|| format!("Failed to save signed PDF: {:?}", pdf))?;
+    Ok(())
+}
+
+/// Renders the first page of the PDF to a PNG or JPEG (by the thumbnail's
+/// extension), scaled to `width` pixels wide.
+pub fn thumbnail(pdf: &Path, thumbnail: &Path, width: u32) -> Result<()> {
+    let jpeg = thumbnail
+        .extension()
+        .map_or(false, |ext| ext == "jpg" || ext == "jpeg");
+
+    // pdftoppm appends the extension to the output prefix itself
+    let prefix = thumbnail.with_extension("");
+    let mut command = Command::new("pdftoppm");
+    command
+        .args(["-f", "1", "-l", "1", "-singlefile"])
+        .arg(if jpeg { "-jpeg" } else { "-png" })
+        .arg("-scale-to-x")
+        .arg(width.to_string())
+        .args(["-scale-to-y", "-1"])
+        .arg(pdf)
+        .arg(&prefix);
+    run_tool(command, "pdftoppm")?;
+
+    let rendered = prefix.with_extension(if jpeg { "jpg" } else { "png" });
+    if rendered != thumbnail {
+        fs::rename(&rendered, thumbnail)
+            .with_context(|| format!("Failed to save thumbnail: {:?}", thumbnail))?;
+    }
+    Ok(())
+}
+
+pub fn page_count(pdf: &Path) -> Result<usize> {
+    let mut command = Command::new("qpdf");
+    command.arg("--show-npages").arg(pdf);
+    let output = tool_output(command, "qpdf")?;
+    output
+        .trim()
+        .parse()
+        .with_context(|| format!("Unexpected qpdf page count: {:?}", output.trim()))
+}
+
+/// Concatenates `parts` into `output`.
+pub fn merge(parts: &[PathBuf], output: &Path) -> Result<()> {
+    let mut command = Command::new("qpdf");
+    command
+        .arg("--empty")
+        .arg("--pages")
+        .args(parts)
+        .arg("--")
+        .arg(output);
+    run_tool(command, "qpdf")
+}
+
+/// Stamps the pages of `overlay` on top of the same `pages` of `pdf`
+/// (a qpdf page range such as `1-z` or `2-z`).
+pub fn overlay(pdf: &Path, overlay: &Path, pages: &str) -> Result<()> {
+    let stamped = sibling_path(pdf, "stamped");
+
+    let mut command = Command::new("qpdf");
+    command
+        .arg(pdf)
+        .arg("--overlay")
+        .arg(overlay)
+        .arg(format!("--to={}", pages))
+        .arg(format!("--from={}", pages))
+        .arg("--")
+        .arg(&stamped);
+    run_tool(command, "qpdf")?;
+
+    fs::rename(&stamped, pdf).with_context(|| format!("Failed to save stamped PDF: {:?}", pdf))?;
+    Ok(())
+}
+
+/// pdfjam's `--nup` grid and sheet orientation for a pages-per-sheet count.
+fn nup_layout(pages_per_sheet: u32) -> Result<(&'static str, bool)> {
+    Ok(match pages_per_sheet {
+        2 => ("2x1", true),
+        4 => ("2x2", false),
+        8 => ("4x2", true),
+        9 => ("3x3", false),
+        16 => ("4x4", false),
+        other => anyhow::bail!("Unsupported --nup {}: use 2, 4, 8, 9 or 16", other),
+    })
+}
+
+/// Places `pages_per_sheet` pages side by side on each A4 sheet, in reading
+/// order, for compact handouts.
+pub fn nup_pdf(pdf: &Path, pages_per_sheet: u32) -> Result<()> {
+    let (grid, landscape) = nup_layout(pages_per_sheet)?;
+    let handout = sibling_path(pdf, "nup");
+
+    let mut command = Command::new("pdfjam");
+    command
+        .args(["--quiet", "--a4paper", "--nup", grid])
+        .arg(if landscape {
+            "--landscape"
+        } else {
+            "--no-landscape"
+        })
+        .arg("--outfile")
+        .arg(&handout)
+        .arg(pdf);
+    run_tool(command, "pdfjam")?;
+
+    fs::rename(&handout, pdf).with_context(|| format!("Failed to save handout PDF: {:?}", pdf))?;
+    Ok(())
+}
+
+/// Imposes the pages for saddle-stitch printing: two pages side by side on
+/// each landscape sheet, ordered so the printed sheets, folded and nested,
+/// read in sequence. Blank pages pad the count to a multiple of four. With a
+/// `signature` size, the document is split into that many pages per folded
+/// bundle (a multiple of four) for thicker books.
+pub fn booklet_pdf(pdf: &Path, signature: Option<u32>) -> Result<()> {
+    let booklet = sibling_path(pdf, "booklet");
+
+    let mut command = Command::new("pdfjam");
+    command.args(["--quiet", "--a4paper", "--landscape"]);
+    match signature {
+        Some(pages) if pages == 0 || pages % 4 != 0 => {
+            anyhow::bail!(
+                "Booklet signatures need a multiple of 4 pages, got {}",
+                pages
+            )
+        }
+        Some(pages) => command.arg("--signature").arg(pages.to_string()),
+        None => command.args(["--booklet", "true"]),
+    };
+    command.arg("--outfile").arg(&booklet).arg(pdf);
+    run_tool(command, "pdfjam")?;
+
+    fs::rename(&booklet, pdf).with_context(|| format!("Failed to save booklet PDF: {:?}", pdf))?;
+    Ok(())
+}
+
+/// Follows an indirect reference (`"12 0 R"`) in qpdf's JSON to its value.
+pub fn resolve<'a>(objects: &'a Value, value: &'a Value) -> &'a Value {
+    match value.as_str() {
+        Some(reference) if reference.ends_with(" R") => {
+            &objects[format!("obj:{}", reference)]["value"]
+        }
+        _ => value,
+    }
+}
+
+/// Decodes a PDF name (`/a#20b`) or string (`u:text`) key from qpdf's JSON.
+fn decode_name(key: &str) -> String {
+    if let Some(text) = key.strip_prefix("u:") {
+        return text.to_string();
+    }
+    let name = key.strip_prefix('/').unwrap_or(key);
+    let mut decoded = Vec::new();
+    let mut bytes = name.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte == b'#' {
+            let hex: String = bytes.by_ref().take(2).map(char::from).collect();
+            if let Ok(value) = u8::from_str_radix(&hex, 16) {
+                decoded.push(value);
+                continue;
+            }
+        }
+        decoded.push(byte);
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Collects `name -> page reference` from a `/Dests` dictionary or a
+/// `/Names` name tree.
+fn collect_destinations<'a>(
+    objects: &'a Value,
+    node: &'a Value,
+    found: &mut Vec<(String, &'a Value)>,
+) {
+    let node = resolve(objects, node);
+    if let Some(names) = node["/Names"].as_array() {
+        for pair in names.chunks(2) {
+            if let [name, destination] = pair {
+                found.push((decode_name(name.as_str().unwrap_or("")), destination));
+            }
+        }
+    }
+    if let Some(kids) = node["/Kids"].as_array() {
+        for kid in kids {
+            collect_destinations(objects, kid, found);
+        }
+    }
+    if node.get("/Names").is_none() && node.get("/Kids").is_none() {
+        if let Some(entries) = node.as_object() {
+            for (name, destination) in entries {
+                found.push((decode_name(name), destination));
+            }
+        }
+    }
+}
+
+/// The PDF's objects and page list as qpdf JSON (version 2).
+pub fn qpdf_json(pdf: &Path) -> Result<Value> {
+    let mut command = Command::new("qpdf");
+    command
+        .args(["--json=2", "--json-key=pages", "--json-key=qpdf"])
+        .arg(pdf);
+    let output = tool_output(command, "qpdf")?;
+    serde_json::from_str(&output).context("Unexpected qpdf JSON output")
+}
+
+/// Replaces or adds the objects in `update` (qpdf JSON in the shape
+/// [`qpdf_json`] returns) in the PDF.
+pub fn update_from_json(pdf: &Path, update: &Value) -> Result<()> {
+    let json_path = pdf.with_extension("update.json");
+    fs::write(&json_path, update.to_string())
+        .with_context(|| format!("Failed to write file: {:?}", json_path))?;
+    let updated = sibling_path(pdf, "updated");
+
+    let mut command = Command::new("qpdf");
+    command
+        .arg(pdf)
+        .arg(format!("--update-from-json={}", json_path.display()))
+        .arg(&updated);
+    let result = run_tool(command, "qpdf");
+    let _ = fs::remove_file(&json_path);
+    result?;
+
+    fs::rename(&updated, pdf).with_context(|| format!("Failed to save PDF: {:?}", pdf))?;
+    Ok(())
+}
+
+/// Where a named destination points.
+pub struct Destination {
+    /// 1-based page number
+    pub page: usize,
+    /// The page's object reference, e.g. `"12 0 R"`
+    pub page_object: String,
+    /// The `/XYZ` target point in PDF units from the page's bottom left
+    pub left: f64,
+    pub top: f64,
+}
+
+/// The named destinations in [`qpdf_json`] output, which Chrome writes for
+/// the targets of in-document links.
+pub fn destinations(json: &Value) -> HashMap<String, Destination> {
+    let pages: HashMap<&str, usize> = json["pages"]
+        .as_array()
+        .map(|pages| {
+            pages
+                .iter()
+                .enumerate()
+                .filter_map(|(index, page)| Some((page["object"].as_str()?, index + 1)))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let objects = &json["qpdf"][1];
+    let root = resolve(objects, &objects["trailer"]["value"]["/Root"]);
+    let mut found = Vec::new();
+    collect_destinations(objects, &root["/Dests"], &mut found);
+    collect_destinations(
+        objects,
+        &resolve(objects, &root["/Names"])["/Dests"],
+        &mut found,
+    );
+
+    found
+        .into_iter()
+        .filter_map(|(name, destination)| {
+            let destination = resolve(objects, destination);
+            // Either `[page /XYZ left top zoom]` or `<< /D [page ...] >>`
+            let array = destination
+                .as_array()
+                .or_else(|| resolve(objects, &destination["/D"]).as_array())?;
+            let page_object = array.first()?.as_str()?;
+            let coordinate = |index: usize| array.get(index).and_then(Value::as_f64).unwrap_or(0.0);
+            Some((
+                name,
+                Destination {
+                    page: *pages.get(page_object)?,
+                    page_object: page_object.to_string(),
+                    left: coordinate(2),
+                    top: coordinate(3),
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Page numbers (1-based) of the PDF's named destinations.
+pub fn named_destinations(pdf: &Path) -> Result<HashMap<String, usize>> {
+    Ok(destinations(&qpdf_json(pdf)?)
+        .into_iter()
+        .map(|(name, destination)| (name, destination.page))
+        .collect())
+}
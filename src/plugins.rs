@@ -0,0 +1,116 @@
+//! WASM markdown transform plugins.
+//!
+//! A plugin is a WASM module exporting `memory`, `alloc(len: u32) -> u32`
+//! and `transform(ptr: u32, len: u32) -> u64`. The host writes the UTF-8
+//! markdown of one file into memory obtained from `alloc`, and `transform`
+//! returns the transformed markdown packed as `(ptr << 32) | len`.
+
+use anyhow::Result;
+use std::path::PathBuf;
+
+#[cfg(feature = "plugins")]
+mod runtime {
+    use anyhow::{Context, Result};
+    use std::path::{Path, PathBuf};
+    use wasmtime::{Engine, Instance, Memory, Module, Store, TypedFunc};
+
+    pub struct Plugin {
+        path: PathBuf,
+        store: Store<()>,
+        memory: Memory,
+        alloc: TypedFunc<u32, u32>,
+        transform: TypedFunc<(u32, u32), u64>,
+    }
+
+    impl Plugin {
+        pub fn load(engine: &Engine, path: &Path) -> Result<Self> {
+            let module = Module::from_file(engine, path)
+                .with_context(|| format!("Failed to load plugin: {:?}", path))?;
+            let mut store = Store::new(engine, ());
+            let instance = Instance::new(&mut store, &module, &[])
+                .with_context(|| format!("Failed to instantiate plugin: {:?}", path))?;
+
+            let memory = instance
+                .get_memory(&mut store, "memory")
+                .with_context(|| format!("Plugin does not export memory: {:?}", path))?;
+            let alloc = instance
+                .get_typed_func::<u32, u32>(&mut store, "alloc")
+                .with_context(|| format!("Plugin does not export alloc: {:?}", path))?;
+            let transform = instance
+                .get_typed_func::<(u32, u32), u64>(&mut store, "transform")
+                .with_context(|| format!("Plugin does not export transform: {:?}", path))?;
+
+            Ok(Plugin {
+                path: path.to_path_buf(),
+                store,
+                memory,
+                alloc,
+                transform,
+            })
+        }
+
+        pub fn transform(&mut self, markdown: &str) -> Result<String> {
+            let input = markdown.as_bytes();
+            let len = u32::try_from(input.len()).context("Document too large for plugin")?;
+
+            let ptr = self.alloc.call(&mut self.store, len)?;
+            self.memory.write(&mut self.store, ptr as usize, input)?;
+
+            let packed = self.transform.call(&mut self.store, (ptr, len))?;
+            let (out_ptr, out_len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+            let mut output = vec![0u8; out_len];
+            self.memory.read(&self.store, out_ptr, &mut output)?;
+
+            String::from_utf8(output)
+                .with_context(|| format!("Plugin returned invalid UTF-8: {:?}", self.path))
+        }
+    }
+
+    pub fn load_all(paths: &[PathBuf]) -> Result<Vec<Plugin>> {
+        let engine = Engine::default();
+        paths
+            .iter()
+            .map(|path| Plugin::load(&engine, path))
+            .collect()
+    }
+}
+
+#[derive(Default)]
+pub struct Plugins {
+    #[cfg(feature = "plugins")]
+    loaded: Vec<runtime::Plugin>,
+}
+
+impl Plugins {
+    pub fn load(paths: &[PathBuf]) -> Result<Self> {
+        #[cfg(feature = "plugins")]
+        {
+            Ok(Plugins {
+                loaded: runtime::load_all(paths)?,
+            })
+        }
+
+        #[cfg(not(feature = "plugins"))]
+        {
+            if !paths.is_empty() {
+                anyhow::bail!("This build has no plugin support. Rebuild with --features plugins");
+            }
+            Ok(Plugins::default())
+        }
+    }
+
+    /// Runs every plugin over the markdown of one file, in the order given.
+    pub fn transform(&mut self, markdown: String) -> Result<String> {
+        #[cfg(feature = "plugins")]
+        {
+            let mut markdown = markdown;
+            for plugin in &mut self.loaded {
+                markdown = plugin.transform(&markdown)?;
+            }
+            Ok(markdown)
+        }
+
+        #[cfg(not(feature = "plugins"))]
+        Ok(markdown)
+    }
+}
@@ -0,0 +1,82 @@
+use crate::template::escape_html;
+use anyhow::{Context, Result};
+use base64::Engine;
+use qrcode::render::svg;
+use qrcode::QrCode;
+
+pub fn qr_image(url: &str) -> Result<String> {
+    let code = QrCode::new(url.as_bytes())
+        .with_context(|| format!("Failed to encode QR code: {}", url))?;
+    let svg = code
+        .render::<svg::Color>()
+        .quiet_zone(true)
+        .min_dimensions(200, 200)
+        .build();
+    Ok(format!(
+        "<img class=\"qr\" alt=\"QR code: {}\" src=\"data:image/svg+xml;base64,{}\">",
+        escape_html(url),
+        base64::engine::general_purpose::STANDARD.encode(svg)
+    ))
+}
+
+/// The URL of a `[text](url)` link ending right before `end`, for `{.qr}`.
+fn link_url_before(line: &str, end: usize) -> Option<&str> {
+    let before = line[..end].strip_suffix(')')?;
+    let start = before.rfind("](")? + 2;
+    Some(before[start..].split_whitespace().next().unwrap_or(""))
+}
+
+fn expand_line(line: &str) -> Result<String> {
+    let mut result = String::new();
+    let mut rest = line;
+
+    loop {
+        let directive = rest.find("{{qr:");
+        let tag = rest.find("{.qr}");
+        match (directive, tag) {
+            (Some(start), _) if tag.map_or(true, |tag| start < tag) => {
+                let Some(end) = rest[start..].find("}}") else {
+                    break;
+                };
+                let url = rest[start + 5..start + end].trim();
+                result.push_str(&rest[..start]);
+                result.push_str(&qr_image(url)?);
+                rest = &rest[start + end + 2..];
+            }
+            (_, Some(start)) => {
+                // `[text](url){.qr}` keeps the link and adds its QR code after it
+                result.push_str(&rest[..start]);
+                if let Some(url) = link_url_before(rest, start).filter(|url| !url.is_empty()) {
+                    result.push(' ');
+                    result.push_str(&qr_image(url)?);
+                }
+                rest = &rest[start + 5..];
+            }
+            _ => break,
+        }
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Replaces `{{qr: https://example.com}}` with a QR code image and adds one
+/// after every `[text](url){.qr}` link, so printed copies can be scanned.
+pub fn expand_qr_codes(markdown: &str) -> Result<String> {
+    let mut result = String::new();
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        if line.trim().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block || !(line.contains("{{qr:") || line.contains("{.qr}")) {
+            result.push_str(line);
+        } else {
+            result.push_str(&expand_line(line)?);
+        }
+        result.push('\n');
+    }
+
+    Ok(result)
+}
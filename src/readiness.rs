@@ -0,0 +1,74 @@
+use crate::exit_codes::Failure;
+use anyhow::{Context, Result};
+use headless_chrome::Tab;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to wait for a selector or for the network to settle.
+const READY_TIMEOUT: Duration = Duration::from_secs(30);
+/// The network counts as idle once no new resources load for this long.
+const IDLE_WINDOW: Duration = Duration::from_millis(500);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// When the page counts as fully rendered, for documents whose math,
+/// diagrams, or web fonts are produced by scripts after load.
+#[derive(Debug, Default)]
+pub struct Readiness {
+    pub selector: Option<String>,
+    pub network_idle: bool,
+    pub delay: Duration,
+}
+
+fn resource_count(tab: &Tab) -> Result<u64> {
+    let result = tab.evaluate(
+        "document.readyState === 'complete' ? performance.getEntriesByType('resource').length : -1",
+        false,
+    )?;
+    // -1 (still loading) doesn't fit in a u64 and counts as busy
+    Ok(result
+        .value
+        .and_then(|value| value.as_u64())
+        .unwrap_or(u64::MAX))
+}
+
+fn wait_network_idle(tab: &Tab) -> Result<()> {
+    let start = Instant::now();
+    let mut last_count = u64::MAX;
+    let mut last_change = Instant::now();
+
+    while start.elapsed() < READY_TIMEOUT {
+        let count = resource_count(tab)?;
+        if count != last_count {
+            last_count = count;
+            last_change = Instant::now();
+        } else if count != u64::MAX && last_change.elapsed() >= IDLE_WINDOW {
+            // Web fonts may still be decoding after their request finished
+            tab.evaluate("document.fonts.ready.then(() => true)", true)?;
+            return Ok(());
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    Err(Failure::RenderTimeout).context("Network did not become idle")
+}
+
+/// Blocks until the page is ready to print.
+pub fn wait(tab: &Tab, readiness: &Readiness) -> Result<()> {
+    if let Some(selector) = &readiness.selector {
+        println!("Waiting for selector: {}", selector);
+        tab.wait_for_element_with_custom_timeout(selector, READY_TIMEOUT)
+            .context(Failure::RenderTimeout)
+            .with_context(|| format!("Selector never appeared: {}", selector))?;
+    }
+
+    if readiness.network_idle {
+        println!("Waiting for the network to become idle...");
+        wait_network_idle(tab)?;
+    }
+
+    if !readiness.delay.is_zero() {
+        thread::sleep(readiness.delay);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use crate::template::escape_html;
+
+/// Marker that opens and closes an inline redaction.
+const MARKER: &str = "::redact::";
+
+/// A black box as wide as the text it replaces. The text itself is left
+/// out, so it can't be selected or extracted from the PDF.
+fn redacted_span(text: &str, show: bool) -> String {
+    if show {
+        return format!(
+            "<span class=\"redacted-visible\">{}</span>",
+            escape_html(text)
+        );
+    }
+    format!(
+        "<span class=\"redacted\" style=\"width: {}ch\"></span>",
+        text.chars().count().max(1)
+    )
+}
+
+fn redact_line(line: &str, show: bool) -> String {
+    let mut result = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find(MARKER) {
+        let inner = &rest[start + MARKER.len()..];
+        let Some(end) = inner.find(MARKER) else {
+            break;
+        };
+        result.push_str(&rest[..start]);
+        result.push_str(&redacted_span(&inner[..end], show));
+        rest = &inner[end + MARKER.len()..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Replaces text between `::redact::` markers, and whole `::: redact`
+/// blocks, with solid black boxes. With `show` the content stays readable
+/// and is only highlighted, for reviewing what will be removed.
+pub fn apply_redactions(markdown: &str, show: bool) -> String {
+    let mut result = String::new();
+    let mut in_code_block = false;
+    let mut block_lines: Option<Vec<&str>> = None;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+
+        if let Some(lines) = block_lines.as_mut() {
+            if trimmed == ":::" {
+                let lines = block_lines.take().unwrap_or_default();
+                if show {
+                    result.push_str("\n<div class=\"redacted-visible\">\n\n");
+                    result.push_str(&lines.join("\n"));
+                    result.push_str("\n\n</div>\n\n");
+                } else {
+                    result.push_str(&format!(
+                        "\n<div class=\"redacted-block\" style=\"height: {}em\"></div>\n\n",
+                        lines.len().max(1) as f32 * 1.6
+                    ));
+                }
+            } else {
+                lines.push(line);
+            }
+            continue;
+        }
+
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+        if !in_code_block && trimmed.strip_prefix(":::").map(str::trim) == Some("redact") {
+            block_lines = Some(Vec::new());
+            continue;
+        }
+
+        if in_code_block || !line.contains(MARKER) {
+            result.push_str(line);
+        } else {
+            result.push_str(&redact_line(line, show));
+        }
+        result.push('\n');
+    }
+
+    // An unclosed block is redacted to the end of the document
+    if let Some(lines) = block_lines {
+        if show {
+            result.push_str(&lines.join("\n"));
+        } else {
+            result.push_str("\n<div class=\"redacted-block\"></div>\n");
+        }
+    }
+
+    result
+}
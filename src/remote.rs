@@ -0,0 +1,26 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// Returns the input as a URL when it names an http(s) resource.
+pub fn as_url(input: &Path) -> Option<&str> {
+    input
+        .to_str()
+        .filter(|s| s.starts_with("http://") || s.starts_with("https://"))
+}
+
+/// The directory part of a URL, used as `<base href>` so relative images
+/// and links in a fetched document resolve against where it came from.
+pub fn base_url(url: &str) -> String {
+    match url.rfind('/') {
+        Some(index) => url[..=index].to_string(),
+        None => format!("{}/", url),
+    }
+}
+
+pub fn fetch(url: &str) -> Result<String> {
+    ureq::get(url)
+        .call()
+        .with_context(|| format!("Failed to fetch: {}", url))?
+        .into_string()
+        .with_context(|| format!("Failed to read response from: {}", url))
+}
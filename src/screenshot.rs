@@ -0,0 +1,88 @@
+use crate::output::OutputFormat;
+use anyhow::{Context, Result};
+use headless_chrome::protocol::cdp::Page::{CaptureScreenshotFormatOption, Viewport};
+use headless_chrome::Tab;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A4 height relative to its width, used to cut the page into images.
+const A4_ASPECT: f64 = 297.0 / 210.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ImageOptions {
+    pub format: OutputFormat,
+    /// Viewport width in CSS pixels
+    pub width: u32,
+    pub full_page: bool,
+}
+
+fn document_height(tab: &Tab) -> Result<f64> {
+    let result = tab
+        .evaluate("document.documentElement.scrollHeight", false)
+        .context("Failed to measure document height")?;
+    Ok(result
+        .value
+        .and_then(|value| value.as_f64())
+        .unwrap_or_default())
+}
+
+/// `out.png` for a single image, `out-1.png`, `out-2.png`, ... for pages.
+fn numbered_path(output: &Path, page: usize) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = output.extension().unwrap_or_default().to_string_lossy();
+    output.with_file_name(format!("{}-{}.{}", stem, page, extension))
+}
+
+/// Captures the loaded document as images. Without `full_page` the document
+/// is cut into A4-proportioned pages at the viewport width. Returns the
+/// written image paths.
+pub fn capture(tab: &Tab, output: &Path, options: &ImageOptions) -> Result<Vec<PathBuf>> {
+    let format = match options.format {
+        OutputFormat::Jpeg => CaptureScreenshotFormatOption::Jpeg,
+        _ => CaptureScreenshotFormatOption::Png,
+    };
+    let width = f64::from(options.width);
+    let height = document_height(tab)?.max(1.0);
+
+    let clips: Vec<Viewport> = if options.full_page {
+        vec![Viewport {
+            x: 0.0,
+            y: 0.0,
+            width,
+            height,
+            scale: 1.0,
+        }]
+    } else {
+        let page_height = width * A4_ASPECT;
+        let pages = (height / page_height).ceil().max(1.0) as usize;
+        (0..pages)
+            .map(|page| {
+                let y = page as f64 * page_height;
+                Viewport {
+                    x: 0.0,
+                    y,
+                    width,
+                    height: page_height.min(height - y),
+                    scale: 1.0,
+                }
+            })
+            .collect()
+    };
+
+    let single = clips.len() == 1;
+    let mut written = Vec::new();
+    for (index, clip) in clips.into_iter().enumerate() {
+        let image = tab
+            .capture_screenshot(format.clone(), Some(90), Some(clip), true)
+            .context("Failed to capture screenshot")?;
+        let path = if single {
+            output.to_path_buf()
+        } else {
+            numbered_path(output, index + 1)
+        };
+        fs::write(&path, image).with_context(|| format!("Failed to save image: {:?}", path))?;
+        written.push(path);
+    }
+
+    Ok(written)
+}
@@ -0,0 +1,64 @@
+use crate::config::Sections;
+use crate::FileGroups;
+
+/// Matches `path` against a glob where `*` and `?` stay within one path
+/// segment and `**` spans any number of segments.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    fn segments_match(pattern: &[&str], path: &[&str]) -> bool {
+        match pattern.split_first() {
+            None => path.is_empty(),
+            Some((&"**", rest)) => (0..=path.len()).any(|skip| segments_match(rest, &path[skip..])),
+            Some((segment, rest)) => path.split_first().map_or(false, |(name, path_rest)| {
+                segment_match(segment.as_bytes(), name.as_bytes())
+                    && segments_match(rest, path_rest)
+            }),
+        }
+    }
+
+    fn segment_match(pattern: &[u8], name: &[u8]) -> bool {
+        match pattern.split_first() {
+            None => name.is_empty(),
+            Some((b'*', rest)) => (0..=name.len()).any(|skip| segment_match(rest, &name[skip..])),
+            Some((b'?', rest)) => !name.is_empty() && segment_match(rest, &name[1..]),
+            Some((c, rest)) => name.first() == Some(c) && segment_match(rest, &name[1..]),
+        }
+    }
+
+    let pattern: Vec<&str> = pattern.trim_matches('/').split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    segments_match(&pattern, &path)
+}
+
+/// Reorders files by the first `[sections] order` pattern their relative
+/// path matches, keeping the existing order among files matching the same
+/// pattern. Consecutive files from one directory are grouped again, so a
+/// directory split up by the order shows up more than once.
+pub fn apply_order(groups: &mut FileGroups, sections: &Sections) {
+    if sections.order.is_empty() {
+        return;
+    }
+
+    let mut files: Vec<(usize, String, crate::MarkdownFile)> = Vec::new();
+    for (dir_key, group) in std::mem::take(groups) {
+        for file in group {
+            let path = file.relative_path.to_string_lossy().replace('\\', "/");
+            let rank = sections
+                .order
+                .iter()
+                .position(|pattern| glob_match(pattern, &path));
+            match rank {
+                Some(rank) => files.push((rank, dir_key.clone(), file)),
+                None if sections.drop_unlisted => {}
+                None => files.push((usize::MAX, dir_key.clone(), file)),
+            }
+        }
+    }
+    files.sort_by_key(|(rank, _, _)| *rank);
+
+    for (_, dir_key, file) in files {
+        match groups.last_mut() {
+            Some((last_key, group)) if *last_key == dir_key => group.push(file),
+            _ => groups.push((dir_key, vec![file])),
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use crate::images;
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Path the rendered document is served under.
+const DOCUMENT_PATH: &str = "/__md2pdf__.html";
+
+/// Origins (`http://127.0.0.1:<port>/`) of the servers this process started.
+static ORIGINS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Whether `url` points at one of the asset servers started by this process,
+/// rather than at anything else listening on localhost.
+pub fn serves(url: &str) -> bool {
+    ORIGINS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .iter()
+        .any(|origin| url.starts_with(origin.as_str()))
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("html") | Some("htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") | Some("mjs") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("ttf") => "font/ttf",
+        Some("otf") => "font/otf",
+        _ => images::mime_type(path),
+    }
+}
+
+/// Maps a request path onto the served directory, refusing to leave it.
+fn resolve(root: &Path, request_path: &str) -> Option<PathBuf> {
+    let path = request_path.split(['?', '#']).next().unwrap_or("");
+    let relative = PathBuf::from(images::percent_decode(path.trim_start_matches('/')));
+    if relative
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+    Some(root.join(relative))
+}
+
+fn respond(mut stream: TcpStream, status: &str, content_type: &str, body: &[u8]) {
+    let header = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    // The browser may have gone away already; nothing to report then
+    let _ = stream
+        .write_all(header.as_bytes())
+        .and_then(|_| stream.write_all(body));
+}
+
+fn handle(stream: TcpStream, root: &Path, document: &str) {
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    // Headers are read and ignored
+    let mut header = String::new();
+    while reader.read_line(&mut header).map_or(false, |n| n > 2) {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (Some("GET"), Some(path)) = (parts.next(), parts.next()) else {
+        respond(stream, "405 Method Not Allowed", "text/plain", b"");
+        return;
+    };
+
+    if path == DOCUMENT_PATH {
+        respond(
+            stream,
+            "200 OK",
+            "text/html; charset=utf-8",
+            document.as_bytes(),
+        );
+        return;
+    }
+
+    match resolve(root, path).and_then(|file| fs::read(&file).ok().map(|body| (file, body))) {
+        Some((file, body)) => respond(stream, "200 OK", content_type(&file), &body),
+        None => respond(stream, "404 Not Found", "text/plain", b"Not found"),
+    }
+}
+
+/// A localhost HTTP server for the rendered document and the files next to
+/// it, so relative stylesheets, images, and fonts load like on a website.
+/// It runs on background threads until the process exits.
+pub struct AssetServer {
+    pub document_url: String,
+}
+
+impl AssetServer {
+    pub fn start(root: &Path, document: String) -> Result<Self> {
+        let listener =
+            TcpListener::bind("127.0.0.1:0").context("Failed to start local asset server")?;
+        let address = listener.local_addr()?;
+        ORIGINS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(format!("http://{}/", address));
+        let root = Arc::new(root.to_path_buf());
+        let document = Arc::new(document);
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let root = Arc::clone(&root);
+                let document = Arc::clone(&document);
+                thread::spawn(move || handle(stream, &root, &document));
+            }
+        });
+
+        Ok(AssetServer {
+            document_url: format!("http://{}{}", address, DOCUMENT_PATH),
+        })
+    }
+}
@@ -0,0 +1,78 @@
+use crate::strings;
+use crate::template::escape_html;
+
+const DIRECTIVE: &str = "{{signature:";
+
+/// A signature block: a rule to sign on with the signer's name and title
+/// below it, and a shorter rule for the date.
+fn signature_block(arguments: &str) -> String {
+    let strings = strings::get();
+    let mut parts = arguments.splitn(2, ',').map(str::trim);
+    let name = parts.next().unwrap_or("");
+    let title = parts.next().unwrap_or("");
+
+    let mut html =
+        String::from("<span class=\"signature-block\"><span class=\"signature-line\"></span>");
+    html.push_str(&format!(
+        "<span class=\"signature-label\">{}</span>",
+        escape_html(&strings.signature)
+    ));
+    if !name.is_empty() {
+        html.push_str(&format!(
+            "<span class=\"signature-name\">{}</span>",
+            escape_html(name)
+        ));
+    }
+    if !title.is_empty() {
+        html.push_str(&format!(
+            "<span class=\"signature-title\">{}</span>",
+            escape_html(title)
+        ));
+    }
+    html.push_str(&format!(
+        "<span class=\"signature-line signature-date\"></span><span class=\"signature-label\">{}</span></span>",
+        escape_html(&strings.date)
+    ));
+    html
+}
+
+fn expand_line(line: &str) -> String {
+    let mut result = String::new();
+    let mut rest = line;
+
+    while let Some(start) = rest.find(DIRECTIVE) {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        result.push_str(&rest[..start]);
+        result.push_str(&signature_block(
+            &rest[start + DIRECTIVE.len()..start + end],
+        ));
+        rest = &rest[start + end + 2..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Replaces `{{signature: Name, Title}}` with a signature block (rule,
+/// name, title, and a date rule). Blocks on the same line sit side by side,
+/// for agreements signed by several parties.
+pub fn expand_signatures(markdown: &str) -> String {
+    let mut result = String::new();
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        if line.trim().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block || !line.contains(DIRECTIVE) {
+            result.push_str(line);
+        } else {
+            result.push_str(&expand_line(line));
+        }
+        result.push('\n');
+    }
+
+    result
+}
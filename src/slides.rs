@@ -0,0 +1,74 @@
+enum Boundary {
+    Rule,
+    Heading,
+}
+
+/// Whether a line starts a new slide: a `---` rule after a blank line (so
+/// setext headings are left alone) or an H2 heading.
+fn boundary(line: &str, previous_blank: bool) -> Option<Boundary> {
+    let trimmed = line.trim();
+    if previous_blank && trimmed.len() >= 3 && trimmed.chars().all(|c| c == '-') {
+        Some(Boundary::Rule)
+    } else if line.starts_with("## ") {
+        Some(Boundary::Heading)
+    } else {
+        None
+    }
+}
+
+fn push_slide(result: &mut String, slide: &[&str]) {
+    if slide.iter().all(|line| line.trim().is_empty()) {
+        return;
+    }
+    result.push_str("<section class=\"slide\">\n\n");
+    result.push_str(&slide.join("\n"));
+    result.push_str("\n\n</section>\n\n");
+}
+
+/// Splits a talk outline into reveal.js-style `<section class="slide">`
+/// blocks, one per printed page: a new slide starts at every `---` rule
+/// (which is dropped) and at every `## ` heading.
+pub fn split_slides(markdown: &str) -> String {
+    let mut result = String::new();
+    let mut slide: Vec<&str> = Vec::new();
+    let mut in_code_block = false;
+    let mut previous_blank = true;
+
+    for line in markdown.lines() {
+        if line.trim().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+        if !in_code_block {
+            match boundary(line, previous_blank) {
+                Some(Boundary::Rule) => {
+                    push_slide(&mut result, &slide);
+                    slide.clear();
+                    previous_blank = true;
+                    continue;
+                }
+                Some(Boundary::Heading) => {
+                    push_slide(&mut result, &slide);
+                    slide.clear();
+                }
+                None => {}
+            }
+        }
+        previous_blank = line.trim().is_empty();
+        slide.push(line);
+    }
+    push_slide(&mut result, &slide);
+
+    result
+}
+
+pub const SLIDES_CSS: &str = r#"
+        body { font-size: 20pt; }
+        .slide {
+            break-after: page;
+            min-height: 16cm;
+            display: flex;
+            flex-direction: column;
+            justify-content: center;
+        }
+        .slide:last-of-type { break-after: auto; }
+        .slide h1, .slide h2 { border-bottom: none; margin-top: 0; }"#;
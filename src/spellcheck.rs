@@ -0,0 +1,100 @@
+use crate::warnings;
+use anyhow::{Context, Result};
+use pulldown_cmark::{Event, Parser, Tag};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+#[derive(Debug, Clone)]
+pub struct Spellcheck {
+    /// Hunspell dictionary name, e.g. `en_US`
+    pub language: String,
+    /// Extra accepted words, one per line
+    pub dictionary: Option<PathBuf>,
+}
+
+/// The prose of a markdown document: text outside code, links, images and
+/// raw HTML, one paragraph per line.
+fn prose(markdown: &str) -> String {
+    let mut text = String::new();
+    let mut skip_depth = 0usize;
+
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Start(Tag::CodeBlock(_) | Tag::Link(..) | Tag::Image(..)) => skip_depth += 1,
+            Event::End(Tag::CodeBlock(_) | Tag::Link(..) | Tag::Image(..)) => {
+                skip_depth = skip_depth.saturating_sub(1)
+            }
+            Event::Text(t) if skip_depth == 0 => {
+                text.push_str(&t);
+                text.push(' ');
+            }
+            Event::SoftBreak | Event::HardBreak => text.push(' '),
+            Event::End(_) => text.push('\n'),
+            _ => {}
+        }
+    }
+
+    text
+}
+
+/// Runs `hunspell -l` over the document's prose and reports unknown words
+/// as warnings, so `--strict` can stop the build on typos.
+pub fn check(markdown: &str, source: &str, options: &Spellcheck) -> Result<()> {
+    let mut command = Command::new("hunspell");
+    command.arg("-l").arg("-d").arg(&options.language);
+    if let Some(dictionary) = &options.dictionary {
+        command.arg("-p").arg(dictionary);
+    }
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run hunspell. Make sure it is installed.")?;
+
+    // Written from a thread so hunspell can't block on a full stdout pipe
+    let text = prose(markdown);
+    let writer = child
+        .stdin
+        .take()
+        .map(|mut stdin| std::thread::spawn(move || stdin.write_all(text.as_bytes())));
+    let output = child.wait_with_output().context("Failed to run hunspell")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "hunspell failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    if let Some(writer) = writer {
+        writer
+            .join()
+            .map_err(|_| anyhow::anyhow!("Failed to send text to hunspell"))?
+            .context("Failed to send text to hunspell")?;
+    }
+
+    let mut misspelled: BTreeMap<String, usize> = BTreeMap::new();
+    for word in String::from_utf8_lossy(&output.stdout).lines() {
+        let word = word.trim();
+        if !word.is_empty() {
+            *misspelled.entry(word.to_string()).or_default() += 1;
+        }
+    }
+    if !misspelled.is_empty() {
+        let words: Vec<String> = misspelled
+            .into_iter()
+            .map(|(word, count)| match count {
+                1 => word,
+                _ => format!("{} ({}×)", word, count),
+            })
+            .collect();
+        warnings::warn(format!(
+            "{}: possible misspellings: {}",
+            source,
+            words.join(", ")
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,287 @@
+use crate::FileGroups;
+use crate::{directives, frontmatter};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SITE_CONFIGS: [&str; 4] = ["hugo.toml", "hugo.yaml", "_config.yml", "_config.yaml"];
+
+/// Whether the input is (or sits in) a Hugo or Jekyll site. A generic
+/// `config.toml` only counts next to a Hugo `content` directory.
+pub fn detect(root: &Path) -> bool {
+    let site_config = [Some(root), root.parent()]
+        .into_iter()
+        .flatten()
+        .any(|dir| SITE_CONFIGS.iter().any(|config| dir.join(config).is_file()));
+    let hugo_content = root.file_name().map_or(false, |name| name == "content")
+        && root.parent().map_or(false, |site| {
+            site.join("config.toml").is_file() || site.join("config.yaml").is_file()
+        });
+    site_config || hugo_content
+}
+
+/// Finds `key="value"` (or `key=value`) in shortcode arguments.
+fn attribute(args: &str, key: &str) -> Option<String> {
+    let start = args.find(&format!("{}=", key))? + key.len() + 1;
+    let rest = &args[start..];
+    match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next().map(str::to_string),
+        None => rest.split_whitespace().next().map(str::to_string),
+    }
+}
+
+/// The first quoted positional argument, e.g. the path in `ref "page.md"`.
+fn positional(args: &str) -> Option<String> {
+    args.split('"').nth(1).map(str::to_string)
+}
+
+/// Names of shortcodes that have a closing `{{< /name >}}` somewhere.
+fn paired_shortcodes(markdown: &str) -> HashSet<String> {
+    let mut names = HashSet::new();
+    for opener in ["{{<", "{{%"] {
+        for part in markdown.split(opener).skip(1) {
+            if let Some(name) = part.trim_start().strip_prefix('/') {
+                if let Some(name) = name.split_whitespace().next() {
+                    names.insert(name.to_string());
+                }
+            }
+        }
+    }
+    names
+}
+
+fn expand_shortcode(inner: &str, paired: &HashSet<String>) -> String {
+    let inner = inner.trim();
+    if inner.starts_with('/') {
+        return "\n:::\n".to_string();
+    }
+
+    let (name, args) = inner.split_once(char::is_whitespace).unwrap_or((inner, ""));
+    match name {
+        _ if paired.contains(name) => format!("\n::: {}\n", name),
+        "ref" | "relref" => positional(args)
+            .or_else(|| attribute(args, "path"))
+            .unwrap_or_default(),
+        "figure" => match attribute(args, "src") {
+            Some(src) => {
+                let caption = attribute(args, "caption")
+                    .or_else(|| attribute(args, "alt"))
+                    .unwrap_or_default();
+                format!("![{}]({})", caption, src)
+            }
+            None => String::new(),
+        },
+        // Embeds (youtube, tweet, gist, ...) have no print equivalent
+        _ => String::new(),
+    }
+}
+
+/// Replaces every `open ... close` span in a line using `expand`.
+fn replace_spans(
+    line: &str,
+    open: &str,
+    close: &str,
+    mut expand: impl FnMut(&str) -> String,
+) -> String {
+    let mut result = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find(open) {
+        let Some(end) = rest[start + open.len()..].find(close) else {
+            break;
+        };
+        let end = start + open.len() + end;
+        result.push_str(&rest[..start]);
+        result.push_str(&expand(&rest[start + open.len()..end]));
+        rest = &rest[end + close.len()..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Top-level names of Jekyll's Liquid objects, as in `{{ page.title }}`.
+const LIQUID_OBJECTS: [&str; 7] = [
+    "content",
+    "include",
+    "jekyll",
+    "layout",
+    "page",
+    "paginator",
+    "site",
+];
+
+/// Whether `{{ inner }}` is a Hugo template action (`{{ .Title }}`,
+/// `{{ $x }}`) or a Liquid output (`{{ page.title | upcase }}`) rather than
+/// md2pdf's own `{{qr: ...}}` and `{{signature: ...}}` or other text.
+fn is_template_output(inner: &str) -> bool {
+    let inner = inner.trim_matches('-').trim();
+    if inner.starts_with(['.', '$']) {
+        return true;
+    }
+    let name = inner
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .next()
+        .unwrap_or("");
+    LIQUID_OBJECTS.contains(&name)
+}
+
+/// Applies `map` to the parts of a line outside inline code spans.
+fn map_outside_code(line: &str, mut map: impl FnMut(&str) -> String) -> String {
+    let mut result = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find('`') {
+        let ticks = rest[start..].len() - rest[start..].trim_start_matches('`').len();
+        let Some(end) = directives::closing_ticks(&rest[start + ticks..], ticks) else {
+            break;
+        };
+        let end = start + ticks + end;
+        result.push_str(&map(&rest[..start]));
+        result.push_str(&rest[start..end]);
+        rest = &rest[end..];
+    }
+    result.push_str(&map(rest));
+    result
+}
+
+/// Expands Hugo shortcodes and Jekyll Liquid tags into plain markdown.
+/// Paired shortcodes like `{{< note >}}` become `::: note` containers,
+/// `ref`/`relref` become the referenced path, `figure` becomes an image,
+/// `{% highlight lang %}` becomes a code fence, and other shortcodes, Liquid
+/// tags and template outputs are dropped. Code spans are left alone.
+pub fn expand_shortcodes(markdown: &str) -> String {
+    let paired = paired_shortcodes(markdown);
+    let mut result = String::new();
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+
+        if let Some(tag) = trimmed
+            .strip_prefix("{%")
+            .and_then(|t| t.strip_suffix("%}"))
+            .map(|t| t.trim_matches('-').trim())
+        {
+            if let Some(language) = tag.strip_prefix("highlight") {
+                let language = language.split_whitespace().next().unwrap_or("");
+                result.push_str(&format!("```{}\n", language));
+                in_code_block = true;
+                continue;
+            }
+            if tag == "endhighlight" {
+                result.push_str("```\n");
+                in_code_block = false;
+                continue;
+            }
+        }
+
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block {
+            result.push_str(line);
+            result.push('\n');
+            continue;
+        }
+
+        let line = map_outside_code(line, |text| {
+            let text = replace_spans(text, "{{<", ">}}", |inner| expand_shortcode(inner, &paired));
+            let text = replace_spans(&text, "{{%", "%}}", |inner| {
+                expand_shortcode(inner, &paired)
+            });
+            let text = replace_spans(&text, "{%", "%}", |_| String::new());
+            replace_spans(&text, "{{", "}}", |inner| {
+                if is_template_output(inner) {
+                    String::new()
+                } else {
+                    format!("{{{{{}}}}}", inner)
+                }
+            })
+        });
+        result.push_str(&line);
+        result.push('\n');
+    }
+
+    result
+}
+
+fn weight(frontmatter: &frontmatter::Frontmatter) -> i64 {
+    frontmatter
+        .get("weight")
+        .and_then(|w| w.parse().ok())
+        .unwrap_or(i64::MAX)
+}
+
+/// The weight and title a Hugo section declares in its `_index.md`.
+fn section_metadata(dir: &Path) -> (i64, Option<String>) {
+    let Ok(content) = fs::read_to_string(dir.join("_index.md")) else {
+        return (i64::MAX, None);
+    };
+    let (frontmatter, _) = frontmatter::split(&content);
+    (
+        weight(&frontmatter),
+        frontmatter.get("title").map(str::to_string),
+    )
+}
+
+/// Orders sections and pages by `weight` frontmatter and names them by
+/// `title`. A section's `_index.md` supplies the section's weight and title;
+/// its body (if any) opens the section as "Overview" instead of repeating
+/// the section heading.
+pub fn apply_weights(root: &Path, groups: &mut FileGroups) {
+    let mut keyed: Vec<(Vec<(i64, String)>, (String, Vec<crate::MarkdownFile>))> = Vec::new();
+
+    for (dir_key, mut files) in std::mem::take(groups) {
+        files.retain(|file| file.name != "_index" || !file.content.trim().is_empty());
+        files.sort_by(|a, b| {
+            (a.name != "_index")
+                .cmp(&(b.name != "_index"))
+                .then_with(|| weight(&a.frontmatter).cmp(&weight(&b.frontmatter)))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        for file in &mut files {
+            if file.name == "_index" {
+                file.name = "Overview".to_string();
+            } else if let Some(title) = file.frontmatter.get("title") {
+                file.name = title.to_string();
+            }
+        }
+        if files.is_empty() {
+            continue;
+        }
+
+        let relative_dir = files
+            .first()
+            .and_then(|f| f.relative_path.parent())
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        let mut sort_key = Vec::new();
+        let mut labels = Vec::new();
+        let mut dir = PathBuf::from(root);
+        for component in relative_dir.iter() {
+            dir.push(component);
+            let name = component.to_string_lossy().into_owned();
+            let (weight, title) = section_metadata(&dir);
+            labels.push(title.unwrap_or_else(|| name.clone()));
+            sort_key.push((weight, name));
+        }
+
+        let dir_key = if labels.is_empty() {
+            dir_key
+        } else {
+            labels.join(" > ")
+        };
+        keyed.push((sort_key, (dir_key, files)));
+    }
+
+    keyed.sort_by(|(a, _), (b, _)| {
+        for ((wa, na), (wb, nb)) in a.iter().zip(b) {
+            let order = wa.cmp(wb).then_with(|| na.cmp(nb));
+            if order.is_ne() {
+                return order;
+            }
+        }
+        a.len().cmp(&b.len())
+    });
+
+    *groups = keyed.into_iter().map(|(_, group)| group).collect();
+}
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Text the tool itself puts into documents: generated headings, caption
+/// labels, and table headers.
+#[derive(Debug, Clone)]
+pub struct Strings {
+    pub documentation: String,
+    pub figure: String,
+    pub table: String,
+    pub list_of_figures: String,
+    pub list_of_tables: String,
+    pub appendix: String,
+    pub links: String,
+    pub notes: String,
+    pub revision_history: String,
+    pub open_items: String,
+    pub signature: String,
+    pub date: String,
+    /// Column headings of the task summary, open items, and revision history tables
+    pub section: String,
+    pub done: String,
+    pub total: String,
+    pub progress: String,
+    pub location: String,
+    pub item: String,
+    pub commit: String,
+    pub author: String,
+    pub message: String,
+    /// Label of a tab that has none of its own
+    pub tab: String,
+}
+
+impl Default for Strings {
+    fn default() -> Self {
+        Strings::built_in("en")
+    }
+}
+
+/// The strings for the current conversion; English until [`set`] is called.
+static STRINGS: RwLock<Option<Strings>> = RwLock::new(None);
+
+impl Strings {
+    /// Built-in translations by language code (`de`, `de-DE`, `de_AT`, ...),
+    /// falling back to English.
+    pub fn built_in(locale: &str) -> Self {
+        let language = locale.split(|c| c == '-' || c == '_').next().unwrap_or("");
+        let values = match language {
+            "de" => [
+                "Dokumentation",
+                "Abbildung",
+                "Tabelle",
+                "Abbildungsverzeichnis",
+                "Tabellenverzeichnis",
+                "Anhang",
+                "Links",
+                "Anmerkungen",
+                "Änderungshistorie",
+                "Offene Punkte",
+                "Unterschrift",
+                "Datum",
+                "Abschnitt",
+                "Erledigt",
+                "Gesamt",
+                "Fortschritt",
+                "Ort",
+                "Punkt",
+                "Commit",
+                "Autor",
+                "Nachricht",
+                "Tab",
+            ],
+            "fr" => [
+                "Documentation",
+                "Figure",
+                "Tableau",
+                "Table des figures",
+                "Liste des tableaux",
+                "Annexe",
+                "Liens",
+                "Notes",
+                "Historique des révisions",
+                "Points ouverts",
+                "Signature",
+                "Date",
+                "Section",
+                "Terminé",
+                "Total",
+                "Progression",
+                "Emplacement",
+                "Élément",
+                "Commit",
+                "Auteur",
+                "Message",
+                "Onglet",
+            ],
+            "es" => [
+                "Documentación",
+                "Figura",
+                "Tabla",
+                "Índice de figuras",
+                "Índice de tablas",
+                "Apéndice",
+                "Enlaces",
+                "Notas",
+                "Historial de revisiones",
+                "Puntos abiertos",
+                "Firma",
+                "Fecha",
+                "Sección",
+                "Hecho",
+                "Total",
+                "Progreso",
+                "Ubicación",
+                "Elemento",
+                "Commit",
+                "Autor",
+                "Mensaje",
+                "Pestaña",
+            ],
+            _ => [
+                "Documentation",
+                "Figure",
+                "Table",
+                "List of Figures",
+                "List of Tables",
+                "Appendix",
+                "Links",
+                "Notes",
+                "Revision History",
+                "Open Items",
+                "Signature",
+                "Date",
+                "Section",
+                "Done",
+                "Total",
+                "Progress",
+                "Location",
+                "Item",
+                "Commit",
+                "Author",
+                "Message",
+                "Tab",
+            ],
+        };
+        let [documentation, figure, table, list_of_figures, list_of_tables, appendix, links, notes, revision_history, open_items, signature, date, section, done, total, progress, location, item, commit, author, message, tab] =
+            values.map(String::from);
+        Strings {
+            documentation,
+            figure,
+            table,
+            list_of_figures,
+            list_of_tables,
+            appendix,
+            links,
+            notes,
+            revision_history,
+            open_items,
+            signature,
+            date,
+            section,
+            done,
+            total,
+            progress,
+            location,
+            item,
+            commit,
+            author,
+            message,
+            tab,
+        }
+    }
+
+    /// Replaces strings with the `[strings]` entries from md2pdf.toml.
+    pub fn with_overrides(mut self, overrides: &HashMap<String, String>) -> anyhow::Result<Self> {
+        for (key, value) in overrides {
+            let slot = match key.as_str() {
+                "documentation" => &mut self.documentation,
+                "figure" => &mut self.figure,
+                "table" => &mut self.table,
+                "list_of_figures" => &mut self.list_of_figures,
+                "list_of_tables" => &mut self.list_of_tables,
+                "appendix" => &mut self.appendix,
+                "links" => &mut self.links,
+                "notes" => &mut self.notes,
+                "revision_history" => &mut self.revision_history,
+                "open_items" => &mut self.open_items,
+                "signature" => &mut self.signature,
+                "date" => &mut self.date,
+                "section" => &mut self.section,
+                "done" => &mut self.done,
+                "total" => &mut self.total,
+                "progress" => &mut self.progress,
+                "location" => &mut self.location,
+                "item" => &mut self.item,
+                "commit" => &mut self.commit,
+                "author" => &mut self.author,
+                "message" => &mut self.message,
+                "tab" => &mut self.tab,
+                _ => anyhow::bail!("Unknown string in [strings]: {}", key),
+            };
+            *slot = value.clone();
+        }
+        Ok(self)
+    }
+}
+
+pub fn set(strings: Strings) {
+    *STRINGS
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(strings);
+}
+
+pub fn get() -> Strings {
+    STRINGS
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
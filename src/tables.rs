@@ -0,0 +1,329 @@
+use clap::ValueEnum;
+use pulldown_cmark::{Event, Tag};
+
+/// How the browser sizes table columns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum TableLayout {
+    /// Size columns by their content (long cells can squeeze the others)
+    #[default]
+    Auto,
+    /// Share the page width evenly, or by `<!-- widths: ... -->`, and wrap long content
+    Fixed,
+}
+
+pub const FIXED_LAYOUT_CSS: &str = r#"
+        table { table-layout: fixed; }
+        th, td { overflow-wrap: anywhere; }"#;
+
+/// A table look, for every table (`--table-style`) or one table
+/// (`<!-- table: striped compact -->`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TableStyle {
+    /// Shade every other row
+    Striped,
+    /// Less padding and smaller text, for dense reference tables
+    Compact,
+    /// Lines around every cell (the default look)
+    Bordered,
+    /// Only a rule under the header row
+    Borderless,
+}
+
+impl TableStyle {
+    fn name(self) -> &'static str {
+        match self {
+            TableStyle::Striped => "striped",
+            TableStyle::Compact => "compact",
+            TableStyle::Bordered => "bordered",
+            TableStyle::Borderless => "borderless",
+        }
+    }
+
+    /// CSS for tables under `scope` (empty for all tables).
+    fn css(self, scope: &str) -> String {
+        match self {
+            TableStyle::Striped => format!(
+                "{s}tbody tr:nth-child(even) td {{ background-color: rgba(127, 127, 127, 0.08); }}",
+                s = scope
+            ),
+            TableStyle::Compact => format!(
+                "{s}table {{ font-size: 0.9em; }} {s}th, {s}td {{ padding: 2px 6px; }}",
+                s = scope
+            ),
+            TableStyle::Bordered => {
+                format!("{s}th, {s}td {{ border: 1px solid #ddd; }}", s = scope)
+            }
+            TableStyle::Borderless => format!(
+                "{s}th, {s}td {{ border: none; }} {s}thead th {{ border-bottom: 2px solid #ddd; }}",
+                s = scope
+            ),
+        }
+    }
+}
+
+/// CSS for the `--table-style` defaults plus every per-table style, which
+/// wins over the defaults by being more specific.
+pub fn style_css(defaults: &[TableStyle]) -> String {
+    let mut css: Vec<String> = defaults.iter().map(|style| style.css("")).collect();
+    for style in TableStyle::value_variants() {
+        css.push(style.css(&format!(".table-{} ", style.name())));
+    }
+    css.join("\n        ")
+}
+
+pub const WIDTHS: &str = "<!-- widths:";
+pub const STYLE: &str = "<!-- table:";
+
+/// Styles from `<!-- table: striped compact -->`.
+fn parse_styles(comment: &str) -> Option<Vec<TableStyle>> {
+    comment
+        .trim()
+        .strip_prefix(STYLE)?
+        .strip_suffix("-->")?
+        .split_whitespace()
+        .map(|name| TableStyle::from_str(name, true).ok())
+        .collect()
+}
+
+/// Column widths from `<!-- widths: 20% 50% 30% -->` (any CSS lengths) or
+/// `<!-- widths: 2 5 3 -->` (relative shares).
+fn parse_widths(comment: &str) -> Option<Vec<String>> {
+    let values: Vec<&str> = comment
+        .trim()
+        .strip_prefix(WIDTHS)?
+        .strip_suffix("-->")?
+        .split_whitespace()
+        .collect();
+    if values.is_empty() {
+        return None;
+    }
+
+    let shares: Option<Vec<f64>> = values.iter().map(|value| value.parse().ok()).collect();
+    Some(match shares {
+        Some(shares) => {
+            let total: f64 = shares.iter().sum();
+            if total <= 0.0 {
+                return None;
+            }
+            shares
+                .iter()
+                .map(|share| format!("{:.2}%", share / total * 100.0))
+                .collect()
+        }
+        None => values.iter().map(|value| value.replace('"', "")).collect(),
+    })
+}
+
+/// Applies `<!-- widths: ... -->` and `<!-- table: ... -->` comments to the
+/// next table: widths become a `<colgroup>`, so columns keep them instead
+/// of being sized by their content, and styles a wrapper's classes.
+pub fn apply_table_comments(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut result = Vec::with_capacity(events.len());
+    let mut pending: Option<Vec<String>> = None;
+    let mut pending_styles: Option<Vec<TableStyle>> = None;
+    let mut wrapped = false;
+
+    for event in events {
+        match event {
+            Event::Html(ref html) if html.trim_start().starts_with(WIDTHS) => {
+                // Consumed here even when malformed, so the hint never reaches the page
+                match parse_widths(html) {
+                    Some(widths) => pending = Some(widths),
+                    None => {
+                        crate::warnings::warn(format!("Invalid column widths: {}", html.trim()))
+                    }
+                }
+            }
+            Event::Html(ref html) if html.trim_start().starts_with(STYLE) => {
+                match parse_styles(html) {
+                    Some(styles) => pending_styles = Some(styles),
+                    None => crate::warnings::warn(format!("Invalid table style: {}", html.trim())),
+                }
+            }
+            Event::Start(Tag::Table(_)) => {
+                if let Some(styles) = pending_styles.take() {
+                    let classes: Vec<String> = styles
+                        .iter()
+                        .map(|style| format!("table-{}", style.name()))
+                        .collect();
+                    result.push(Event::Html(
+                        format!("<div class=\"{}\">", classes.join(" ")).into(),
+                    ));
+                    wrapped = true;
+                }
+                result.push(event);
+                if let Some(widths) = pending.take() {
+                    let mut colgroup = String::from("<colgroup>");
+                    for width in widths {
+                        colgroup.push_str(&format!("<col style=\"width: {}\">", width));
+                    }
+                    colgroup.push_str("</colgroup>");
+                    result.push(Event::Html(colgroup.into()));
+                }
+            }
+            Event::End(Tag::Table(_)) => {
+                result.push(event);
+                if wrapped {
+                    result.push(Event::Html("</div>\n".into()));
+                    wrapped = false;
+                }
+            }
+            other => result.push(other),
+        }
+    }
+
+    result
+}
+
+/// Table tags whose legacy presentational attributes are dropped.
+const TABLE_TAGS: [&str; 7] = ["table", "thead", "tbody", "tfoot", "tr", "th", "td"];
+
+/// Attributes that would override the theme (`border="1"`, `bgcolor`, ...).
+const PRESENTATIONAL: [&str; 8] = [
+    "border",
+    "cellpadding",
+    "cellspacing",
+    "bgcolor",
+    "width",
+    "height",
+    "frame",
+    "rules",
+];
+
+/// Splits the attributes of a tag into `(name, value)` pairs.
+fn attributes(text: &str) -> Vec<(String, Option<String>)> {
+    let mut found = Vec::new();
+    let mut rest = text.trim_start();
+
+    while !rest.is_empty() {
+        let name_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=')
+            .unwrap_or(rest.len());
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+
+        let value = match rest.strip_prefix('=') {
+            Some(after) => {
+                let after = after.trim_start();
+                let (value, remaining) = match after.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let end = after[1..].find(quote).map_or(after.len(), |end| end + 1);
+                        (&after[1..end], after.get(end + 1..).unwrap_or(""))
+                    }
+                    _ => {
+                        let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                        (&after[..end], &after[end..])
+                    }
+                };
+                rest = remaining.trim_start();
+                Some(value.to_string())
+            }
+            None => None,
+        };
+        if !name.is_empty() {
+            found.push((name, value));
+        }
+    }
+
+    found
+}
+
+/// Rewrites a table tag without presentational attributes, turning
+/// `align`/`valign` into the equivalent inline style.
+fn normalize_tag(name: &str, attribute_text: &str) -> String {
+    let mut styles = Vec::new();
+    let mut kept = Vec::new();
+    for (attribute, value) in attributes(attribute_text) {
+        match (attribute.as_str(), value) {
+            ("align", Some(value)) if name != "table" => {
+                styles.push(format!("text-align: {}", value))
+            }
+            ("valign", Some(value)) => styles.push(format!("vertical-align: {}", value)),
+            ("style", Some(value)) => styles.insert(0, value.trim_end_matches(';').to_string()),
+            (attribute, _) if PRESENTATIONAL.contains(&attribute) || attribute == "align" => {}
+            (attribute, Some(value)) => kept.push(format!(
+                "{}=\"{}\"",
+                attribute,
+                value.replace('"', "&quot;")
+            )),
+            (attribute, None) => kept.push(attribute.to_string()),
+        }
+    }
+    if !styles.is_empty() {
+        kept.push(format!("style=\"{}\"", styles.join("; ").replace('"', "'")));
+    }
+
+    let mut tag = format!("<{}", name);
+    for attribute in kept {
+        tag.push(' ');
+        tag.push_str(&attribute);
+    }
+    tag.push('>');
+    tag
+}
+
+/// Moves a leading row of only `<th>` cells into a `<thead>`, so header
+/// styles apply to HTML tables written without one.
+fn add_header_section(html: &str) -> String {
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find("<table") {
+        let Some(open_end) = rest[start..].find('>').map(|end| start + end + 1) else {
+            break;
+        };
+        result.push_str(&rest[..open_end]);
+        rest = &rest[open_end..];
+
+        let body = rest.trim_start();
+        if body.starts_with("<tr") {
+            let leading = rest.len() - body.len();
+            if let Some(row_end) = body.find("</tr>").map(|end| end + "</tr>".len()) {
+                let row = &body[..row_end];
+                if row.contains("<th") && !row.contains("<td") && !row.contains("<table") {
+                    result.push_str(&rest[..leading]);
+                    result.push_str("<thead>");
+                    result.push_str(row);
+                    result.push_str("</thead>");
+                    rest = &body[row_end..];
+                }
+            }
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Normalizes HTML tables written directly in markdown so they look like
+/// markdown tables: presentational attributes (`border`, `cellpadding`,
+/// `bgcolor`, `width`, ...) that would override the theme are dropped,
+/// `align`/`valign` become styles, and a leading header row gets a
+/// `<thead>`. Markdown tables pass through unchanged.
+pub fn normalize_html_tables(html: &str) -> String {
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        result.push_str(&rest[..start]);
+        let tag = &rest[start..];
+        let name_end = tag[1..]
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .map_or(tag.len(), |end| end + 1);
+        let name = tag[1..name_end].to_ascii_lowercase();
+        match tag.find('>') {
+            Some(end) if TABLE_TAGS.contains(&name.as_str()) => {
+                let attribute_text = tag[name_end..end].trim_end_matches('/');
+                result.push_str(&normalize_tag(&name, attribute_text));
+                rest = &tag[end + 1..];
+            }
+            _ => {
+                result.push('<');
+                rest = &tag[1..];
+            }
+        }
+    }
+
+    result.push_str(rest);
+    add_header_section(&result)
+}
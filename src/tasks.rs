@@ -0,0 +1,118 @@
+use crate::strings;
+
+#[derive(Debug, Default)]
+pub struct TaskProgress {
+    pub section: String,
+    pub done: usize,
+    pub total: usize,
+}
+
+fn task_state(trimmed: &str) -> Option<bool> {
+    let rest = trimmed
+        .strip_prefix("- ")
+        .or_else(|| trimmed.strip_prefix("* "))
+        .or_else(|| trimmed.strip_prefix("+ "))?;
+
+    if rest.starts_with("[ ]") {
+        Some(false)
+    } else if rest.starts_with("[x]") || rest.starts_with("[X]") {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+/// Counts task list items per section. A new section starts at every H1/H2
+/// heading; tasks before the first heading belong to `default_section`.
+pub fn progress_by_section(markdown: &str, default_section: &str) -> Vec<TaskProgress> {
+    let mut sections = vec![TaskProgress {
+        section: default_section.to_string(),
+        ..Default::default()
+    }];
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+
+        if let Some(heading) = trimmed
+            .strip_prefix("# ")
+            .or_else(|| trimmed.strip_prefix("## "))
+        {
+            sections.push(TaskProgress {
+                section: heading.trim().to_string(),
+                ..Default::default()
+            });
+            continue;
+        }
+
+        if let (Some(done), Some(section)) = (task_state(trimmed), sections.last_mut()) {
+            section.total += 1;
+            if done {
+                section.done += 1;
+            }
+        }
+    }
+
+    sections.retain(|s| s.total > 0);
+    sections
+}
+
+/// Counts all task list items as a single section.
+pub fn progress(markdown: &str, section: &str) -> TaskProgress {
+    let mut total = TaskProgress {
+        section: section.to_string(),
+        ..Default::default()
+    };
+    for part in progress_by_section(markdown, section) {
+        total.done += part.done;
+        total.total += part.total;
+    }
+    total
+}
+
+fn percent(done: usize, total: usize) -> usize {
+    if total == 0 {
+        0
+    } else {
+        done * 100 / total
+    }
+}
+
+pub fn summary_table(rows: &[TaskProgress]) -> String {
+    if rows.is_empty() {
+        return String::new();
+    }
+
+    let strings = strings::get();
+    let mut table = format!(
+        "| {} | {} | {} | {} |\n|---|---:|---:|---:|\n",
+        strings.section, strings.done, strings.total, strings.progress
+    );
+    let (mut done, mut total) = (0, 0);
+    for row in rows {
+        table.push_str(&format!(
+            "| {} | {} | {} | {}% |\n",
+            row.section.replace('|', "\\|"),
+            row.done,
+            row.total,
+            percent(row.done, row.total)
+        ));
+        done += row.done;
+        total += row.total;
+    }
+    table.push_str(&format!(
+        "| **{}** | **{}** | **{}** | **{}%** |\n\n",
+        strings.total,
+        done,
+        total,
+        percent(done, total)
+    ));
+    table
+}
@@ -0,0 +1,51 @@
+use anyhow::{Context, Result};
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+/// A scratch directory under the system temp dir, removed when dropped.
+pub struct TempDir {
+    pub path: PathBuf,
+}
+
+/// A name suffix nobody can predict: std seeds every `RandomState` randomly.
+fn random_suffix() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    format!("{:016x}", hasher.finish())
+}
+
+impl TempDir {
+    /// Creates a fresh directory only this user can read. The name is random
+    /// and an existing directory (or symlink) is never reused, so other users
+    /// can't plant files or links in it.
+    pub fn new(prefix: &str) -> Result<Self> {
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+
+        let mut attempts = 0;
+        loop {
+            let path = std::env::temp_dir().join(format!("md2pdf-{}-{}", prefix, random_suffix()));
+            match builder.create(&path) {
+                Ok(()) => return Ok(TempDir { path }),
+                Err(err) if err.kind() == ErrorKind::AlreadyExists && attempts < 10 => {
+                    attempts += 1;
+                }
+                Err(err) => {
+                    return Err(err).with_context(|| {
+                        format!("Failed to create temporary directory: {:?}", path)
+                    })
+                }
+            }
+        }
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
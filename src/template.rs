@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+pub type Variables = BTreeMap<String, String>;
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Formats a timestamp with a strftime pattern, using the month and weekday
+/// names of `locale` (e.g. `de-DE`) when given.
+fn format_time(
+    time: chrono::DateTime<chrono::Local>,
+    format: &str,
+    default_format: &str,
+    locale: Option<&str>,
+) -> Option<String> {
+    let format = if format.is_empty() {
+        default_format
+    } else {
+        format
+    };
+    let locale = locale.and_then(|l| chrono::Locale::try_from(l.replace('-', "_").as_str()).ok());
+
+    // Invalid patterns surface as a formatting error rather than a panic
+    let mut formatted = String::new();
+    let result = match locale {
+        Some(locale) => write!(formatted, "{}", time.format_localized(format, locale)),
+        None => write!(formatted, "{}", time.format(format)),
+    };
+    result.ok().map(|_| formatted)
+}
+
+/// Where [`file_variables`] keeps the raw modification time for formatting.
+const MTIME_KEY: &str = "file.mtime.rfc3339";
+
+/// `{file.*}` variables for the input: its name, path, size in bytes, and
+/// modification time (`{file.mtime}`, or `{file.mtime:%d %B %Y}`).
+pub fn file_variables(path: &Path) -> Variables {
+    let mut variables = Variables::new();
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return variables;
+    };
+
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    variables.insert("file.name".to_string(), name);
+    variables.insert("file.path".to_string(), path.display().to_string());
+    variables.insert("file.size".to_string(), metadata.len().to_string());
+    if let Ok(modified) = metadata.modified() {
+        let modified: chrono::DateTime<chrono::Local> = modified.into();
+        variables.insert(MTIME_KEY.to_string(), modified.to_rfc3339());
+    }
+    variables
+}
+
+pub fn is_valid_locale(locale: &str) -> bool {
+    chrono::Locale::try_from(locale.replace('-', "_").as_str()).is_ok()
+}
+
+fn lookup(
+    key: &str,
+    variables: &Variables,
+    locale: Option<&str>,
+    allow_env: bool,
+) -> Option<String> {
+    let (name, format) = key.split_once(':').unwrap_or((key, ""));
+    match name {
+        "date" => format_time(chrono::Local::now(), format, "%Y-%m-%d", locale),
+        "now" => format_time(chrono::Local::now(), format, "%Y-%m-%d %H:%M", locale),
+        "env" if allow_env && !format.is_empty() => Some(std::env::var(format).unwrap_or_default()),
+        "file.mtime" => {
+            let modified = chrono::DateTime::parse_from_rfc3339(variables.get(MTIME_KEY)?).ok()?;
+            format_time(modified.into(), format, "%Y-%m-%d %H:%M", locale)
+        }
+        _ => variables.get(key).cloned(),
+    }
+}
+
+/// Replaces `{name}` placeholders in an HTML template with escaped variable
+/// values, `{date}`/`{date:%d %B %Y}` and `{now}`/`{now:%H:%M}` with the
+/// current date and time, and `{env:NAME}` with an environment variable
+/// (empty when unset). Unknown placeholders are left untouched so CSS braces
+/// survive.
+pub fn render(template: &str, variables: &Variables, locale: Option<&str>) -> String {
+    substitute(template, variables, locale, escape_html, true)
+}
+
+/// Like [`render`], but leaves substituted values as they are, for text that
+/// is escaped where it is finally output (the document title).
+pub fn render_plain(template: &str, variables: &Variables, locale: Option<&str>) -> String {
+    substitute(template, variables, locale, str::to_string, true)
+}
+
+/// Like [`render_plain`], for text written in the document itself (letter
+/// frontmatter): `{env:NAME}` is left as is, so converting someone else's
+/// files can't print the environment into them.
+pub fn render_document(template: &str, variables: &Variables, locale: Option<&str>) -> String {
+    substitute(template, variables, locale, str::to_string, false)
+}
+
+fn substitute(
+    template: &str,
+    variables: &Variables,
+    locale: Option<&str>,
+    escape: fn(&str) -> String,
+    allow_env: bool,
+) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        match after
+            .find('}')
+            .and_then(|end| lookup(&after[..end], variables, locale, allow_env).map(|v| (end, v)))
+        {
+            Some((end, value)) => {
+                result.push_str(&escape(&value));
+                rest = &after[end + 1..];
+            }
+            None => {
+                result.push('{');
+                rest = after;
+            }
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
@@ -0,0 +1,382 @@
+use crate::template::escape_html;
+use clap::ValueEnum;
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, Tag};
+
+const ESC: char = '\u{1b}';
+
+/// What happens to ANSI escape sequences in code blocks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum AnsiMode {
+    /// Render colors and bold/italic/underline as styled text
+    #[default]
+    Color,
+    /// Drop the sequences and keep the plain text
+    Strip,
+}
+
+const COLOR_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// The 16 terminal colors on a light background, then on a dark one.
+const LIGHT_PALETTE: [&str; 16] = [
+    "#24292f", "#cf222e", "#116329", "#7d4e00", "#0969da", "#8250df", "#1b7c83", "#6e7781",
+    "#57606a", "#a40e26", "#1a7f37", "#633c01", "#218bff", "#a475f9", "#3192aa", "#8c959f",
+];
+const DARK_PALETTE: [&str; 16] = [
+    "#484f58", "#ff7b72", "#3fb950", "#d29922", "#58a6ff", "#bc8cff", "#39c5cf", "#b1bac4",
+    "#6e7681", "#ffa198", "#56d364", "#e3b341", "#79c0ff", "#d2a8ff", "#56d4dd", "#f0f6fc",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Color {
+    /// One of the 16 themed colors
+    Palette(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    /// A color from the 256-color table: the 16 themed colors, a 6×6×6
+    /// cube, then 24 greys.
+    fn indexed(index: u8) -> Self {
+        match index {
+            0..=15 => Color::Palette(index),
+            16..=231 => {
+                let level = |value: u8| if value == 0 { 0 } else { 55 + value * 40 };
+                let cube = index - 16;
+                Color::Rgb(level(cube / 36), level(cube / 6 % 6), level(cube % 6))
+            }
+            _ => {
+                let grey = 8 + (index - 232) * 10;
+                Color::Rgb(grey, grey, grey)
+            }
+        }
+    }
+}
+
+fn palette_class(index: u8) -> String {
+    let name = COLOR_NAMES[index as usize % 8];
+    if index >= 8 {
+        format!("bright-{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// The text attributes set by SGR (`ESC[...m`) sequences so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Style {
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    underline: bool,
+    foreground: Option<Color>,
+    background: Option<Color>,
+}
+
+impl Style {
+    /// Applies the parameters of one SGR sequence; `ESC[m` resets.
+    fn apply(&mut self, parameters: &str) {
+        let mut codes = parameters
+            .split(';')
+            .map(|code| code.parse::<u16>().unwrap_or(0));
+        while let Some(code) = codes.next() {
+            match code {
+                0 => *self = Style::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                22 => {
+                    self.bold = false;
+                    self.dim = false;
+                }
+                23 => self.italic = false,
+                24 => self.underline = false,
+                30..=37 => self.foreground = Some(Color::Palette(code as u8 - 30)),
+                90..=97 => self.foreground = Some(Color::Palette(code as u8 - 90 + 8)),
+                39 => self.foreground = None,
+                40..=47 => self.background = Some(Color::Palette(code as u8 - 40)),
+                100..=107 => self.background = Some(Color::Palette(code as u8 - 100 + 8)),
+                49 => self.background = None,
+                38 | 48 => {
+                    // 38;5;N picks from the 256-color table, 38;2;R;G;B is true color
+                    let mut next = || codes.next().unwrap_or(0).min(255) as u8;
+                    let color = match next() {
+                        5 => Color::indexed(next()),
+                        2 => Color::Rgb(next(), next(), next()),
+                        _ => continue,
+                    };
+                    if code == 38 {
+                        self.foreground = Some(color);
+                    } else {
+                        self.background = Some(color);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn open_tag(&self) -> String {
+        let mut classes = Vec::new();
+        let mut styles = Vec::new();
+        for (set, class) in [
+            (self.bold, "ansi-bold"),
+            (self.dim, "ansi-dim"),
+            (self.italic, "ansi-italic"),
+            (self.underline, "ansi-underline"),
+        ] {
+            if set {
+                classes.push(class.to_string());
+            }
+        }
+        match self.foreground {
+            Some(Color::Palette(index)) => classes.push(format!("ansi-{}", palette_class(index))),
+            Some(Color::Rgb(r, g, b)) => {
+                styles.push(format!("color: #{:02x}{:02x}{:02x}", r, g, b))
+            }
+            None => {}
+        }
+        match self.background {
+            Some(Color::Palette(index)) => {
+                classes.push(format!("ansi-bg-{}", palette_class(index)))
+            }
+            Some(Color::Rgb(r, g, b)) => {
+                styles.push(format!("background-color: #{:02x}{:02x}{:02x}", r, g, b))
+            }
+            None => {}
+        }
+
+        let mut tag = String::from("<span");
+        if !classes.is_empty() {
+            tag.push_str(&format!(" class=\"{}\"", classes.join(" ")));
+        }
+        if !styles.is_empty() {
+            tag.push_str(&format!(" style=\"{}\"", styles.join("; ")));
+        }
+        tag.push('>');
+        tag
+    }
+}
+
+/// A line as the terminal showed it: text before a carriage return (a
+/// progress bar redrawing itself) is overwritten by what follows.
+fn visible_line(line: &str) -> &str {
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    line.rsplit('\r').next().unwrap_or(line)
+}
+
+/// Renders terminal output as HTML, with each run of styled text in a
+/// `<span>`. `style` carries over from the previous text of the same block.
+fn render(text: &str, style: &mut Style, mode: AnsiMode) -> String {
+    let mut html = String::with_capacity(text.len());
+    let mut run = String::new();
+
+    let flush = |html: &mut String, run: &mut String, style: &Style| {
+        if run.is_empty() {
+            return;
+        }
+        if mode == AnsiMode::Color && *style != Style::default() {
+            html.push_str(&style.open_tag());
+            html.push_str(&escape_html(run));
+            html.push_str("</span>");
+        } else {
+            html.push_str(&escape_html(run));
+        }
+        run.clear();
+    };
+
+    for line in text.split_inclusive('\n') {
+        let (line, newline) = match line.strip_suffix('\n') {
+            Some(line) => (line, "\n"),
+            None => (line, ""),
+        };
+        let mut chars = visible_line(line).chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != ESC {
+                run.push(c);
+                continue;
+            }
+            match chars.next() {
+                // CSI: parameters, then a final byte in @..~
+                Some('[') => {
+                    let mut parameters = String::new();
+                    let mut last = None;
+                    for c in chars.by_ref() {
+                        if ('@'..='~').contains(&c) {
+                            last = Some(c);
+                            break;
+                        }
+                        parameters.push(c);
+                    }
+                    if last == Some('m') {
+                        flush(&mut html, &mut run, style);
+                        style.apply(&parameters);
+                    }
+                }
+                // OSC (window titles, hyperlinks) ends with BEL or ESC \
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\u{7}' {
+                            break;
+                        }
+                        if c == ESC && chars.peek() == Some(&'\\') {
+                            chars.next();
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        run.push_str(newline);
+    }
+    flush(&mut html, &mut run, style);
+
+    html
+}
+
+/// Turns ANSI escape sequences in code blocks (terminal output pasted into a
+/// ```` ```console ```` block) into colored text, or strips them, instead of
+/// printing them as garbage.
+pub fn render_ansi(events: Vec<Event<'_>>, mode: AnsiMode) -> Vec<Event<'_>> {
+    let mut in_code_block = false;
+    let mut style = Style::default();
+
+    events
+        .into_iter()
+        .map(|event| match event {
+            Event::Start(Tag::CodeBlock(_)) => {
+                in_code_block = true;
+                style = Style::default();
+                event
+            }
+            Event::End(Tag::CodeBlock(_)) => {
+                in_code_block = false;
+                event
+            }
+            Event::Text(text)
+                if in_code_block && (text.contains(ESC) || style != Style::default()) =>
+            {
+                Event::Html(render(&text, &mut style, mode).into())
+            }
+            other => other,
+        })
+        .collect()
+}
+
+/// Fence languages of terminal transcripts, whose prompt lines are styled.
+const CONSOLE_LANGUAGES: [&str; 5] = [
+    "console",
+    "shell",
+    "shell-session",
+    "sh-session",
+    "terminal",
+];
+
+pub const PROMPT_CSS: &str = r#"
+        .console-command { font-weight: bold; }
+        .console-prompt { user-select: none; }
+        .console-output { opacity: 0.7; }"#;
+
+/// Splits a prompt line into its prompt (`$ `, `# `, or `user@host:~$ `)
+/// and the command after it.
+fn split_prompt(line: &str) -> Option<(&str, &str)> {
+    if line == "$" || line == "#" {
+        return Some((line, ""));
+    }
+    let end = ["$ ", "# "]
+        .iter()
+        .filter_map(|prompt| line.find(prompt))
+        .min()?;
+    let prefix = &line[..end];
+    let is_prompt =
+        prefix.is_empty() || (prefix.contains('@') && !prefix.contains(char::is_whitespace));
+    is_prompt.then(|| line.split_at(end + 2))
+}
+
+/// In console blocks, wraps prompts, the commands typed after them (with
+/// their `\` continuation lines), and output lines in spans so commands
+/// stand out from what they print.
+pub fn style_prompts(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut result = Vec::with_capacity(events.len());
+    let mut in_console = false;
+    let mut continues = false;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(ref info))) => {
+                let language = info.split_whitespace().next().unwrap_or("");
+                in_console = CONSOLE_LANGUAGES.contains(&language);
+                continues = false;
+                result.push(event);
+            }
+            Event::End(Tag::CodeBlock(_)) => {
+                in_console = false;
+                result.push(event);
+            }
+            Event::Text(text) if in_console => {
+                for line in text.split_inclusive('\n') {
+                    let (line, newline) = match line.strip_suffix('\n') {
+                        Some(line) => (line, "\n"),
+                        None => (line, ""),
+                    };
+                    let command = if continues {
+                        Some(("", line))
+                    } else {
+                        split_prompt(line)
+                    };
+                    match command {
+                        Some((prompt, command)) => {
+                            if !prompt.is_empty() {
+                                result.push(Event::Html("<span class=\"console-prompt\">".into()));
+                                result.push(Event::Text(CowStr::from(prompt.to_string())));
+                                result.push(Event::Html("</span>".into()));
+                            }
+                            result.push(Event::Html("<span class=\"console-command\">".into()));
+                            result.push(Event::Text(CowStr::from(command.to_string())));
+                            continues = command.trim_end().ends_with('\\');
+                        }
+                        None => {
+                            result.push(Event::Html("<span class=\"console-output\">".into()));
+                            result.push(Event::Text(CowStr::from(line.to_string())));
+                        }
+                    }
+                    result.push(Event::Html(format!("</span>{}", newline).into()));
+                }
+            }
+            other => result.push(other),
+        }
+    }
+
+    result
+}
+
+/// Colors for the classes [`render_ansi`] adds, following the page theme.
+pub fn css() -> String {
+    let mut css = String::from(
+        r#"
+        .ansi-bold { font-weight: bold; }
+        .ansi-dim { opacity: 0.7; }
+        .ansi-italic { font-style: italic; }
+        .ansi-underline { text-decoration: underline; }"#,
+    );
+    for (scope, palette) in [
+        ("", LIGHT_PALETTE),
+        ("html[data-theme=\"dark\"] ", DARK_PALETTE),
+        ("section.theme-dark ", DARK_PALETTE),
+        ("section.theme-light ", LIGHT_PALETTE),
+    ] {
+        for (index, color) in palette.iter().enumerate() {
+            let class = palette_class(index as u8);
+            css.push_str(&format!(
+                "\n        {s}.ansi-{c} {{ color: {v}; }} {s}.ansi-bg-{c} {{ background-color: {v}; }}",
+                s = scope,
+                c = class,
+                v = color
+            ));
+        }
+    }
+    css
+}
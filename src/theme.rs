@@ -0,0 +1,84 @@
+use crate::frontmatter::Frontmatter;
+use clap::ValueEnum;
+use std::process::Command;
+
+/// Environment variable that overrides the system colour scheme in auto mode.
+pub const THEME_ENV: &str = "MD2PDF_THEME";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DarkMode {
+    /// Light theme for everything
+    Off,
+    /// Dark theme for everything
+    On,
+    /// Follow the frontmatter `theme`, then MD2PDF_THEME, then the system colour scheme
+    Auto,
+}
+
+/// Parses `dark`/`light` (as used by `theme:` and MD2PDF_THEME).
+fn parse(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "dark" => Some(true),
+        "light" => Some(false),
+        _ => None,
+    }
+}
+
+/// The `theme: dark|light` a document declares in its frontmatter.
+pub fn from_frontmatter(frontmatter: &Frontmatter) -> Option<bool> {
+    frontmatter.get("theme").and_then(parse)
+}
+
+/// Whether the desktop prefers a dark colour scheme (macOS or GNOME).
+fn system_prefers_dark() -> bool {
+    let output = |program: &str, args: &[&str]| {
+        Command::new(program)
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).to_lowercase())
+    };
+
+    if cfg!(target_os = "macos") {
+        return output("defaults", &["read", "-g", "AppleInterfaceStyle"])
+            .map_or(false, |style| style.contains("dark"));
+    }
+    output(
+        "gsettings",
+        &["get", "org.gnome.desktop.interface", "color-scheme"],
+    )
+    .map_or(false, |scheme| scheme.contains("dark"))
+}
+
+/// Decides whether a document renders dark. An explicit `--dark-mode` or
+/// `--dark-mode off` wins; otherwise the frontmatter decides, and in auto
+/// mode the environment and system colour scheme are consulted last.
+pub fn is_dark(mode: Option<DarkMode>, document: Option<bool>) -> bool {
+    match mode {
+        Some(DarkMode::On) => true,
+        Some(DarkMode::Off) => false,
+        None => document.unwrap_or(false),
+        Some(DarkMode::Auto) => document
+            .or_else(|| std::env::var(THEME_ENV).ok().as_deref().and_then(parse))
+            .unwrap_or_else(system_prefers_dark),
+    }
+}
+
+/// Class added to a combined file's `<section>` for its frontmatter `theme`.
+pub fn section_class(frontmatter: &Frontmatter) -> &'static str {
+    match from_frontmatter(frontmatter) {
+        Some(true) => " theme-dark",
+        Some(false) => " theme-light",
+        None => "",
+    }
+}
+
+/// Styles for sections whose frontmatter picks a theme other than the document's.
+pub const SECTION_CSS: &str = r#"
+        section.theme-dark { background-color: #1a1a1a; color: #e0e0e0; }
+        section.theme-dark pre, section.theme-dark code { background-color: #2d2d2d; }
+        section.theme-dark th { background-color: #3a3a3a; }
+        section.theme-light { background-color: white; color: black; }
+        section.theme-light pre, section.theme-light code { background-color: #f5f5f5; }
+        section.theme-light th { background-color: #f9f9f9; }"#;
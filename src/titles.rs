@@ -0,0 +1,110 @@
+use crate::frontmatter::Frontmatter;
+use clap::ValueEnum;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SectionTitles {
+    /// Use the file name as-is
+    #[default]
+    Filename,
+    /// Prettify the file name: strip numeric prefixes, split words, title-case
+    Pretty,
+    /// Use the file's first heading
+    Heading,
+    /// Use the `title` frontmatter field
+    Frontmatter,
+    /// Promote the file's first H1 to the section heading and drop it from the body
+    Promote,
+}
+
+/// Turns a file stem like `02_api_reference-v2` into `Api Reference V2`.
+pub fn prettify(stem: &str) -> String {
+    let without_prefix = stem
+        .trim_start_matches(|c: char| c.is_ascii_digit())
+        .trim_start_matches(['-', '_', '.', ' ']);
+    let source = if without_prefix.is_empty() {
+        stem
+    } else {
+        without_prefix
+    };
+
+    source
+        .split(['-', '_', ' '])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+pub fn first_heading(markdown: &str) -> Option<String> {
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block || !trimmed.starts_with('#') {
+            continue;
+        }
+
+        let text = trimmed.trim_start_matches('#');
+        if text.starts_with(' ') && !text.trim().is_empty() {
+            return Some(text.trim().trim_end_matches('#').trim().to_string());
+        }
+    }
+
+    None
+}
+
+/// Removes the first `# H1` line outside code blocks, returning its text
+/// and the remaining markdown.
+pub fn take_first_h1(markdown: &str) -> Option<(String, String)> {
+    let mut in_code_block = false;
+    let mut offset = 0;
+
+    for line in markdown.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+        } else if !in_code_block {
+            if let Some(text) = trimmed.strip_prefix("# ") {
+                let title = text.trim().trim_end_matches('#').trim().to_string();
+                let rest = format!(
+                    "{}{}",
+                    &markdown[..offset],
+                    &markdown[offset + line.len()..]
+                );
+                return Some((title, rest));
+            }
+        }
+        offset += line.len();
+    }
+
+    None
+}
+
+pub fn section_title(
+    name: &str,
+    content: &str,
+    frontmatter: &Frontmatter,
+    mode: SectionTitles,
+) -> String {
+    match mode {
+        SectionTitles::Filename => name.to_string(),
+        SectionTitles::Pretty => prettify(name),
+        SectionTitles::Heading | SectionTitles::Promote => {
+            first_heading(content).unwrap_or_else(|| name.to_string())
+        }
+        SectionTitles::Frontmatter => frontmatter
+            .get("title")
+            .map(str::to_string)
+            .unwrap_or_else(|| name.to_string()),
+    }
+}
@@ -0,0 +1,67 @@
+use crate::strings;
+
+pub struct OpenItem {
+    pub location: String,
+    pub text: String,
+}
+
+fn open_item_text(trimmed: &str) -> Option<&str> {
+    for marker in ["- [ ] ", "* [ ] ", "+ [ ] "] {
+        if let Some(text) = trimmed.strip_prefix(marker) {
+            return Some(text);
+        }
+    }
+
+    ["TODO:", "FIXME:"]
+        .iter()
+        .find_map(|marker| trimmed.find(marker).map(|start| &trimmed[start..]))
+}
+
+/// Records TODO/FIXME markers and unchecked task items in `markdown`,
+/// tagging each line with an anchor the "Open Items" appendix links to.
+pub fn collect_open_items(markdown: &str, source: &str, items: &mut Vec<OpenItem>) -> String {
+    let mut result = String::new();
+    let mut in_code_block = false;
+
+    for (index, line) in markdown.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+
+        result.push_str(line);
+        if !in_code_block {
+            if let Some(text) = open_item_text(trimmed) {
+                items.push(OpenItem {
+                    location: format!("{}:{}", source, index + 1),
+                    text: text.to_string(),
+                });
+                result.push_str(&format!(" <a id=\"open-item-{}\"></a>", items.len()));
+            }
+        }
+        result.push('\n');
+    }
+
+    result
+}
+
+pub fn open_items_section(items: &[OpenItem]) -> String {
+    if items.is_empty() {
+        return String::new();
+    }
+
+    let strings = strings::get();
+    let mut section = format!(
+        "\n\n# {}\n\n| {} | {} |\n|---|---|\n",
+        strings.open_items, strings.location, strings.item
+    );
+    for (index, item) in items.iter().enumerate() {
+        section.push_str(&format!(
+            "| [{}](#open-item-{}) | {} |\n",
+            item.location,
+            index + 1,
+            item.text.replace('|', "\\|")
+        ));
+    }
+    section
+}
@@ -0,0 +1,151 @@
+use crate::images;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Warnings reported so far; `--strict` fails the build when there are any.
+static WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+/// How many of those warnings are broken links, which fail with their own exit code.
+static BROKEN_LINKS: AtomicUsize = AtomicUsize::new(0);
+
+/// Fence languages that highlighters commonly know; anything else is
+/// probably a typo.
+const KNOWN_LANGUAGES: &[&str] = &[
+    "abc",
+    "ansi",
+    "bash",
+    "c",
+    "cmd",
+    "console",
+    "cpp",
+    "cs",
+    "csharp",
+    "css",
+    "csv",
+    "diff",
+    "docker",
+    "dockerfile",
+    "elixir",
+    "erlang",
+    "geojson",
+    "go",
+    "gradle",
+    "graphql",
+    "groovy",
+    "haskell",
+    "hcl",
+    "html",
+    "ini",
+    "java",
+    "javascript",
+    "js",
+    "json",
+    "json5",
+    "jsx",
+    "kotlin",
+    "kt",
+    "latex",
+    "lua",
+    "makefile",
+    "markdown",
+    "md",
+    "mermaid",
+    "nginx",
+    "objc",
+    "ocaml",
+    "patch",
+    "perl",
+    "php",
+    "plaintext",
+    "powershell",
+    "proto",
+    "ps1",
+    "py",
+    "python",
+    "r",
+    "rb",
+    "ruby",
+    "rust",
+    "rs",
+    "scala",
+    "scss",
+    "sh-session",
+    "sh",
+    "shell",
+    "shell-session",
+    "sql",
+    "swift",
+    "terraform",
+    "terminal",
+    "text",
+    "toml",
+    "topojson",
+    "ts",
+    "tsx",
+    "txt",
+    "typescript",
+    "vim",
+    "xml",
+    "yaml",
+    "yml",
+    "zsh",
+];
+
+pub fn warn(message: impl Into<String>) {
+    let message = message.into();
+    eprintln!("⚠️  {}", message);
+    WARNINGS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .push(message);
+}
+
+/// Warns about a link whose target doesn't exist.
+pub fn broken_link(message: impl Into<String>) {
+    BROKEN_LINKS.fetch_add(1, Ordering::Relaxed);
+    warn(message);
+}
+
+pub fn broken_link_count() -> usize {
+    BROKEN_LINKS.load(Ordering::Relaxed)
+}
+
+pub fn count() -> usize {
+    WARNINGS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .len()
+}
+
+/// Warns about local images that don't exist and fence languages that
+/// aren't in [`KNOWN_LANGUAGES`].
+pub fn check_markdown(markdown: &str, base_dir: &Path, source: &str) {
+    images::rewrite_image_targets(markdown, |target| {
+        if !images::is_remote(target) {
+            let path = base_dir.join(images::percent_decode(target));
+            if !path.exists() {
+                warn(format!("{}: missing image {}", source, target));
+            }
+        }
+        None
+    });
+
+    let mut in_code_block = false;
+    for line in markdown.lines() {
+        let Some(info) = line.trim().strip_prefix("```") else {
+            continue;
+        };
+        in_code_block = !in_code_block;
+        let language = info
+            .split(|c: char| c.is_whitespace() || c == '{' || c == ',')
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+        if in_code_block && !language.is_empty() && !KNOWN_LANGUAGES.contains(&language.as_str()) {
+            warn(format!(
+                "{}: unknown code block language {}",
+                source, language
+            ));
+        }
+    }
+}
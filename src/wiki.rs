@@ -0,0 +1,117 @@
+use crate::FileGroups;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Wiki chrome pages rendered around every page on GitHub, not content.
+const SPECIAL_PAGES: [&str; 3] = ["_Sidebar", "_Footer", "_Header"];
+
+/// Whether the input looks like a GitHub wiki clone.
+pub fn detect(root: &Path) -> bool {
+    SPECIAL_PAGES
+        .iter()
+        .any(|page| root.join(format!("{}.md", page)).is_file())
+}
+
+/// GitHub matches page names case-insensitively, with spaces and hyphens
+/// interchangeable.
+fn normalize(page: &str) -> String {
+    page.trim()
+        .trim_end_matches(".md")
+        .to_lowercase()
+        .replace(' ', "-")
+}
+
+/// Page names referenced by `[[Page]]`, `[[Text|Page]]` and `[Text](Page)`
+/// links, in order of appearance.
+fn linked_pages(markdown: &str) -> Vec<String> {
+    let mut pages = Vec::new();
+    for part in markdown.split("[[").skip(1) {
+        if let Some((link, _)) = part.split_once("]]") {
+            let target = link.rsplit('|').next().unwrap_or(link);
+            pages.push(normalize(target));
+        }
+    }
+    for part in markdown.split("](").skip(1) {
+        if let Some((target, _)) = part.split_once(')') {
+            if !target.contains("://") {
+                pages.push(normalize(target.split('#').next().unwrap_or(target)));
+            }
+        }
+    }
+    pages
+}
+
+/// Rewrites `[[Page Title]]` and `[[Link text|Page Title]]` into links to
+/// the section of the matching page.
+pub fn resolve_links(markdown: &str, sections: &HashMap<String, String>) -> String {
+    let mut result = String::new();
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        if line.trim().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block || !line.contains("[[") {
+            result.push_str(line);
+            result.push('\n');
+            continue;
+        }
+
+        let mut rest = line;
+        while let Some(start) = rest.find("[[") {
+            let Some(end) = rest[start..].find("]]") else {
+                break;
+            };
+            let link = &rest[start + 2..start + end];
+            let (text, page) = link.split_once('|').unwrap_or((link, link));
+            result.push_str(&rest[..start]);
+            match sections.get(&normalize(page)) {
+                Some(id) => result.push_str(&format!("[{}](#{})", text.trim(), id)),
+                None => result.push_str(text.trim()),
+            }
+            rest = &rest[start + end + 2..];
+        }
+        result.push_str(rest);
+        result.push('\n');
+    }
+
+    result
+}
+
+/// Drops the sidebar/footer/header pages and orders the remaining pages by
+/// their first appearance in `_Sidebar.md` (or `Home` first without one).
+/// Returns a map from page name to section id for [`resolve_links`].
+pub fn apply_sidebar_order(root: &Path, groups: &mut FileGroups) -> HashMap<String, String> {
+    let sidebar = fs::read_to_string(root.join("_Sidebar.md"))
+        .map(|content| linked_pages(&content))
+        .unwrap_or_else(|_| vec!["home".to_string()]);
+    let rank = |name: &str| {
+        sidebar
+            .iter()
+            .position(|page| *page == normalize(name))
+            .unwrap_or(usize::MAX)
+    };
+
+    for (_, files) in groups.iter_mut() {
+        files.retain(|file| !SPECIAL_PAGES.contains(&file.name.as_str()));
+        files.sort_by(|a, b| {
+            rank(&a.name)
+                .cmp(&rank(&b.name))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+    }
+    groups.retain(|(_, files)| !files.is_empty());
+    groups.sort_by_key(|(_, files)| files.iter().map(|f| rank(&f.name)).min());
+
+    groups
+        .iter()
+        .flat_map(|(_, files)| files)
+        .map(|file| {
+            (
+                normalize(&file.name),
+                crate::section_id(&file.relative_path),
+            )
+        })
+        .collect()
+}